@@ -0,0 +1,18 @@
+import { AreaPropertySummary } from './property'
+
+// Number of schools with each Ofsted rating.
+export interface SchoolRatingSplit {
+  outstanding: number,
+  good: number,
+  requiresImprovement: number,
+  inadequate: number,
+  unknown: number
+}
+
+export interface BoroughSummary {
+  code: string,
+  name: string,
+  stations: string[], // postcodes
+  schools: SchoolRatingSplit,
+  property: AreaPropertySummary[] // for each action and number of beds
+}
@@ -0,0 +1,13 @@
+// Monthly cost of living near a station, with housing at the median rent or asking price.
+export interface CostOfLivingSummary {
+  postcode: string,
+  coordinates: [number, number], // [longitude, latitude]
+  numBeds: number,
+  boroughCode: string, // GSS code
+  borough: string,
+  travelcardZones: [number, number], // from and to
+  monthlyHousing: number, // rent, or if buying mortgage, service charge and ground rent
+  monthlyCouncilTax: number,
+  monthlyTravelcard: number,
+  monthlyTotal: number
+}
@@ -1,5 +1,11 @@
 export interface LastUpdated {
   property?: number, // unix milliseconds
   schools?: number, // unix milliseconds
-  tube?: number // unix milliseconds
+  tube?: number, // unix milliseconds
+  soldPrices?: number, // unix milliseconds
+  epc?: number, // unix milliseconds
+  isochrones?: number, // unix milliseconds
+  postcodes?: number, // unix milliseconds
+  boundaries?: number, // unix milliseconds
+  costOfLiving?: number // unix milliseconds
 }
@@ -0,0 +1,6 @@
+export interface Postcode {
+  _id: string; // postcode, e.g. N1 9AL
+  outcode: string; // district, e.g. N1
+  sector: string; // e.g. N1 9
+  coordinates: [number, number]; // [longitude, latitude]
+}
@@ -9,15 +9,90 @@ export interface Stats {
   median: number,
   q3: number,
   max: number,
-  count: number
+  count: number, // after trimming
+  mean: number,
+  stdDev: number,
+  percentiles: Percentile[],
+  histogram: Histogram,
+  trimming: Trimming,
+  numTrimmed: number,
+  medianCi?: ConfidenceInterval, // absent if not bootstrapped
+  reliable: boolean // false if count is below the minimum
+}
+
+export interface ConfidenceInterval {
+  level: number, // e.g. 0.95
+  lower: number,
+  upper: number
+}
+
+export interface Percentile {
+  percentile: number, // between 0 and 1
+  value: number
+}
+
+export interface Histogram {
+  start: number,
+  binWidth: number,
+  counts: number[]
+}
+
+export type Trimming =
+  | { mode: 'none' }
+  | { mode: 'iqr', k: number }
+  | { mode: 'percentile', lower: number, upper: number }
+
+export enum PropertyType {
+  Flat = 'flat',
+  Maisonette = 'maisonette',
+  Terraced = 'terraced',
+  SemiDetached = 'semiDetached',
+  Detached = 'detached',
+  Bungalow = 'bungalow',
+  Other = 'other'
 }
 
 export interface PropertyStats {
   price: Stats,
   listedDays: Stats,
-  percentTransacted: Stats,
+  percentTransacted: number,
+  percentReduced: number,
   squareFeet: Stats,
-  rentalYield: Stats
+  squareFeetWithImputed: Stats,
+  pricePerSquareFoot: Stats,
+  pricePerSquareFootWithImputed: Stats,
+  percentSquareFeetImputed: number,
+  rentalYield: Stats,
+  tenure: TenureSplit,
+  serviceCharge: Stats,
+  groundRent: Stats,
+  epcBand: EpcBandSplit
+}
+
+export interface TenureSplit {
+  freehold: number,
+  leasehold: number,
+  shareOfFreehold: number,
+  commonhold: number,
+  unknown: number
+}
+
+export interface EpcBandSplit {
+  a: number,
+  b: number,
+  c: number,
+  d: number,
+  e: number,
+  f: number,
+  g: number,
+  unknown: number
+}
+
+export interface SearchCoverage {
+  resultCount: number,
+  retrieved: number,
+  numQueries: number,
+  numTruncated: number
 }
 
 export interface PropertySummary {
@@ -26,4 +101,158 @@ export interface PropertySummary {
   action: PropertyAction,
   numBeds: number,
   stats: PropertyStats,
+  statsByType: Partial<Record<PropertyType, PropertyStats>>,
+  coverage: SearchCoverage,
+  marketSpeed: MarketSpeed,
+  statsByDistance: DistanceRingStats[] // innermost ring first
+}
+
+// How much of an area was searched, from points sampled on a grid.
+export interface AreaCoverage {
+  numSamples: number, // sampled points in the area
+  numSearched: number // of those within a station search's radius
+}
+
+export interface AreaPropertySummary {
+  area: string, // e.g. N1, N1 9, or the borough GSS code
+  coordinates: [number, number], // centre of the listings
+  action: PropertyAction,
+  numBeds: number,
+  stats: PropertyStats,
+  statsByType: Partial<Record<PropertyType, PropertyStats>>,
+  coverage: AreaCoverage
+}
+
+export interface DistanceRingStats {
+  fromMiles: number, // inclusive
+  toMiles: number, // exclusive, except for the outermost ring
+  stats: PropertyStats
+}
+
+export interface MarketSpeed {
+  daysToAgree: Stats,
+  weeklyAbsorptionRate?: number,
+  monthsOfInventory?: number,
+  weeklyNewSupplyRate?: number,
+  numAvailable: number,
+  windowWeeks: number
+}
+
+export interface NetYieldSummary {
+  postcode: string,
+  coordinates: [number, number],
+  numBeds: number,
+  grossYield: Stats,
+  netYield: Stats
+}
+
+export interface AffordabilitySummary {
+  postcode: string,
+  coordinates: [number, number],
+  numBeds: number,
+  price: number,
+  stampDuty: number,
+  upfrontCost: number,
+  loan: number,
+  monthlyMortgage: number,
+  monthlyServiceCharge: number,
+  monthlyGroundRent: number,
+  monthlyBuyCost: number,
+  monthlyRent: number,
+  monthlyBuyMinusRent: number
+}
+
+export interface SoldPriceSummary {
+  postcode: string,
+  coordinates: [number, number],
+  months: number,
+  periodEnd: number, // unix milliseconds
+  stats: Stats,
+  statsByType: Partial<Record<PropertyType, Stats>>
+}
+
+export interface PriceDiscountSnapshot {
+  date: number, // unix milliseconds
+  postcode: string,
+  coordinates: [number, number],
+  propertyType: PropertyType | null, // null in older snapshots, for all property types
+  asking: Stats,
+  sold: Stats,
+  askingGap: number // 1 - median sold / median asking, sales of every size
+}
+
+export interface PriceDiscountTrend {
+  postcode: string,
+  coordinates: [number, number],
+  propertyType: PropertyType | null,
+  latest: PriceDiscountSnapshot,
+  trendPerMonth: number | null,
+  numSnapshots: number
+}
+
+export enum ListingEventKind {
+  New = 'new',
+  PriceChange = 'priceChange',
+  Reduced = 'reduced',
+  UnderOffer = 'underOffer',
+  SoldStc = 'soldStc',
+  LetAgreed = 'letAgreed',
+  Withdrawn = 'withdrawn',
+  Relisted = 'relisted'
+}
+
+export interface ListingEvent {
+  id: number,
+  postcode: string,
+  coordinates: [number, number],
+  action: PropertyAction,
+  numBeds: number,
+  date: number, // unix milliseconds
+  kind: ListingEventKind,
+  price: number,
+  previousPrice?: number,
+  listedDays?: number
+}
+
+export interface MarketHeat {
+  date: number, // unix milliseconds
+  postcode: string,
+  coordinates: [number, number],
+  action: PropertyAction,
+  numBeds: number,
+  score?: number,
+  newListingVelocity?: number,
+  percentReduced: number,
+  percentTransacted: number,
+  medianListedDays: number
+}
+
+export interface MarketHeatPoint {
+  date: number, // unix milliseconds
+  score?: number
+}
+
+export interface MarketHeatHistory {
+  latest: MarketHeat,
+  history: MarketHeatPoint[]
+}
+
+export type RollupLevel = 'zone' | 'line' | 'borough'
+
+export interface PropertyRollup {
+  level: RollupLevel,
+  area: string, // zone, line, or borough GSS code
+  action: PropertyAction,
+  numBeds: number,
+  stations: string[], // postcodes
+  stats: PropertyStats
+}
+
+export interface StationOverlap {
+  postcodes: [string, string],
+  action: PropertyAction,
+  numBeds: number,
+  numShared: number,
+  numListings: [number, number], // returned by each station's search
+  overlap: number // shared over listings returned by either
 }
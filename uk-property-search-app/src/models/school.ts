@@ -12,4 +12,7 @@ export interface School {
   coordinates?: [number, number];
   rating?: Rating;
   inspectionDateMs?: number; // unix milliseconds
+  borough?: string; // GSS code, absent outside the borough boundaries
+  ward?: string; // GSS code
+  lsoa?: string; // GSS code
 }
@@ -3,5 +3,15 @@ export interface TubeStation {
      zone: number[],
      postcode: string,
      coordinates: [number, number],
-     lines: string[]
+     lines: string[],
+     borough?: string, // GSS code, absent outside the borough boundaries
+     ward?: string, // GSS code
+     lsoa?: string // GSS code
+}
+
+// Properties of each feature in the /api/isochrones GeoJSON.
+export interface IsochroneProperties {
+     name: string,
+     postcode: string,
+     minutes: number
 }
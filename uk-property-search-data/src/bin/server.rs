@@ -1,16 +1,41 @@
 #[path = "../lib/mod.rs"]
 mod lib;
 
+use chrono::Utc;
 use flate2::{read::GzEncoder, Compression};
-use lib::property::property::PropertySummary;
+use lib::finance::{
+    affordability::{AffordabilityAssumptions, AffordabilitySummary},
+    cost_of_living::{CostOfLivingAssumptions, CostOfLivingSummary},
+    net_yield::{NetYieldSummary, YieldAssumptions},
+    stamp_duty::{StampDutyRates, StampDutyTable},
+};
+use lib::geography::{borough::BoroughSummary, boundary::BoundaryLevel};
+use lib::postcode::{normalise, prefix_regex, Postcode};
+use lib::property::{
+    aggregator::PropertyAggregator,
+    area::{AreaLevel, AreaPropertySummary},
+    assignment::StationOverlap,
+    discount::PriceDiscountTrend,
+    heat::MarketHeatHistory,
+    lifecycle::ListingEvent,
+    property::PropertySummary,
+    rollup::{PropertyRollup, RollupLevel},
+    sold_price::SoldPriceSummary,
+};
+use lib::routing::isochrone::StationIsochrone;
 use lib::school::School;
 use lib::tube::TubeStation;
 use lib::util::{db::LastUpdated, ext::MongoCollectionExt, globals::Globals};
+use log::warn;
+use mongodb::{bson::doc, options::FindOptions};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::fs::FileServer;
+use rocket::http::Status;
+use rocket::response::{status::Custom, Debug};
 use rocket::serde::json::Json;
 use rocket::{Config, State};
 use rocket::{Request, Response};
+use serde_json::Value;
 use std::env;
 use std::io::{Cursor, Read};
 use std::net::Ipv4Addr;
@@ -18,24 +43,288 @@ use std::net::Ipv4Addr;
 #[macro_use]
 extern crate rocket;
 
+// Autocomplete suggestions, between 1 and this many.
+const MAX_POSTCODE_SUGGESTIONS: i64 = 50;
+
 #[get("/property")]
 async fn property(state: &State<Globals>) -> Json<Vec<PropertySummary>> {
     let property = state.inner().db.property().find_to_vec().await;
     Json(property)
 }
 
+#[get("/property/rollup?<by>")]
+async fn property_rollup(
+    state: &State<Globals>,
+    by: RollupLevel,
+) -> Result<Json<Vec<PropertyRollup>>, Debug<anyhow::Error>> {
+    let globals = state.inner();
+    let (tube_stations, sketches) = (
+        globals.db.tube().find_to_vec().await,
+        globals.db.property_sketches().find_to_vec().await,
+    );
+    let aggregator = PropertyAggregator::new(globals)?;
+    Ok(Json(PropertyRollup::from_sketches(
+        &aggregator,
+        by,
+        &tube_stations,
+        &sketches,
+    )))
+}
+
+#[get("/property/areas?<level>")]
+async fn property_areas(
+    state: &State<Globals>,
+    level: AreaLevel,
+) -> Json<Vec<AreaPropertySummary>> {
+    let property = state.inner().db.area_property(level).find_to_vec().await;
+    Json(property)
+}
+
+#[get("/sold-prices")]
+async fn sold_prices(state: &State<Globals>) -> Json<Vec<SoldPriceSummary>> {
+    let sold_prices = state.inner().db.sold_prices().find_to_vec().await;
+    Json(sold_prices)
+}
+
+#[get("/price-discounts")]
+async fn price_discounts(state: &State<Globals>) -> Json<Vec<PriceDiscountTrend>> {
+    let snapshots = state.inner().db.price_discounts().find_to_vec().await;
+    Json(PriceDiscountTrend::from_snapshots(snapshots))
+}
+
+#[get("/events?<postcode>&<since>")]
+async fn events(
+    state: &State<Globals>,
+    postcode: Option<String>,
+    since: Option<i64>, // unix milliseconds
+) -> Json<Vec<ListingEvent>> {
+    let mut filter = doc! {};
+    if let Some(postcode) = postcode {
+        filter.insert("postcode", postcode);
+    }
+    if let Some(since) = since {
+        filter.insert("date", doc! {"$gte": since});
+    }
+    let events = state
+        .inner()
+        .db
+        .listing_events()
+        .find_filtered_to_vec(
+            filter,
+            Some(FindOptions::builder().sort(doc! {"date": -1}).build()),
+        )
+        .await;
+    Json(events)
+}
+
+#[get("/market-heat?<postcode>")]
+async fn market_heat(
+    state: &State<Globals>,
+    postcode: Option<String>,
+) -> Json<Vec<MarketHeatHistory>> {
+    let filter = match postcode {
+        Some(postcode) => doc! {"postcode": postcode},
+        None => doc! {},
+    };
+    let heats = state
+        .inner()
+        .db
+        .market_heat()
+        .find_filtered_to_vec(filter, None)
+        .await;
+    Json(MarketHeatHistory::from_heats(heats))
+}
+
+#[get("/station-overlap?<postcode>")]
+async fn station_overlap(
+    state: &State<Globals>,
+    postcode: Option<String>,
+) -> Json<Vec<StationOverlap>> {
+    // Matches either station of the pair.
+    let filter = match postcode {
+        Some(postcode) => doc! {"postcodes": postcode},
+        None => doc! {},
+    };
+    let overlaps = state
+        .inner()
+        .db
+        .station_overlap()
+        .find_filtered_to_vec(filter, None)
+        .await;
+    Json(overlaps)
+}
+
+#[get("/yield?<assumptions..>")]
+async fn net_yield(
+    state: &State<Globals>,
+    stamp_duty_rates: &State<Option<StampDutyRates>>,
+    assumptions: YieldAssumptions,
+) -> Result<Json<Vec<NetYieldSummary>>, Custom<String>> {
+    let property = state.inner().db.property().find_to_vec().await;
+    let stamp_duty = stamp_duty_table(stamp_duty_rates)?;
+    Ok(Json(assumptions.net_yield_summaries(stamp_duty, property)))
+}
+
+#[get("/affordability?<assumptions..>")]
+async fn affordability(
+    state: &State<Globals>,
+    stamp_duty_rates: &State<Option<StampDutyRates>>,
+    assumptions: AffordabilityAssumptions,
+) -> Result<Json<Vec<AffordabilitySummary>>, Custom<String>> {
+    let property = state.inner().db.property().find_to_vec().await;
+    let stamp_duty = stamp_duty_table(stamp_duty_rates)?;
+    Ok(Json(
+        assumptions.affordability_summaries(stamp_duty, property),
+    ))
+}
+
+#[get("/cost-of-living?<assumptions..>")]
+async fn cost_of_living(
+    state: &State<Globals>,
+    stamp_duty_rates: &State<Option<StampDutyRates>>,
+    assumptions: CostOfLivingAssumptions,
+) -> Result<Json<Vec<CostOfLivingSummary>>, Custom<String>> {
+    let db = &state.inner().db;
+    let (property, tube_stations, council_tax, fares) = (
+        db.property().find_to_vec().await,
+        db.tube().find_to_vec().await,
+        db.council_tax().find_to_vec().await,
+        db.travelcard_fares().find_to_vec().await,
+    );
+    let stamp_duty = stamp_duty_table(stamp_duty_rates)?;
+    Ok(Json(assumptions.cost_of_living_summaries(
+        stamp_duty,
+        property,
+        &tube_stations,
+        council_tax,
+        &fares,
+    )))
+}
+
+/// Today's stamp duty rates, or a server error if they couldn't be loaded or don't cover today.
+fn stamp_duty_table(
+    stamp_duty_rates: &Option<StampDutyRates>,
+) -> Result<&StampDutyTable, Custom<String>> {
+    let today = Utc::now().date_naive();
+    stamp_duty_rates
+        .as_ref()
+        .ok_or_else(|| "Stamp duty rates are unavailable".to_owned())
+        .and_then(|rates| {
+            rates
+                .table_at(today)
+                .ok_or_else(|| format!("No stamp duty rates in force on [{}]", today))
+        })
+        .map_err(|message| Custom(Status::ServiceUnavailable, message))
+}
+
 #[get("/tube-stations")]
 async fn tube_stations(state: &State<Globals>) -> Json<Vec<TubeStation>> {
     let tube_stations = state.inner().db.tube().find_to_vec().await;
     Json(tube_stations)
 }
 
+#[get("/isochrones?<postcode>&<minutes>")]
+async fn isochrones(
+    state: &State<Globals>,
+    postcode: Option<String>,
+    minutes: Option<u32>,
+) -> Json<Value> {
+    let mut filter = doc! {};
+    if let Some(postcode) = postcode {
+        filter.insert("postcode", postcode);
+    }
+    if let Some(minutes) = minutes {
+        filter.insert("minutes", minutes);
+    }
+    let isochrones = state
+        .inner()
+        .db
+        .isochrones()
+        .find_filtered_to_vec(filter, None)
+        .await;
+    Json(StationIsochrone::to_geojson(&isochrones))
+}
+
+#[get("/postcodes?<prefix>&<limit>")]
+async fn postcode_autocomplete(
+    state: &State<Globals>,
+    prefix: String,
+    limit: Option<i64>,
+) -> Json<Vec<Postcode>> {
+    let postcodes = state
+        .inner()
+        .db
+        .postcodes()
+        .find_filtered_to_vec(
+            doc! {"_id": {"$regex": prefix_regex(&prefix)}},
+            Some(
+                FindOptions::builder()
+                    .sort(doc! {"_id": 1})
+                    .limit(limit.unwrap_or(10).clamp(1, MAX_POSTCODE_SUGGESTIONS))
+                    .build(),
+            ),
+        )
+        .await;
+    Json(postcodes)
+}
+
+#[get("/postcodes/nearest?<lng>&<lat>")]
+async fn postcode_nearest(state: &State<Globals>, lng: f64, lat: f64) -> Option<Json<Postcode>> {
+    let postcode = state
+        .inner()
+        .db
+        .postcodes()
+        .find_one(doc! {"coordinates": {"$nearSphere": [lng, lat]}}, None)
+        .await
+        .unwrap();
+    postcode.map(Json)
+}
+
+#[get("/postcodes/<postcode>")]
+async fn postcode_lookup(state: &State<Globals>, postcode: &str) -> Option<Json<Postcode>> {
+    let postcode = state
+        .inner()
+        .db
+        .postcodes()
+        .find_one(doc! {"_id": normalise(postcode)?}, None)
+        .await
+        .unwrap();
+    postcode.map(Json)
+}
+
 #[get("/schools")]
 async fn schools(state: &State<Globals>) -> Json<Vec<School>> {
     let schools = state.inner().db.schools().find_to_vec().await;
     Json(schools)
 }
 
+#[get("/boroughs")]
+async fn boroughs(state: &State<Globals>) -> Json<Vec<BoroughSummary>> {
+    let db = &state.inner().db;
+    let boroughs = db
+        .boundaries()
+        .find_filtered_to_vec(
+            doc! {"level": BoundaryLevel::Borough.name()},
+            Some(
+                FindOptions::builder()
+                    .projection(doc! {"polygons": 0})
+                    .build(),
+            ),
+        )
+        .await;
+    let (tube_stations, schools, property) = (
+        db.tube().find_to_vec().await,
+        db.schools().find_to_vec().await,
+        db.borough_property().find_to_vec().await,
+    );
+    Json(BoroughSummary::from_assigned(
+        &boroughs,
+        &tube_stations,
+        &schools,
+        property,
+    ))
+}
+
 #[get("/last-updated")]
 async fn last_updated(state: &State<Globals>) -> Json<LastUpdated> {
     let maybe_last_updated = state.inner().db.last_updated().find_to_vec().await;
@@ -45,6 +334,12 @@ async fn last_updated(state: &State<Globals>) -> Json<LastUpdated> {
             property: None,
             schools: None,
             tube: None,
+            sold_prices: None,
+            epc: None,
+            isochrones: None,
+            postcodes: None,
+            boundaries: None,
+            cost_of_living: None,
         }),
     }
 }
@@ -67,9 +362,34 @@ async fn rocket() -> _ {
 
     rocket::custom(&config)
         .manage(globals)
+        .manage(
+            StampDutyRates::load()
+                .map_err(|e| warn!("Stamp duty endpoints are disabled: {:?}", e))
+                .ok(),
+        )
         .mount(
             "/api",
-            routes![property, tube_stations, schools, last_updated],
+            routes![
+                property,
+                property_rollup,
+                property_areas,
+                sold_prices,
+                price_discounts,
+                events,
+                market_heat,
+                station_overlap,
+                net_yield,
+                affordability,
+                cost_of_living,
+                tube_stations,
+                isochrones,
+                postcode_autocomplete,
+                postcode_nearest,
+                postcode_lookup,
+                schools,
+                boroughs,
+                last_updated
+            ],
         )
         .mount("/", FileServer::from("../uk-property-search-app/dist/pwa"))
         .attach(Compressor)
@@ -8,7 +8,13 @@ pub struct Cli {
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum, Debug)]
 pub enum CliTask {
+    UpdateBoundaries,
+    UpdateCostOfLiving,
+    UpdateEpc,
+    UpdateIsochrones,
+    UpdatePostcodes,
     UpdateProperty,
     UpdateSchools,
+    UpdateSoldPrices,
     UpdateTube,
 }
@@ -0,0 +1,207 @@
+use super::{
+    mortgage::monthly_repayment,
+    stamp_duty::{BuyerCircumstances, StampDutyTable},
+};
+use crate::lib::property::property::{PropertyStats, PropertySummary};
+use rocket::FromForm;
+use serde::{Deserialize, Serialize};
+
+/// How a buyer would fund a purchase.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct AffordabilityAssumptions {
+    #[field(default = 50000.0)]
+    pub deposit: f64, // cash put towards the price, stamp duty is paid on top
+    #[field(default = 0.045)]
+    pub rate: f64, // annual mortgage interest rate
+    #[field(default = 25)]
+    pub term: u32, // mortgage term in years
+    #[field(name = "firstTimeBuyer", default = false)]
+    pub first_time_buyer: bool,
+    #[field(name = "additionalDwelling", default = false)]
+    pub additional_dwelling: bool,
+    #[field(name = "nonResident", default = false)]
+    pub non_resident: bool,
+}
+
+/// Monthly cost of buying at the median asking price, compared with renting at the median rent.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AffordabilitySummary {
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub num_beds: u32,
+    pub price: f64,
+    pub stamp_duty: f64,
+    pub upfront_cost: f64, // deposit and stamp duty
+    pub loan: f64,
+    pub monthly_mortgage: f64,
+    pub monthly_service_charge: f64,
+    pub monthly_ground_rent: f64,
+    pub monthly_buy_cost: f64, // mortgage, service charge and ground rent
+    pub monthly_rent: f64,
+    pub monthly_buy_minus_rent: f64, // positive if buying costs more each month
+}
+
+impl AffordabilityAssumptions {
+    pub fn buyer(&self) -> BuyerCircumstances {
+        BuyerCircumstances {
+            first_time_buyer: self.first_time_buyer,
+            additional_dwelling: self.additional_dwelling,
+            non_resident: self.non_resident,
+        }
+    }
+
+    pub fn affordability(
+        &self,
+        stamp_duty: &StampDutyTable,
+        buy_summary: &PropertySummary,
+        rent_stats: &PropertyStats,
+    ) -> AffordabilitySummary {
+        fn median_or_zero(count: usize, median: f64) -> f64 {
+            if count == 0 {
+                0.0
+            } else {
+                median
+            }
+        }
+        let buy_stats = &buy_summary.stats;
+        let price = buy_stats.price.median;
+        let deposit = self.deposit.min(price).max(0.0);
+        let stamp_duty = stamp_duty.stamp_duty(price, &self.buyer());
+        let loan = price - deposit;
+        let monthly_mortgage = monthly_repayment(loan, self.rate, self.term);
+        let monthly_service_charge = median_or_zero(
+            buy_stats.service_charge.count,
+            buy_stats.service_charge.median,
+        ) / 12.0;
+        let monthly_ground_rent =
+            median_or_zero(buy_stats.ground_rent.count, buy_stats.ground_rent.median) / 12.0;
+        let monthly_buy_cost = monthly_mortgage + monthly_service_charge + monthly_ground_rent;
+        let monthly_rent = rent_stats.price.median;
+        AffordabilitySummary {
+            postcode: buy_summary.postcode.clone(),
+            coordinates: buy_summary.coordinates,
+            num_beds: buy_summary.num_beds,
+            price,
+            stamp_duty,
+            upfront_cost: deposit + stamp_duty,
+            loan,
+            monthly_mortgage,
+            monthly_service_charge,
+            monthly_ground_rent,
+            monthly_buy_cost,
+            monthly_rent,
+            monthly_buy_minus_rent: monthly_buy_cost - monthly_rent,
+        }
+    }
+
+    /// Affordability for each station and number of beds, leaving out those without buy
+    /// listings, which have no asking price to borrow against.
+    pub fn affordability_summaries(
+        &self,
+        stamp_duty: &StampDutyTable,
+        summaries: Vec<PropertySummary>,
+    ) -> Vec<AffordabilitySummary> {
+        PropertySummary::pair_buy_and_rent(summaries)
+            .iter()
+            .map(|(buy_summary, rent_summary)| {
+                self.affordability(stamp_duty, buy_summary, &rent_summary.stats)
+            })
+            .filter(|summary| !summary.price.is_nan())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AffordabilityAssumptions;
+    use crate::lib::{
+        finance::stamp_duty::StampDutyRates,
+        math::stats::StatsOptions,
+        property::{
+            aggregator::PropertyAggregator,
+            estate_agents::rightmove::RightmoveProperty,
+            market_speed::MarketSpeed,
+            property::{PropertyAction, PropertySummary, SearchCoverage},
+        },
+    };
+    use chrono::NaiveDate;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_affordability_summaries() {
+        let rates = StampDutyRates::load().unwrap();
+        let stamp_duty = rates
+            .table_at(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+            .unwrap();
+        let aggregator = PropertyAggregator::with_options(HashSet::new(), StatsOptions::default());
+        let coordinates = (-0.123795, 51.530312);
+        let summaries = |postcode: &str, prices: &[u32], rents: &[u32]| {
+            let listings = |prices: &[u32]| {
+                prices
+                    .iter()
+                    .enumerate()
+                    .map(|(id, &price)| {
+                        RightmoveProperty::test_listing(id as u32, coordinates, price)
+                    })
+                    .collect()
+            };
+            let stats =
+                aggregator.calculate_buy_and_rent_property_stats(listings(prices), listings(rents));
+            let summary = |action: PropertyAction, stats, stats_by_type| PropertySummary {
+                postcode: postcode.to_owned(),
+                coordinates,
+                action: action as u8,
+                num_beds: 1,
+                stats,
+                stats_by_type,
+                coverage: SearchCoverage::default(),
+                market_speed: MarketSpeed::empty(),
+                stats_by_distance: vec![],
+            };
+            [
+                summary(
+                    PropertyAction::Buy,
+                    stats.buy_stats,
+                    stats.buy_stats_by_type,
+                ),
+                summary(
+                    PropertyAction::Rent,
+                    stats.rent_stats,
+                    stats.rent_stats_by_type,
+                ),
+            ]
+        };
+        // The deposit covers the whole price, so there's nothing to borrow.
+        let assumptions = AffordabilityAssumptions {
+            deposit: 400000.0,
+            rate: 0.045,
+            term: 25,
+            first_time_buyer: false,
+            additional_dwelling: false,
+            non_resident: false,
+        };
+
+        let affordability = assumptions.affordability_summaries(
+            stamp_duty,
+            [
+                summaries("N1 9AL", &[300000], &[1500]),
+                // No buy listings, so no asking price.
+                summaries("NW1 2DU", &[], &[1500]),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+        );
+        assert_eq!(affordability.len(), 1);
+        let summary = &affordability[0];
+        assert_eq!(summary.postcode, "N1 9AL");
+        assert_eq!(summary.stamp_duty, 5000.0);
+        assert_eq!(summary.upfront_cost, 305000.0);
+        assert_eq!(summary.loan, 0.0);
+        assert_eq!(summary.monthly_mortgage, 0.0);
+        assert_eq!(summary.monthly_buy_cost, 0.0);
+        assert_eq!(summary.monthly_buy_minus_rent, -1500.0);
+    }
+}
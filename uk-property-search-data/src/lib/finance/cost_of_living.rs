@@ -0,0 +1,353 @@
+use super::{affordability::AffordabilityAssumptions, stamp_duty::StampDutyTable};
+use crate::lib::{property::property::PropertySummary, tube::TubeStation};
+use rocket::{FromForm, FromFormField};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Annual council tax charges in a borough.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CouncilTax {
+    #[serde(rename = "_id")]
+    pub code: String, // GSS code of the borough, as assigned to stations
+    pub borough: String, // name
+    pub bands: Vec<f64>, // annual charge for bands A to H, including the GLA precept
+}
+
+// Form values are matched ignoring case, so "d" is band D.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, FromFormField)]
+pub enum CouncilTaxBand {
+    #[field(value = "A")]
+    A,
+    #[field(value = "B")]
+    B,
+    #[field(value = "C")]
+    C,
+    #[field(value = "D")]
+    D,
+    #[field(value = "E")]
+    E,
+    #[field(value = "F")]
+    F,
+    #[field(value = "G")]
+    G,
+    #[field(value = "H")]
+    H,
+}
+
+impl CouncilTax {
+    pub fn monthly(&self, band: CouncilTaxBand) -> Option<f64> {
+        self.bands.get(band as usize).map(|annual| annual / 12.0)
+    }
+}
+
+/// Price of an annual TfL travelcard covering every zone from `from_zone` to `to_zone`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelcardFare {
+    pub from_zone: u8,
+    pub to_zone: u8, // not below from_zone
+    pub annual: f64,
+}
+
+impl TravelcardFare {
+    /// Cheapest travelcard between any of a station's zones and the destination zone, as
+    /// stations on a zone boundary can be treated as in either.
+    pub fn cheapest<'a>(
+        fares: &'a [TravelcardFare],
+        station_zones: &[u8],
+        destination_zone: u8,
+    ) -> Option<&'a TravelcardFare> {
+        station_zones
+            .iter()
+            .filter_map(|&zone| {
+                let (from_zone, to_zone) = (zone.min(destination_zone), zone.max(destination_zone));
+                fares
+                    .iter()
+                    .find(|fare| fare.from_zone == from_zone && fare.to_zone == to_zone)
+            })
+            .min_by(|a, b| a.annual.total_cmp(&b.annual))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, FromFormField)]
+#[serde(rename_all = "camelCase")]
+pub enum Housing {
+    #[field(value = "rent")]
+    Rent,
+    #[field(value = "buy")]
+    Buy,
+}
+
+/// How someone living near a station would pay for housing and travel.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct CostOfLivingAssumptions {
+    #[field(default = Housing::Rent)]
+    pub housing: Housing,
+    #[field(default = CouncilTaxBand::D)]
+    pub band: CouncilTaxBand, // council tax band of a typical home
+    #[field(name = "destinationZone", default = 1)]
+    pub destination_zone: u8, // zone commuted to
+    #[field(default = 50000.0)]
+    pub deposit: f64, // if buying, as for affordability
+    #[field(default = 0.045)]
+    pub rate: f64, // if buying, annual mortgage interest rate
+    #[field(default = 25)]
+    pub term: u32, // if buying, mortgage term in years
+}
+
+/// Monthly cost of living near a station, with housing at the median rent or asking price.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CostOfLivingSummary {
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub num_beds: u32,
+    pub borough_code: String, // GSS code
+    pub borough: String,
+    pub travelcard_zones: (u8, u8), // from and to
+    pub monthly_housing: f64,       // rent, or if buying mortgage, service charge and ground rent
+    pub monthly_council_tax: f64,
+    pub monthly_travelcard: f64,
+    pub monthly_total: f64,
+}
+
+impl CostOfLivingAssumptions {
+    /// Cost of living near the station, or None if its council tax, travelcard or housing cost
+    /// isn't known, e.g. with no rent listings.
+    pub fn cost_of_living(
+        &self,
+        stamp_duty: &StampDutyTable,
+        buy_summary: &PropertySummary,
+        rent_summary: &PropertySummary,
+        station: &TubeStation,
+        council_tax: &HashMap<String, CouncilTax>,
+        fares: &[TravelcardFare],
+    ) -> Option<CostOfLivingSummary> {
+        let council_tax = council_tax.get(station.borough.as_ref()?)?;
+        let monthly_council_tax = council_tax.monthly(self.band)?;
+        let fare = TravelcardFare::cheapest(fares, &station.zone, self.destination_zone)?;
+        let monthly_travelcard = fare.annual / 12.0;
+        let monthly_housing = match self.housing {
+            Housing::Rent => rent_summary.stats.price.median,
+            Housing::Buy => {
+                AffordabilityAssumptions {
+                    deposit: self.deposit,
+                    rate: self.rate,
+                    term: self.term,
+                    first_time_buyer: false,
+                    additional_dwelling: false,
+                    non_resident: false,
+                }
+                .affordability(stamp_duty, buy_summary, &rent_summary.stats)
+                .monthly_buy_cost
+            }
+        };
+        if monthly_housing.is_nan() {
+            return None;
+        }
+        Some(CostOfLivingSummary {
+            postcode: buy_summary.postcode.clone(),
+            coordinates: buy_summary.coordinates,
+            num_beds: buy_summary.num_beds,
+            borough_code: council_tax.code.clone(),
+            borough: council_tax.borough.clone(),
+            travelcard_zones: (fare.from_zone, fare.to_zone),
+            monthly_housing,
+            monthly_council_tax,
+            monthly_travelcard,
+            monthly_total: monthly_housing + monthly_council_tax + monthly_travelcard,
+        })
+    }
+
+    /// Cost of living for each station and number of beds with both buy and rent listings,
+    /// leaving out stations whose council tax, travelcard or housing cost isn't known.
+    pub fn cost_of_living_summaries(
+        &self,
+        stamp_duty: &StampDutyTable,
+        summaries: Vec<PropertySummary>,
+        stations: &[TubeStation],
+        council_tax: Vec<CouncilTax>,
+        fares: &[TravelcardFare],
+    ) -> Vec<CostOfLivingSummary> {
+        let stations = stations
+            .iter()
+            .map(|station| (station.postcode.as_str(), station))
+            .collect::<HashMap<_, _>>();
+        let council_tax = council_tax
+            .into_iter()
+            .map(|council_tax| (council_tax.code.clone(), council_tax))
+            .collect::<HashMap<_, _>>();
+        PropertySummary::pair_buy_and_rent(summaries)
+            .iter()
+            .filter_map(|(buy_summary, rent_summary)| {
+                self.cost_of_living(
+                    stamp_duty,
+                    buy_summary,
+                    rent_summary,
+                    stations.get(buy_summary.postcode.as_str())?,
+                    &council_tax,
+                    fares,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CostOfLivingAssumptions, CouncilTax, CouncilTaxBand, Housing, TravelcardFare};
+    use crate::lib::{
+        finance::stamp_duty::StampDutyRates,
+        math::stats::StatsOptions,
+        property::{
+            aggregator::PropertyAggregator,
+            estate_agents::rightmove::RightmoveProperty,
+            market_speed::MarketSpeed,
+            property::{PropertyAction, PropertySummary, SearchCoverage},
+        },
+        tube::TubeStation,
+    };
+    use chrono::NaiveDate;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_council_tax_and_travelcard() {
+        let council_tax = CouncilTax {
+            code: "E09000007".to_owned(),
+            borough: "Camden".to_owned(),
+            bands: vec![
+                1200.0, 1400.0, 1600.0, 1800.0, 2200.0, 2600.0, 3000.0, 3600.0,
+            ],
+        };
+        assert_eq!(council_tax.monthly(CouncilTaxBand::A), Some(100.0));
+        assert_eq!(council_tax.monthly(CouncilTaxBand::D), Some(150.0));
+        assert_eq!(council_tax.monthly(CouncilTaxBand::H), Some(300.0));
+
+        let fare = |from_zone: u8, to_zone: u8, annual: f64| TravelcardFare {
+            from_zone,
+            to_zone,
+            annual,
+        };
+        let fares = vec![
+            fare(1, 2, 1800.0),
+            fare(1, 3, 2100.0),
+            fare(2, 3, 1000.0),
+            fare(3, 3, 900.0),
+        ];
+        let cheapest = |zones: &[u8], destination: u8| {
+            TravelcardFare::cheapest(&fares, zones, destination).map(|f| (f.from_zone, f.to_zone))
+        };
+        assert_eq!(cheapest(&[2], 1), Some((1, 2)));
+        // On the zone 2/3 boundary, commuting to zone 1 needs zones 1-2.
+        assert_eq!(cheapest(&[2, 3], 1), Some((1, 2)));
+        assert_eq!(cheapest(&[2, 3], 3), Some((3, 3)));
+        assert_eq!(cheapest(&[6], 1), None);
+    }
+
+    #[test]
+    fn test_cost_of_living() {
+        let rates = StampDutyRates::load().unwrap();
+        let stamp_duty = rates
+            .table_at(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+            .unwrap();
+        let aggregator = PropertyAggregator::with_options(HashSet::new(), StatsOptions::default());
+        let coordinates = (-0.123795, 51.530312);
+        let summaries = |postcode: &str, rents: &[u32]| {
+            let listings = |prices: &[u32]| {
+                prices
+                    .iter()
+                    .enumerate()
+                    .map(|(id, &price)| {
+                        RightmoveProperty::test_listing(id as u32, coordinates, price)
+                    })
+                    .collect()
+            };
+            let stats = aggregator
+                .calculate_buy_and_rent_property_stats(listings(&[500000]), listings(rents));
+            let summary = |action: PropertyAction, stats, stats_by_type| PropertySummary {
+                postcode: postcode.to_owned(),
+                coordinates,
+                action: action as u8,
+                num_beds: 1,
+                stats,
+                stats_by_type,
+                coverage: SearchCoverage::default(),
+                market_speed: MarketSpeed::empty(),
+                stats_by_distance: vec![],
+            };
+            [
+                summary(
+                    PropertyAction::Buy,
+                    stats.buy_stats,
+                    stats.buy_stats_by_type,
+                ),
+                summary(
+                    PropertyAction::Rent,
+                    stats.rent_stats,
+                    stats.rent_stats_by_type,
+                ),
+            ]
+        };
+        let station = |postcode: &str, borough: &str| TubeStation {
+            name: postcode.to_owned(),
+            zone: vec![1],
+            postcode: postcode.to_owned(),
+            coordinates,
+            lines: HashSet::new(),
+            borough: Some(borough.to_owned()),
+            ward: None,
+            lsoa: None,
+        };
+        let council_tax = vec![CouncilTax {
+            code: "E09000019".to_owned(),
+            borough: "Islington".to_owned(),
+            bands: vec![
+                1200.0, 1400.0, 1600.0, 1800.0, 2200.0, 2600.0, 3000.0, 3600.0,
+            ],
+        }];
+        let fares = vec![TravelcardFare {
+            from_zone: 1,
+            to_zone: 1,
+            annual: 1800.0,
+        }];
+        let assumptions = CostOfLivingAssumptions {
+            housing: Housing::Rent,
+            band: CouncilTaxBand::D,
+            destination_zone: 1,
+            deposit: 50000.0,
+            rate: 0.045,
+            term: 25,
+        };
+
+        let cost_of_living = assumptions.cost_of_living_summaries(
+            stamp_duty,
+            [
+                summaries("N1 9AL", &[2000, 2200]),
+                // No rent listings, so no housing cost.
+                summaries("N1 0XX", &[]),
+                // Camden has no council tax.
+                summaries("NW1 2DU", &[2000, 2200]),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            &[
+                station("N1 9AL", "E09000019"),
+                station("N1 0XX", "E09000019"),
+                station("NW1 2DU", "E09000007"),
+            ],
+            council_tax,
+            &fares,
+        );
+        assert_eq!(cost_of_living.len(), 1);
+        let summary = &cost_of_living[0];
+        assert_eq!(summary.postcode, "N1 9AL");
+        assert_eq!(summary.borough, "Islington");
+        assert_eq!(summary.monthly_housing, 2100.0);
+        assert_eq!(summary.monthly_council_tax, 150.0);
+        assert_eq!(summary.monthly_travelcard, 150.0);
+        assert_eq!(summary.monthly_total, 2400.0);
+    }
+}
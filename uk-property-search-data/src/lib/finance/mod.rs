@@ -0,0 +1,5 @@
+pub mod affordability;
+pub mod cost_of_living;
+pub mod mortgage;
+pub mod net_yield;
+pub mod stamp_duty;
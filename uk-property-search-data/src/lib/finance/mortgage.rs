@@ -0,0 +1,25 @@
+/// Monthly repayment on a capital and interest mortgage.
+pub fn monthly_repayment(principal: f64, annual_rate: f64, term_years: u32) -> f64 {
+    let num_payments = (term_years * 12) as f64;
+    if principal <= 0.0 || num_payments == 0.0 {
+        return principal.max(0.0);
+    }
+    let monthly_rate = annual_rate / 12.0;
+    if monthly_rate == 0.0 {
+        return principal / num_payments;
+    }
+    principal * monthly_rate / (1.0 - (1.0 + monthly_rate).powf(-num_payments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::monthly_repayment;
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_monthly_repayment() {
+        assert_almost_eq!(monthly_repayment(200000.0, 0.05, 25), 1169.18, 1e-2);
+        assert_eq!(monthly_repayment(120000.0, 0.0, 10), 1000.0);
+        assert_eq!(monthly_repayment(-1000.0, 0.05, 25), 0.0);
+    }
+}
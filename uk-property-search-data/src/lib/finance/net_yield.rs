@@ -0,0 +1,165 @@
+use super::stamp_duty::{BuyerCircumstances, StampDutyTable};
+use crate::lib::{
+    math::stats::Stats,
+    property::property::{PropertyStats, PropertySummary},
+};
+use rocket::FromForm;
+use serde::{Deserialize, Serialize};
+
+/// Costs of letting out a property, as assumed by the investor.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct YieldAssumptions {
+    #[field(name = "lettingFeeRate", default = 0.12)]
+    pub letting_fee_rate: f64, // fraction of rent collected
+    #[field(name = "voidWeeks", default = 2.0)]
+    pub void_weeks: f64, // per year
+    #[field(name = "maintenanceRate", default = 0.05)]
+    pub maintenance_rate: f64, // fraction of rent
+    #[field(name = "serviceCharge")]
+    pub service_charge: Option<f64>, // annual, defaults to the advertised median
+    #[field(name = "groundRent")]
+    pub ground_rent: Option<f64>, // annual, defaults to the advertised median
+    #[field(name = "includeStampDuty", default = true)]
+    pub include_stamp_duty: bool,
+    #[field(name = "additionalDwelling", default = true)]
+    pub additional_dwelling: bool,
+}
+
+impl Default for YieldAssumptions {
+    fn default() -> Self {
+        YieldAssumptions {
+            letting_fee_rate: 0.12,
+            void_weeks: 2.0,
+            maintenance_rate: 0.05,
+            service_charge: None,
+            ground_rent: None,
+            include_stamp_duty: true,
+            additional_dwelling: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetYieldSummary {
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub num_beds: u32,
+    pub gross_yield: Stats,
+    pub net_yield: Stats,
+}
+
+impl YieldAssumptions {
+    /// Annual rent left after costs, divided by the total cost of purchase.
+    pub fn net_yield(
+        &self,
+        stamp_duty: &StampDutyTable,
+        monthly_rent: f64,
+        price: f64,
+        service_charge: f64,
+        ground_rent: f64,
+    ) -> f64 {
+        let annual_rent = monthly_rent * 12.0;
+        let collected_rent = annual_rent * (1.0 - self.void_weeks / 52.0);
+        let costs = collected_rent * self.letting_fee_rate
+            + annual_rent * self.maintenance_rate
+            + self.service_charge.unwrap_or(service_charge)
+            + self.ground_rent.unwrap_or(ground_rent);
+        let purchase_cost = if self.include_stamp_duty {
+            let buyer = BuyerCircumstances {
+                additional_dwelling: self.additional_dwelling,
+                ..BuyerCircumstances::default()
+            };
+            price + stamp_duty.stamp_duty(price, &buyer)
+        } else {
+            price
+        };
+        (collected_rent - costs) / purchase_cost
+    }
+
+    /// Net yield at each quantile, pairing rent and price quantiles like the gross rental yield.
+    pub fn net_yield_stats(
+        &self,
+        stamp_duty: &StampDutyTable,
+        buy_stats: &PropertyStats,
+        rent_stats: &PropertyStats,
+    ) -> Stats {
+        fn median_or_zero(stats: &Stats) -> f64 {
+            if stats.count == 0 {
+                0.0
+            } else {
+                stats.median
+            }
+        }
+        let service_charge = median_or_zero(&buy_stats.service_charge);
+        let ground_rent = median_or_zero(&buy_stats.ground_rent);
+        let net_yield = |rent: f64, price: f64| -> f64 {
+            self.net_yield(stamp_duty, rent, price, service_charge, ground_rent)
+        };
+        Stats {
+            min: net_yield(rent_stats.price.min, buy_stats.price.min),
+            q1: net_yield(rent_stats.price.q1, buy_stats.price.q1),
+            median: net_yield(rent_stats.price.median, buy_stats.price.median),
+            q3: net_yield(rent_stats.price.q3, buy_stats.price.q3),
+            max: net_yield(rent_stats.price.max, buy_stats.price.max),
+            count: buy_stats.rental_yield.count,
+            trimming: buy_stats.price.trimming,
+            ..Stats::nan()
+        }
+    }
+
+    /// Net yield for each station and number of beds with both buy and rent listings.
+    pub fn net_yield_summaries(
+        &self,
+        stamp_duty: &StampDutyTable,
+        summaries: Vec<PropertySummary>,
+    ) -> Vec<NetYieldSummary> {
+        PropertySummary::pair_buy_and_rent(summaries)
+            .into_iter()
+            .map(|(buy_summary, rent_summary)| NetYieldSummary {
+                net_yield: self.net_yield_stats(
+                    stamp_duty,
+                    &buy_summary.stats,
+                    &rent_summary.stats,
+                ),
+                gross_yield: buy_summary.stats.rental_yield,
+                postcode: buy_summary.postcode,
+                coordinates: buy_summary.coordinates,
+                num_beds: buy_summary.num_beds,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::YieldAssumptions;
+    use crate::lib::finance::stamp_duty::StampDutyRates;
+    use chrono::NaiveDate;
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_net_yield() {
+        let rates = StampDutyRates::load().unwrap();
+        let stamp_duty = rates
+            .table_at(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+            .unwrap();
+        let assumptions = YieldAssumptions {
+            include_stamp_duty: false,
+            ..YieldAssumptions::default()
+        };
+        // 24000 rent, 23076.92 collected, 2769.23 letting fee, 1200 maintenance, 2000 fixed costs
+        assert_almost_eq!(
+            assumptions.net_yield(stamp_duty, 2000.0, 400000.0, 1500.0, 500.0),
+            0.042769,
+            1e-6
+        );
+
+        let with_stamp_duty = YieldAssumptions::default();
+        assert!(
+            with_stamp_duty.net_yield(stamp_duty, 2000.0, 400000.0, 1500.0, 500.0)
+                < assumptions.net_yield(stamp_duty, 2000.0, 400000.0, 1500.0, 500.0)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use config::{Config, File};
+use serde::{Deserialize, Serialize};
+
+const RATES_FILE: &str = "assets/stamp_duty_rates.toml";
+
+/// Stamp Duty Land Tax rate tables, each effective from a given date.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StampDutyRates {
+    tables: Vec<StampDutyTable>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StampDutyTable {
+    effective_from: String, // yyyy-mm-dd
+    bands: Vec<StampDutyBand>,
+    first_time_buyer_bands: Vec<StampDutyBand>,
+    first_time_buyer_max_price: f64, // relief is lost entirely above this price
+    additional_dwelling_surcharge: f64,
+    additional_dwelling_min_price: f64,
+    non_resident_surcharge: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StampDutyBand {
+    from: f64,
+    rate: f64, // on the portion of the price above "from"
+}
+
+/// Who is buying, which decides the reliefs and surcharges that apply.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuyerCircumstances {
+    pub first_time_buyer: bool,
+    pub additional_dwelling: bool,
+    pub non_resident: bool,
+}
+
+impl StampDutyRates {
+    pub fn load() -> Result<StampDutyRates> {
+        let rates: StampDutyRates = Config::builder()
+            .add_source(File::with_name(RATES_FILE))
+            .build()
+            .and_then(|config| config.try_deserialize())
+            .with_context(|| format!("Failed to load stamp duty rates from [{}]", RATES_FILE))?;
+        for table in &rates.tables {
+            table.effective_from()?;
+        }
+        Ok(rates)
+    }
+
+    /// The rate table in force on the given completion date, if any.
+    pub fn table_at(&self, date: NaiveDate) -> Option<&StampDutyTable> {
+        self.tables
+            .iter()
+            .filter_map(|table| Some((table.effective_from().ok()?, table)))
+            .filter(|(effective_from, _)| *effective_from <= date)
+            .max_by_key(|(effective_from, _)| *effective_from)
+            .map(|(_, table)| table)
+    }
+}
+
+impl StampDutyTable {
+    fn effective_from(&self) -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&self.effective_from, "%Y-%m-%d").with_context(|| {
+            format!(
+                "[{}] is not a valid stamp duty effective date!",
+                self.effective_from
+            )
+        })
+    }
+
+    /// Stamp duty payable on a residential purchase at the given price.
+    pub fn stamp_duty(&self, price: f64, buyer: &BuyerCircumstances) -> f64 {
+        // First-time buyer relief does not apply alongside the additional dwelling surcharge.
+        let first_time_buyer_relief = buyer.first_time_buyer
+            && !buyer.additional_dwelling
+            && price <= self.first_time_buyer_max_price;
+        let bands = if first_time_buyer_relief {
+            &self.first_time_buyer_bands
+        } else {
+            &self.bands
+        };
+        let banded: f64 = bands
+            .iter()
+            .enumerate()
+            .map(|(i, band)| {
+                let upper = bands.get(i + 1).map_or(f64::INFINITY, |next| next.from);
+                (price.min(upper) - band.from).max(0.0) * band.rate
+            })
+            .sum();
+
+        let mut surcharge_rate = 0.0;
+        if buyer.additional_dwelling && price >= self.additional_dwelling_min_price {
+            surcharge_rate += self.additional_dwelling_surcharge;
+        }
+        if buyer.non_resident {
+            surcharge_rate += self.non_resident_surcharge;
+        }
+        // Tax is rounded down to the pound, after rounding away floating point error in pennies.
+        ((banded + price * surcharge_rate) * 100.0)
+            .round()
+            .div_euclid(100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuyerCircumstances, StampDutyRates};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_stamp_duty() {
+        let rates = StampDutyRates::load().unwrap();
+        let table = rates
+            .table_at(NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+            .unwrap();
+        let buyer = BuyerCircumstances::default();
+        assert_eq!(table.stamp_duty(100000.0, &buyer), 0.0);
+        assert_eq!(table.stamp_duty(300000.0, &buyer), 5000.0);
+        assert_eq!(table.stamp_duty(1000000.0, &buyer), 43750.0);
+
+        let first_time_buyer = BuyerCircumstances {
+            first_time_buyer: true,
+            ..buyer
+        };
+        assert_eq!(table.stamp_duty(300000.0, &first_time_buyer), 0.0);
+        assert_eq!(table.stamp_duty(400000.0, &first_time_buyer), 5000.0);
+        assert_eq!(table.stamp_duty(600000.0, &first_time_buyer), 20000.0);
+
+        let additional_non_resident = BuyerCircumstances {
+            additional_dwelling: true,
+            non_resident: true,
+            ..buyer
+        };
+        assert_eq!(
+            table.stamp_duty(300000.0, &additional_non_resident),
+            26000.0
+        );
+    }
+
+    #[test]
+    fn test_table_at() {
+        let rates = StampDutyRates::load().unwrap();
+        let buyer = BuyerCircumstances {
+            additional_dwelling: true,
+            ..BuyerCircumstances::default()
+        };
+        let table_at = |y, m, d| rates.table_at(NaiveDate::from_ymd_opt(y, m, d).unwrap());
+        let before_surcharge_rise = table_at(2024, 10, 30).unwrap();
+        let after_surcharge_rise = table_at(2024, 10, 31).unwrap();
+        assert_eq!(before_surcharge_rise.stamp_duty(300000.0, &buyer), 11500.0);
+        assert_eq!(after_surcharge_rise.stamp_duty(300000.0, &buyer), 17500.0);
+        assert!(table_at(1900, 1, 1).is_none());
+    }
+}
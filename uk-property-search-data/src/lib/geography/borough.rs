@@ -0,0 +1,146 @@
+use super::boundary::Boundary;
+use crate::lib::{
+    property::area::AreaPropertySummary,
+    school::{Rating, School},
+    tube::TubeStation,
+};
+use serde::{Deserialize, Serialize};
+
+/// Number of schools with each Ofsted rating.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SchoolRatingSplit {
+    pub outstanding: usize,
+    pub good: usize,
+    pub requires_improvement: usize,
+    pub inadequate: usize,
+    pub unknown: usize,
+}
+
+impl SchoolRatingSplit {
+    pub fn add(&mut self, rating: u8) {
+        match rating {
+            r if r == Rating::Outstanding as u8 => self.outstanding += 1,
+            r if r == Rating::Good as u8 => self.good += 1,
+            r if r == Rating::RequiresImprovement as u8 => self.requires_improvement += 1,
+            r if r == Rating::Inadequate as u8 => self.inadequate += 1,
+            _ => self.unknown += 1,
+        }
+    }
+}
+
+/// Stations, schools and listings within a borough.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoroughSummary {
+    pub code: String,
+    pub name: String,
+    pub stations: Vec<String>, // postcodes
+    pub schools: SchoolRatingSplit,
+    pub property: Vec<AreaPropertySummary>, // for each action and number of beds
+}
+
+impl BoroughSummary {
+    pub fn from_assigned(
+        boroughs: &[Boundary],
+        stations: &[TubeStation],
+        schools: &[School],
+        property: Vec<AreaPropertySummary>,
+    ) -> Vec<BoroughSummary> {
+        let mut summaries = boroughs
+            .iter()
+            .map(|borough| BoroughSummary {
+                code: borough.code.clone(),
+                name: borough.name.clone(),
+                stations: stations
+                    .iter()
+                    .filter(|station| station.borough.as_ref() == Some(&borough.code))
+                    .map(|station| station.postcode.clone())
+                    .collect(),
+                schools: schools
+                    .iter()
+                    .filter(|school| school.borough.as_ref() == Some(&borough.code))
+                    .fold(SchoolRatingSplit::default(), |mut split, school| {
+                        split.add(school.rating);
+                        split
+                    }),
+                property: vec![],
+            })
+            .collect::<Vec<_>>();
+        // Stations, schools and property summaries are all assigned by the borough's GSS code.
+        for summary in property {
+            if let Some(borough) = summaries.iter_mut().find(|b| b.code == summary.area) {
+                borough.property.push(summary);
+            }
+        }
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        summaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoroughSummary;
+    use crate::lib::{
+        geography::boundary::{Boundary, BoundaryLevel},
+        school::{Rating, School},
+        tube::TubeStation,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_from_assigned() {
+        let borough = |code: &str, name: &str| Boundary {
+            level: BoundaryLevel::Borough,
+            code: code.to_owned(),
+            name: name.to_owned(),
+            polygons: vec![],
+        };
+        let station = |postcode: &str, borough: Option<&str>| TubeStation {
+            name: postcode.to_owned(),
+            zone: vec![1],
+            postcode: postcode.to_owned(),
+            coordinates: (-0.123795, 51.530312),
+            lines: HashSet::new(),
+            borough: borough.map(|b| b.to_owned()),
+            ward: None,
+            lsoa: None,
+        };
+        let school = |id: i64, rating: Rating, borough: &str| School {
+            id,
+            name: String::new(),
+            postcode: String::new(),
+            coordinates: (-0.123795, 51.530312),
+            rating: rating as u8,
+            inspection_date_ms: None,
+            borough: Some(borough.to_owned()),
+            ward: None,
+            lsoa: None,
+        };
+        let summaries = BoroughSummary::from_assigned(
+            &[
+                borough("E09000019", "Islington"),
+                borough("E09000007", "Camden"),
+            ],
+            &[
+                station("N1 9AL", Some("E09000007")),
+                station("NW1 2DU", Some("E09000007")),
+                station("N1 0XX", Some("E09000019")),
+                station("RM1 1AA", None),
+            ],
+            &[
+                school(1, Rating::Outstanding, "E09000007"),
+                school(2, Rating::Good, "E09000007"),
+                school(3, Rating::Unknown, "E09000019"),
+            ],
+            vec![],
+        );
+        assert_eq!(summaries[0].name, "Camden");
+        assert_eq!(summaries[0].stations, vec!["N1 9AL", "NW1 2DU"]);
+        assert_eq!(summaries[0].schools.outstanding, 1);
+        assert_eq!(summaries[0].schools.good, 1);
+        assert_eq!(summaries[1].name, "Islington");
+        assert_eq!(summaries[1].stations, vec!["N1 0XX"]);
+        assert_eq!(summaries[1].schools.unknown, 1);
+    }
+}
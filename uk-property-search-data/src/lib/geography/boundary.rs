@@ -0,0 +1,182 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+
+// Feature properties holding the code and name, in the London Datastore and ONS releases.
+const CODE_KEYS: [&str; 6] = [
+    "GSS_CODE", "LAD22CD", "WD22CD", "LSOA21CD", "LSOA11CD", "code",
+];
+const NAME_KEYS: [&str; 6] = ["NAME", "LAD22NM", "WD22NM", "LSOA21NM", "LSOA11NM", "name"];
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum BoundaryLevel {
+    Borough,
+    Ward,
+    Lsoa, // lower layer super output area, of around 1,500 residents
+}
+
+impl BoundaryLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            BoundaryLevel::Borough => "borough",
+            BoundaryLevel::Ward => "ward",
+            BoundaryLevel::Lsoa => "lsoa",
+        }
+    }
+}
+
+/// Administrative area, as polygons of (longitude, latitude).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Boundary {
+    pub level: BoundaryLevel,
+    pub code: String, // e.g. E09000007
+    pub name: String, // e.g. Camden
+    // Each an exterior ring then any holes. Left out when only names are needed.
+    #[serde(default)]
+    pub polygons: Vec<Vec<Vec<(f64, f64)>>>,
+}
+
+impl Boundary {
+    /// Read the Polygon and MultiPolygon features of a GeoJSON FeatureCollection. Coordinates
+    /// must be longitude and latitude, as GeoJSON requires, rather than a national grid.
+    pub fn read_geojson(level: BoundaryLevel, path: &str) -> Result<Vec<Boundary>> {
+        let geojson: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let features = geojson["features"]
+            .as_array()
+            .ok_or_else(|| anyhow!("[{}] is not a GeoJSON FeatureCollection!", path))?;
+        let mut boundaries = vec![];
+        for feature in features {
+            let property = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| feature["properties"][key].as_str())
+                    .map(|s| s.to_owned())
+            };
+            let (code, name) = match (property(&CODE_KEYS), property(&NAME_KEYS)) {
+                (Some(code), Some(name)) => (code, name),
+                _ => bail!("Feature in [{}] has no code or name!", path),
+            };
+            let geometry = &feature["geometry"];
+            let polygons = match geometry["type"].as_str() {
+                Some("Polygon") => vec![parse_polygon(&geometry["coordinates"])],
+                Some("MultiPolygon") => geometry["coordinates"]
+                    .as_array()
+                    .map(|polygons| polygons.iter().map(parse_polygon).collect())
+                    .unwrap_or_default(),
+                _ => continue,
+            }
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow!("[{}] in [{}] has invalid coordinates!", name, path))?;
+            let is_longitude_latitude = polygons
+                .iter()
+                .flatten()
+                .flatten()
+                .all(|&(x, y)| (-180.0..=180.0).contains(&x) && (-90.0..=90.0).contains(&y));
+            if !is_longitude_latitude {
+                bail!("[{}] in [{}] is not in longitude and latitude!", name, path);
+            }
+            boundaries.push(Boundary {
+                level,
+                code,
+                name,
+                polygons,
+            });
+        }
+        Ok(boundaries)
+    }
+
+    pub fn contains(&self, point: (f64, f64)) -> bool {
+        self.polygons.iter().any(|rings| match rings.split_first() {
+            Some((exterior, holes)) => {
+                ring_contains(exterior, point)
+                    && !holes.iter().any(|hole| ring_contains(hole, point))
+            }
+            None => false,
+        })
+    }
+
+    /// South west and north east corners of the box around every polygon.
+    pub fn bounding_box(&self) -> ((f64, f64), (f64, f64)) {
+        self.polygons.iter().flatten().flatten().fold(
+            (
+                (f64::INFINITY, f64::INFINITY),
+                (f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |((west, south), (east, north)), &(x, y)| {
+                ((west.min(x), south.min(y)), (east.max(x), north.max(y)))
+            },
+        )
+    }
+}
+
+fn parse_polygon(polygon: &Value) -> Option<Vec<Vec<(f64, f64)>>> {
+    polygon
+        .as_array()?
+        .iter()
+        .map(|ring| {
+            ring.as_array()?
+                .iter()
+                .map(|position| Some((position[0].as_f64()?, position[1].as_f64()?)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Even-odd rule: whether a ray east from the point crosses the ring an odd number of times.
+fn ring_contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    for (i, &(xi, yi)) in ring.iter().enumerate() {
+        let (xj, yj) = ring[(i + ring.len() - 1) % ring.len()];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Boundary, BoundaryLevel};
+    use std::io::Write;
+
+    #[test]
+    fn test_read_geojson() {
+        let mut file = tempfile::Builder::new()
+            .suffix(".geojson")
+            .tempfile()
+            .unwrap();
+        file.write_all(
+            br#"{"type": "FeatureCollection", "features": [
+                {"type": "Feature", "properties": {"NAME": "Square", "GSS_CODE": "E1"},
+                 "geometry": {"type": "Polygon", "coordinates": [
+                    [[0, 0], [4, 0], [4, 4], [0, 4], [0, 0]],
+                    [[1, 1], [2, 1], [2, 2], [1, 2], [1, 1]]]}},
+                {"type": "Feature", "properties": {"LSOA11NM": "Islands", "LSOA11CD": "E2"},
+                 "geometry": {"type": "MultiPolygon", "coordinates": [
+                    [[[10, 10], [11, 10], [11, 11], [10, 10]]],
+                    [[[20, 20], [21, 20], [21, 21], [20, 20]]]]}}
+            ]}"#,
+        )
+        .unwrap();
+        let boundaries =
+            Boundary::read_geojson(BoundaryLevel::Borough, file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(boundaries.len(), 2);
+        let (square, islands) = (&boundaries[0], &boundaries[1]);
+        assert_eq!(
+            (square.code.as_str(), square.name.as_str()),
+            ("E1", "Square")
+        );
+        assert!(square.contains((3.0, 3.0)));
+        assert!(!square.contains((1.5, 1.5))); // in the hole
+        assert!(!square.contains((5.0, 3.0)));
+        assert_eq!(square.bounding_box(), ((0.0, 0.0), (4.0, 4.0)));
+
+        assert_eq!(islands.name, "Islands");
+        assert!(islands.contains((20.8, 20.2)));
+        assert!(!islands.contains((15.0, 15.0)));
+    }
+}
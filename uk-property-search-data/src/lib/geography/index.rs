@@ -0,0 +1,133 @@
+use super::boundary::{Boundary, BoundaryLevel};
+use crate::lib::{
+    property::estate_agents::rightmove::RightmoveProperty,
+    util::{db::Db, ext::MongoCollectionExt},
+};
+use log::warn;
+use mongodb::bson::doc;
+use std::collections::HashMap;
+
+// Roughly 700m by 1.1km in London, so that each cell overlaps only a few wards.
+const GRID_CELL_DEGREES: f64 = 0.01;
+
+/// Boundaries of one level, gridded by their bounding boxes so that only those near a point
+/// need testing.
+pub struct BoundaryIndex {
+    boundaries: Vec<Boundary>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl BoundaryIndex {
+    pub fn new(boundaries: Vec<Boundary>) -> BoundaryIndex {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, boundary) in boundaries.iter().enumerate() {
+            let (south_west, north_east) = boundary.bounding_box();
+            let ((west, south), (east, north)) = (cell(south_west), cell(north_east));
+            for x in west..=east {
+                for y in south..=north {
+                    cells.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+        BoundaryIndex { boundaries, cells }
+    }
+
+    /// Boundaries of the level, or none if they haven't been loaded, so that nothing is
+    /// assigned to them.
+    pub async fn load(db: &Db, level: BoundaryLevel) -> BoundaryIndex {
+        let boundaries = db
+            .boundaries()
+            .find_filtered_to_vec(doc! {"level": level.name()}, None)
+            .await;
+        if boundaries.is_empty() {
+            warn!(
+                "No [{}] boundaries found, so skipping assignment to them. Run the update-boundaries task first!",
+                level.name()
+            );
+        }
+        BoundaryIndex::new(boundaries)
+    }
+
+    /// Boundary containing the point, if any. Boundaries of a level shouldn't overlap, but if
+    /// they do the first is used.
+    pub fn locate(&self, point: (f64, f64)) -> Option<&Boundary> {
+        self.cells
+            .get(&cell(point))?
+            .iter()
+            .map(|&i| &self.boundaries[i])
+            .find(|boundary| boundary.contains(point))
+    }
+
+    /// GSS code of the boundary containing the point, if any.
+    pub fn locate_code(&self, point: (f64, f64)) -> Option<String> {
+        self.locate(point).map(|boundary| boundary.code.clone())
+    }
+}
+
+/// Boroughs, wards and LSOAs, for assigning points to each.
+pub struct Geography {
+    pub boroughs: BoundaryIndex,
+    pub wards: BoundaryIndex,
+    pub lsoas: BoundaryIndex,
+}
+
+impl Geography {
+    pub async fn load(db: &Db) -> Geography {
+        Geography {
+            boroughs: BoundaryIndex::load(db, BoundaryLevel::Borough).await,
+            wards: BoundaryIndex::load(db, BoundaryLevel::Ward).await,
+            lsoas: BoundaryIndex::load(db, BoundaryLevel::Lsoa).await,
+        }
+    }
+
+    /// Assign listings to the borough, ward and LSOA they're in.
+    pub fn fill(&self, properties: &mut [RightmoveProperty]) {
+        for property in properties.iter_mut() {
+            property.borough = self.boroughs.locate_code(property.coordinates);
+            property.ward = self.wards.locate_code(property.coordinates);
+            property.lsoa = self.lsoas.locate_code(property.coordinates);
+        }
+    }
+}
+
+fn cell((longitude, latitude): (f64, f64)) -> (i64, i64) {
+    (
+        (longitude / GRID_CELL_DEGREES).floor() as i64,
+        (latitude / GRID_CELL_DEGREES).floor() as i64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoundaryIndex;
+    use crate::lib::geography::boundary::{Boundary, BoundaryLevel};
+
+    #[test]
+    fn test_locate() {
+        let square = |code: &str, (x, y): (f64, f64), size: f64| Boundary {
+            level: BoundaryLevel::Ward,
+            code: code.to_owned(),
+            name: format!("Ward {}", code),
+            polygons: vec![vec![vec![
+                (x, y),
+                (x + size, y),
+                (x + size, y + size),
+                (x, y + size),
+                (x, y),
+            ]]],
+        };
+        let index = BoundaryIndex::new(vec![
+            square("E05000001", (-0.2, 51.5), 0.1),
+            square("E05000002", (-0.1, 51.5), 0.1),
+        ]);
+        assert_eq!(
+            index.locate_code((-0.15, 51.55)),
+            Some("E05000001".to_owned())
+        );
+        assert_eq!(
+            index.locate_code((-0.05, 51.52)),
+            Some("E05000002".to_owned())
+        );
+        assert_eq!(index.locate_code((0.05, 51.52)), None);
+    }
+}
@@ -0,0 +1,4 @@
+#[allow(dead_code)] // only used by the server
+pub mod borough;
+pub mod boundary;
+pub mod index;
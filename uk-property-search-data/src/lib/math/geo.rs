@@ -0,0 +1,26 @@
+const EARTH_RADIUS_MILES: f64 = 3958.8;
+
+/// Great circle distance in miles between two (longitude, latitude) coordinates.
+pub fn haversine_miles(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (from_longitude, from_latitude) = (from.0.to_radians(), from.1.to_radians());
+    let (to_longitude, to_latitude) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((to_latitude - from_latitude) / 2.0).sin().powi(2)
+        + from_latitude.cos()
+            * to_latitude.cos()
+            * ((to_longitude - from_longitude) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_MILES * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::haversine_miles;
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_haversine_miles() {
+        let kings_cross = (-0.123795, 51.530312);
+        let oxford_circus = (-0.141899, 51.515224);
+        assert_almost_eq!(haversine_miles(kings_cross, oxford_circus), 1.30, 1e-2);
+        assert_eq!(haversine_miles(kings_cross, kings_cross), 0.0);
+    }
+}
@@ -1 +1,4 @@
+pub mod geo;
+pub mod random;
 pub mod stats;
+pub mod tdigest;
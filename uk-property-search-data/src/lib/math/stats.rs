@@ -1,27 +1,381 @@
 use std::f64::NAN;
 
+use super::{random::SplitMix64, tdigest::TDigest};
 use serde::{Deserialize, Serialize};
-use statrs::statistics::{Data, Max, Median, Min, OrderStatistics};
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+use statrs::statistics::{Data, Distribution, Max, Median, Min, OrderStatistics};
+
+// Deciles by default.
+const DEFAULT_PERCENTILES: [f64; 9] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+const DEFAULT_HISTOGRAM_BINS: usize = 20;
+// Fixed so that the same data always gets the same confidence interval.
+const BOOTSTRAP_SEED: u64 = 0x5eed_5eed_5eed_5eed;
+
+// Fields added later default when reading documents stored before them.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct Stats {
     pub min: f64,
     pub q1: f64,
     pub median: f64,
     pub q3: f64,
     pub max: f64,
-    pub count: usize,
+    pub count: usize, // after trimming
+    #[serde(default = "nan")]
+    pub mean: f64,
+    #[serde(default = "nan")]
+    pub std_dev: f64,
+    #[serde(default)]
+    pub percentiles: Vec<Percentile>,
+    #[serde(default)]
+    pub histogram: Histogram,
+    #[serde(default)]
+    pub trimming: Trimming,
+    #[serde(default)]
+    pub num_trimmed: usize, // values left out by trimming
+    #[serde(default)]
+    pub median_ci: Option<ConfidenceInterval>, // None if not bootstrapped
+    #[serde(default = "reliable")]
+    pub reliable: bool, // false if count is below the minimum
+}
+
+/// Bootstrap percentile interval.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ConfidenceInterval {
+    pub level: f64, // e.g. 0.95
+    pub lower: f64,
+    pub upper: f64,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Percentile {
+    pub percentile: f64, // between 0 and 1
+    pub value: f64,
+}
+
+/// Counts in equal width bins from `start`, covering min to max.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Histogram {
+    pub start: f64,
+    pub bin_width: f64,
+    pub counts: Vec<usize>,
+}
+
+/// Which outliers are left out before the stats are worked out.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "mode")]
+pub enum Trimming {
+    #[default]
+    None,
+    // Outside [q1 - k * IQR, q3 + k * IQR].
+    Iqr {
+        k: f64,
+    },
+    // Outside the [lower, upper] percentiles, each between 0 and 1.
+    Percentile {
+        lower: f64,
+        upper: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsOptions {
+    pub percentiles: Vec<f64>,
+    pub histogram_bins: usize,
+    pub trimming: Trimming,
+    pub bootstrap: Option<Bootstrap>,
+    pub min_count: usize,
+    pub below_min_count: BelowMinCount,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bootstrap {
+    pub resamples: usize,
+    pub level: f64,
+}
+
+/// What to do with stats over fewer than the minimum count of values.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BelowMinCount {
+    // Keep the numbers, but mark them unreliable.
+    Flag,
+    // Also replace the numbers with NaN, keeping only the count.
+    Suppress,
+}
+
+fn nan() -> f64 {
+    NAN
+}
+
+fn reliable() -> bool {
+    true
+}
+
+impl BelowMinCount {
+    pub fn parse(s: &str) -> Option<BelowMinCount> {
+        match s.trim() {
+            "flag" => Some(BelowMinCount::Flag),
+            "suppress" => Some(BelowMinCount::Suppress),
+            _ => None,
+        }
+    }
+}
+
+impl Bootstrap {
+    /// Percentile interval of the medians of `resamples` resamples of `values`.
+    fn median_ci(&self, values: &[f64]) -> Option<ConfidenceInterval> {
+        if values.is_empty() || self.resamples == 0 {
+            return None;
+        }
+        let mut rng = SplitMix64::new(BOOTSTRAP_SEED);
+        let mut resample = vec![0f64; values.len()];
+        let medians: Vec<f64> = (0..self.resamples)
+            .map(|_| {
+                for value in resample.iter_mut() {
+                    *value = values[rng.next_index(values.len())];
+                }
+                median_of(&mut resample)
+            })
+            .collect();
+        let mut medians = Data::new(medians);
+        let alpha = (1.0 - self.level) / 2.0;
+        Some(ConfidenceInterval {
+            level: self.level,
+            lower: medians.quantile(alpha),
+            upper: medians.quantile(1.0 - alpha),
+        })
+    }
+}
+
+fn median_of(values: &mut [f64]) -> f64 {
+    let (mid, odd) = (values.len() / 2, values.len() % 2 == 1);
+    let (lower, upper, _) = values.select_nth_unstable_by(mid, f64::total_cmp);
+    if odd {
+        *upper
+    } else {
+        let below = lower.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (below + *upper) / 2.0
+    }
+}
+
+impl Trimming {
+    /// Parse e.g. "none", "iqr:1.5" or "percentile:0.01,0.99".
+    pub fn parse(s: &str) -> Option<Trimming> {
+        let (mode, args) = s.split_once(':').unwrap_or((s, ""));
+        let args: Vec<f64> = args
+            .split(',')
+            .filter(|arg| !arg.trim().is_empty())
+            .map(|arg| arg.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        match (mode.trim(), args.as_slice()) {
+            ("none", []) => Some(Trimming::None),
+            ("iqr", [k]) => Some(Trimming::Iqr { k: *k }),
+            ("percentile", [lower, upper]) if lower < upper => Some(Trimming::Percentile {
+                lower: *lower,
+                upper: *upper,
+            }),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, values: Vec<f64>) -> Vec<f64> {
+        let (lower, upper) = match *self {
+            Trimming::None => return values,
+            Trimming::Iqr { k } => {
+                let mut data = Data::new(values.clone());
+                let (q1, q3) = (data.lower_quartile(), data.upper_quartile());
+                (q1 - k * (q3 - q1), q3 + k * (q3 - q1))
+            }
+            Trimming::Percentile { lower, upper } => {
+                let mut data = Data::new(values.clone());
+                (data.quantile(lower), data.quantile(upper))
+            }
+        };
+        values
+            .into_iter()
+            .filter(|v| *v >= lower && *v <= upper)
+            .collect()
+    }
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        StatsOptions {
+            percentiles: DEFAULT_PERCENTILES.to_vec(),
+            histogram_bins: DEFAULT_HISTOGRAM_BINS,
+            trimming: Trimming::None,
+            bootstrap: None,
+            min_count: 0,
+            below_min_count: BelowMinCount::Flag,
+        }
+    }
+}
+
+impl Histogram {
+    fn new(values: &[f64], min: f64, max: f64, bins: usize) -> Histogram {
+        Histogram::weighted(values.iter().map(|&value| (value, 1)), min, max, bins)
+    }
+
+    // Centroids go in the bin of their mean, so this is only approximate.
+    fn from_digest(digest: &TDigest, bins: usize) -> Histogram {
+        Histogram::weighted(
+            digest
+                .centroids
+                .iter()
+                .map(|c| (c.mean, c.weight.round() as usize)),
+            digest.min,
+            digest.max,
+            bins,
+        )
+    }
+
+    fn weighted(
+        values: impl Iterator<Item = (f64, usize)>,
+        min: f64,
+        max: f64,
+        bins: usize,
+    ) -> Histogram {
+        let mut values = values.peekable();
+        if values.peek().is_none() || bins == 0 {
+            return Histogram::default();
+        }
+        let bin_width = (max - min) / bins as f64;
+        let mut counts = vec![0; bins];
+        for (value, weight) in values {
+            let bin = if bin_width > 0.0 {
+                (((value - min) / bin_width) as usize).min(bins - 1)
+            } else {
+                0
+            };
+            counts[bin] += weight;
+        }
+        Histogram {
+            start: min,
+            bin_width,
+            counts,
+        }
+    }
 }
 
 impl Stats {
     pub fn from_vec<T: Into<f64> + Copy>(vec: &Vec<T>) -> Stats {
-        let mut data: Data<Vec<f64>> = Data::new(vec.iter().map(|v| (*v).into()).collect());
+        Stats::from_vec_with(vec, &StatsOptions::default())
+    }
+
+    pub fn from_vec_with<T: Into<f64> + Copy>(vec: &[T], options: &StatsOptions) -> Stats {
+        let values: Vec<f64> = vec.iter().map(|v| (*v).into()).collect();
+        let num_values = values.len();
+        let values = if values.is_empty() {
+            values
+        } else {
+            options.trimming.apply(values)
+        };
+        let mut data: Data<Vec<f64>> = Data::new(values.clone());
+        let (min, max) = (data.min(), data.max());
         Stats {
-            min: data.min(),
+            min,
             q1: data.lower_quartile(),
             median: data.median(),
             q3: data.upper_quartile(),
-            max: data.max(),
+            max,
             count: data.len(),
+            mean: data.mean().unwrap_or(NAN),
+            std_dev: data.std_dev().unwrap_or(NAN),
+            percentiles: options
+                .percentiles
+                .iter()
+                .map(|&percentile| Percentile {
+                    percentile,
+                    value: data.quantile(percentile),
+                })
+                .collect(),
+            histogram: Histogram::new(&values, min, max, options.histogram_bins),
+            trimming: options.trimming,
+            num_trimmed: num_values - data.len(),
+            median_ci: options
+                .bootstrap
+                .and_then(|bootstrap| bootstrap.median_ci(&values)),
+            reliable: true,
+        }
+        .with_min_count(options)
+    }
+
+    /// Estimated stats from a quantile sketch, through the same trimming, bootstrap and minimum
+    /// count as `from_vec_with`. The values themselves are gone, so trimming bounds come from the
+    /// sketch's quantiles, and the median is bootstrapped from values estimated from it.
+    pub fn from_digest(digest: &TDigest, options: &StatsOptions) -> Stats {
+        let num_values = digest.count;
+        let bounds = match options.trimming {
+            Trimming::None => None,
+            Trimming::Iqr { k } => {
+                let (q1, q3) = (digest.quantile(0.25), digest.quantile(0.75));
+                Some((q1 - k * (q3 - q1), q3 + k * (q3 - q1)))
+            }
+            Trimming::Percentile { lower, upper } => {
+                Some((digest.quantile(lower), digest.quantile(upper)))
+            }
+        };
+        let trimmed;
+        let digest = match bounds {
+            Some((lower, upper)) if num_values > 0 => {
+                trimmed = digest.trimmed(lower, upper);
+                &trimmed
+            }
+            _ => digest,
+        };
+        if digest.count == 0 {
+            return Stats {
+                trimming: options.trimming,
+                num_trimmed: num_values,
+                ..Stats::nan()
+            }
+            .with_min_count(options);
+        }
+        Stats {
+            min: digest.min,
+            q1: digest.quantile(0.25),
+            median: digest.quantile(0.5),
+            q3: digest.quantile(0.75),
+            max: digest.max,
+            count: digest.count,
+            mean: digest.mean(),
+            std_dev: digest.std_dev(),
+            percentiles: options
+                .percentiles
+                .iter()
+                .map(|&percentile| Percentile {
+                    percentile,
+                    value: digest.quantile(percentile),
+                })
+                .collect(),
+            histogram: Histogram::from_digest(digest, options.histogram_bins),
+            trimming: options.trimming,
+            num_trimmed: num_values.saturating_sub(digest.count),
+            median_ci: options
+                .bootstrap
+                .and_then(|bootstrap| bootstrap.median_ci(&digest.estimated_values())),
+            reliable: true,
+        }
+        .with_min_count(options)
+    }
+
+    /// Flag, or suppress, stats over fewer values than the minimum count.
+    pub fn with_min_count(self, options: &StatsOptions) -> Stats {
+        if self.count >= options.min_count {
+            return self;
+        }
+        match options.below_min_count {
+            BelowMinCount::Flag => Stats {
+                reliable: false,
+                ..self
+            },
+            BelowMinCount::Suppress => Stats {
+                count: self.count,
+                trimming: self.trimming,
+                num_trimmed: self.num_trimmed,
+                reliable: false,
+                ..Stats::nan()
+            },
         }
     }
 
@@ -33,13 +387,22 @@ impl Stats {
             q3: NAN,
             max: NAN,
             count: 0,
+            mean: NAN,
+            std_dev: NAN,
+            percentiles: vec![],
+            histogram: Histogram::default(),
+            trimming: Trimming::None,
+            num_trimmed: 0,
+            median_ci: None,
+            reliable: true,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Stats;
+    use super::{BelowMinCount, Bootstrap, Stats, StatsOptions, Trimming};
+    use crate::lib::math::tdigest::{TDigest, DEFAULT_COMPRESSION};
     use statrs::assert_almost_eq;
 
     #[test]
@@ -52,5 +415,105 @@ mod tests {
         assert_almost_eq!(stats.q3, 3.583333333333333, 1e-15);
         assert_eq!(stats.max, 4.0);
         assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean, 2.5);
+        assert_almost_eq!(stats.std_dev, 1.290994448735806, 1e-15);
+        assert_eq!(stats.percentiles.len(), 9);
+        assert_eq!(stats.histogram.counts.iter().sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn test_from_vec_with_trimming() {
+        let v = vec![
+            300000, 320000, 350000, 360000, 380000, 400000, 420000, 17000000,
+        ];
+        let options = StatsOptions {
+            percentiles: vec![0.5],
+            histogram_bins: 2,
+            trimming: Trimming::parse("iqr:1.5").unwrap(),
+            ..StatsOptions::default()
+        };
+        let stats = Stats::from_vec_with(&v, &options);
+        assert_eq!(stats.max, 420000.0);
+        assert_eq!(stats.count, 7);
+        assert_eq!(stats.num_trimmed, 1);
+        assert_eq!(stats.trimming, Trimming::Iqr { k: 1.5 });
+        assert_eq!(stats.histogram.start, 300000.0);
+        assert_eq!(stats.histogram.counts, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_parse_trimming() {
+        assert_eq!(Trimming::parse("none"), Some(Trimming::None));
+        assert_eq!(
+            Trimming::parse("percentile:0.01,0.99"),
+            Some(Trimming::Percentile {
+                lower: 0.01,
+                upper: 0.99
+            })
+        );
+        assert_eq!(Trimming::parse("percentile:0.99,0.01"), None);
+        assert_eq!(Trimming::parse("iqr"), None);
+    }
+
+    #[test]
+    fn test_median_ci() {
+        let v = (1..=100).collect::<Vec<_>>();
+        let options = StatsOptions {
+            bootstrap: Some(Bootstrap {
+                resamples: 500,
+                level: 0.95,
+            }),
+            ..StatsOptions::default()
+        };
+        let stats = Stats::from_vec_with(&v, &options);
+        let ci = stats.median_ci.unwrap();
+        assert!(ci.lower < stats.median && stats.median < ci.upper);
+        assert!(ci.lower > 30.0 && ci.upper < 70.0);
+        // Seeded, so the same data always gets the same interval.
+        assert_eq!(Stats::from_vec_with(&v, &options).median_ci, Some(ci));
+    }
+
+    #[test]
+    fn test_from_digest_with_trimming_and_bootstrap() {
+        let mut v = (1..=100).map(|v| v as f64).collect::<Vec<_>>();
+        v.push(10000.0);
+        let digest = TDigest::from_values(&v, DEFAULT_COMPRESSION);
+        let options = StatsOptions {
+            trimming: Trimming::parse("iqr:1.5").unwrap(),
+            bootstrap: Some(Bootstrap {
+                resamples: 500,
+                level: 0.95,
+            }),
+            ..StatsOptions::default()
+        };
+        let stats = Stats::from_digest(&digest, &options);
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.num_trimmed, 1);
+        assert_eq!(stats.max, 100.0);
+        assert_almost_eq!(stats.mean, 50.5, 1e-9);
+        let ci = stats.median_ci.unwrap();
+        assert!(ci.lower < stats.median && stats.median < ci.upper);
+        assert!(ci.lower > 30.0 && ci.upper < 70.0);
+    }
+
+    #[test]
+    fn test_min_count() {
+        let v = vec![300000, 320000];
+        let options = StatsOptions {
+            min_count: 5,
+            ..StatsOptions::default()
+        };
+        let stats = Stats::from_vec_with(&v, &options);
+        assert!(!stats.reliable);
+        assert_eq!(stats.median, 310000.0);
+
+        let options = StatsOptions {
+            below_min_count: BelowMinCount::Suppress,
+            ..options
+        };
+        let stats = Stats::from_vec_with(&v, &options);
+        assert!(!stats.reliable);
+        assert!(stats.median.is_nan());
+        assert_eq!(stats.count, 2);
     }
 }
@@ -0,0 +1,234 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+// Keeps up to roughly this many centroids, trading size for accuracy.
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// Mergeable quantile sketch (merging t-digest), so that quantiles of combined data can be
+/// estimated from sketches of the parts. Count, sum and extremes are kept exactly.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TDigest {
+    pub compression: f64,
+    pub centroids: Vec<Centroid>, // sorted by mean
+    pub count: usize,
+    pub sum: f64,
+    pub sum_squares: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Centroid {
+    pub mean: f64,
+    pub weight: f64,
+}
+
+impl TDigest {
+    pub fn empty(compression: f64) -> TDigest {
+        TDigest {
+            compression,
+            centroids: vec![],
+            count: 0,
+            sum: 0.0,
+            sum_squares: 0.0,
+            min: f64::NAN,
+            max: f64::NAN,
+        }
+    }
+
+    pub fn from_values<T: Into<f64> + Copy>(values: &[T], compression: f64) -> TDigest {
+        let values: Vec<f64> = values.iter().map(|v| (*v).into()).collect();
+        let centroids = values
+            .iter()
+            .map(|&mean| Centroid { mean, weight: 1.0 })
+            .collect();
+        TDigest {
+            compression,
+            centroids: compress(centroids, compression),
+            count: values.len(),
+            sum: values.iter().sum(),
+            sum_squares: values.iter().map(|v| v * v).sum(),
+            min: values.iter().copied().fold(f64::NAN, f64::min),
+            max: values.iter().copied().fold(f64::NAN, f64::max),
+        }
+    }
+
+    /// Combine sketches as if they had been built from all of their values together.
+    pub fn merge<'a>(digests: impl IntoIterator<Item = &'a TDigest>, compression: f64) -> TDigest {
+        let mut merged = TDigest::empty(compression);
+        let mut centroids = vec![];
+        for digest in digests {
+            centroids.extend_from_slice(&digest.centroids);
+            merged.count += digest.count;
+            merged.sum += digest.sum;
+            merged.sum_squares += digest.sum_squares;
+            merged.min = merged.min.min(digest.min);
+            merged.max = merged.max.max(digest.max);
+        }
+        merged.centroids = compress(centroids, compression);
+        merged
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            f64::NAN
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    /// Sample standard deviation, as statrs works it out.
+    pub fn std_dev(&self) -> f64 {
+        if self.count < 2 {
+            return f64::NAN;
+        }
+        let n = self.count as f64;
+        ((self.sum_squares - self.sum * self.sum / n) / (n - 1.0))
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// Estimate the q-th quantile, for q between 0 and 1, interpolating between centroid means.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let (first, last) = match (self.centroids.first(), self.centroids.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return f64::NAN,
+        };
+        let total: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let target = q.clamp(0.0, 1.0) * total;
+        if target <= first.weight / 2.0 {
+            return interpolate(self.min, first.mean, target / (first.weight / 2.0));
+        }
+        if target >= total - last.weight / 2.0 {
+            let from = total - last.weight / 2.0;
+            return interpolate(last.mean, self.max, (target - from) / (last.weight / 2.0));
+        }
+        // Each centroid's mean sits in the middle of the cumulative weight it covers.
+        let mut cumulative = 0.0;
+        for (left, right) in self.centroids.iter().zip(self.centroids.iter().skip(1)) {
+            let left_mid = cumulative + left.weight / 2.0;
+            let right_mid = cumulative + left.weight + right.weight / 2.0;
+            if target < right_mid {
+                return interpolate(
+                    left.mean,
+                    right.mean,
+                    (target - left_mid) / (right_mid - left_mid),
+                );
+            }
+            cumulative += left.weight;
+        }
+        last.mean
+    }
+
+    /// Sketch of only the values between `lower` and `upper`, keeping whole centroids whose
+    /// mean is in range. The sum of squares only reflects the spread between centroids.
+    pub fn trimmed(&self, lower: f64, upper: f64) -> TDigest {
+        let centroids = self
+            .centroids
+            .iter()
+            .filter(|c| c.mean >= lower && c.mean <= upper)
+            .copied()
+            .collect::<Vec<_>>();
+        if centroids.is_empty() {
+            return TDigest::empty(self.compression);
+        }
+        TDigest {
+            compression: self.compression,
+            count: centroids.iter().map(|c| c.weight).sum::<f64>().round() as usize,
+            sum: centroids.iter().map(|c| c.mean * c.weight).sum(),
+            sum_squares: centroids.iter().map(|c| c.mean * c.mean * c.weight).sum(),
+            // Where the extremes were trimmed, the outermost centroids kept are the best guess.
+            min: if self.min >= lower {
+                self.min
+            } else {
+                centroids[0].mean
+            },
+            max: if self.max <= upper {
+                self.max
+            } else {
+                centroids[centroids.len() - 1].mean
+            },
+            centroids,
+        }
+    }
+
+    /// As many evenly spaced quantiles as there are values, standing in for the values.
+    pub fn estimated_values(&self) -> Vec<f64> {
+        let n = self.count as f64;
+        (0..self.count)
+            .map(|i| self.quantile((i as f64 + 0.5) / n))
+            .collect()
+    }
+}
+
+fn interpolate(from: f64, to: f64, t: f64) -> f64 {
+    from + (to - from) * t.clamp(0.0, 1.0)
+}
+
+/// Merge neighbouring centroids as far as the k1 scale function allows, which keeps centroids
+/// small near the tails where accuracy matters most.
+fn compress(mut centroids: Vec<Centroid>, compression: f64) -> Vec<Centroid> {
+    centroids.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+    let total: f64 = centroids.iter().map(|c| c.weight).sum();
+    let k = |q: f64| compression / (2.0 * PI) * (2.0 * q.min(1.0) - 1.0).asin();
+
+    let mut centroids = centroids.into_iter();
+    let mut current = match centroids.next() {
+        Some(first) => first,
+        None => return vec![],
+    };
+    let mut compressed = vec![];
+    let mut cumulative = 0.0; // weight before the current centroid
+    let mut k_lower = k(0.0);
+    for next in centroids {
+        let weight = current.weight + next.weight;
+        if k((cumulative + weight) / total) - k_lower <= 1.0 {
+            current = Centroid {
+                mean: current.mean + (next.mean - current.mean) * next.weight / weight,
+                weight,
+            };
+        } else {
+            cumulative += current.weight;
+            k_lower = k(cumulative / total);
+            compressed.push(current);
+            current = next;
+        }
+    }
+    compressed.push(current);
+    compressed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TDigest, DEFAULT_COMPRESSION};
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_quantile() {
+        let digest = TDigest::from_values(&[1, 2, 3, 4], DEFAULT_COMPRESSION);
+        assert_eq!(digest.quantile(0.0), 1.0);
+        assert_eq!(digest.quantile(0.5), 2.5);
+        assert_eq!(digest.quantile(1.0), 4.0);
+        assert_eq!(digest.mean(), 2.5);
+        assert_almost_eq!(digest.std_dev(), 1.290994448735806, 1e-12);
+    }
+
+    #[test]
+    fn test_merge() {
+        let values = (1..=10000).map(|v| v as f64).collect::<Vec<_>>();
+        let parts = values
+            .chunks(1000)
+            .map(|chunk| TDigest::from_values(chunk, DEFAULT_COMPRESSION))
+            .collect::<Vec<_>>();
+
+        let merged = TDigest::merge(&parts, DEFAULT_COMPRESSION);
+        assert_eq!(merged.count, 10000);
+        assert_eq!((merged.min, merged.max), (1.0, 10000.0));
+        assert!(merged.centroids.len() <= 2 * DEFAULT_COMPRESSION as usize);
+        for q in [0.01, 0.25, 0.5, 0.75, 0.99] {
+            assert!((merged.quantile(q) - q * 10000.0).abs() < 0.01 * 10000.0);
+        }
+    }
+}
@@ -1,5 +1,10 @@
+#[allow(dead_code)] // only used by the server
+pub mod finance;
+pub mod geography;
 pub mod math;
+pub mod postcode;
 pub mod property;
+pub mod routing;
 pub mod school;
 pub mod tube;
 pub mod util;
@@ -0,0 +1,228 @@
+use crate::lib::{
+    math::geo::haversine_miles,
+    util::{db::Db, ext::MongoCollectionExt},
+};
+use anyhow::{bail, Result};
+use itertools::{multizip, Itertools};
+use mongodb::bson::doc;
+use polars::{io::SerReader, prelude::CsvReader};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Keeps each `$in` query well under the maximum document size.
+const LOOKUP_BATCH_SIZE: usize = 10_000;
+
+// Roughly 150m by 220m in London, holding a few dozen postcodes.
+const GRID_CELL_DEGREES: f64 = 0.002;
+// Points further than this many cells from any postcode aren't reverse geocoded.
+const MAX_SNAP_CELLS: i64 = 3;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Postcode {
+    #[serde(rename = "_id")]
+    pub postcode: String, // e.g. N1 9AL
+    pub outcode: String,         // district, e.g. N1
+    pub sector: String,          // e.g. N1 9
+    pub coordinates: (f64, f64), // (longitude, latitude)
+}
+
+impl Postcode {
+    pub fn new(postcode: &str, coordinates: (f64, f64)) -> Option<Postcode> {
+        let postcode = normalise(postcode)?;
+        let (outcode, incode) = postcode.split_once(' ')?;
+        Some(Postcode {
+            outcode: outcode.to_owned(),
+            sector: format!("{} {}", outcode, &incode[..1]),
+            postcode,
+            coordinates,
+        })
+    }
+
+    /// Read postcodes with coordinates from a csv with `pcds`, `lat` and `long` columns.
+    pub fn read_csv(path: &str) -> Result<Vec<Postcode>> {
+        let df = CsvReader::from_path(path)?.finish()?;
+        let postcodes = df.column("pcds")?.utf8()?;
+        let latitudes = df.column("lat")?.f64()?;
+        let longitudes = df.column("long")?.f64()?;
+        Ok(multizip((postcodes, longitudes, latitudes))
+            .filter_map(|(postcode, longitude, latitude)| {
+                Postcode::new(postcode?, (longitude?, latitude?))
+            })
+            .collect())
+    }
+}
+
+/// Upper case with a single space before the incode, e.g. "n19al" becomes "N1 9AL", or None if
+/// it isn't shaped like a postcode.
+pub fn normalise(postcode: &str) -> Option<String> {
+    let compact = postcode
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<String>();
+    if !(5..=7).contains(&compact.len()) || !compact.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let (outcode, incode) = compact.split_at(compact.len() - 3);
+    let mut incode_chars = incode.chars();
+    let is_incode = incode_chars.next().is_some_and(|c| c.is_ascii_digit())
+        && incode_chars.all(|c| c.is_ascii_alphabetic());
+    let is_outcode = outcode.starts_with(|c: char| c.is_ascii_alphabetic());
+    (is_incode && is_outcode).then_some(format!("{} {}", outcode, incode))
+}
+
+/// Anchored regex matching postcodes starting with `prefix`, whether or not it was typed with
+/// the space. Only letters and digits are kept, so nothing needs escaping.
+#[allow(dead_code)] // only used by the server
+pub fn prefix_regex(prefix: &str) -> String {
+    let chars = prefix
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase().to_string())
+        .collect_vec();
+    format!("^{}", chars.join(" ?"))
+}
+
+/// Coordinates of postcodes, fetched from the postcodes collection for geocoding in tasks.
+pub struct PostcodeLookup {
+    coordinates: HashMap<String, (f64, f64)>, // by normalised postcode
+}
+
+impl PostcodeLookup {
+    /// Look up the given postcodes, skipping any that aren't valid or aren't known.
+    pub async fn load<'a>(
+        db: &Db,
+        postcodes: impl IntoIterator<Item = &'a str>,
+    ) -> Result<PostcodeLookup> {
+        let normalised = postcodes
+            .into_iter()
+            .filter_map(normalise)
+            .unique()
+            .collect_vec();
+        let mut coordinates = HashMap::new();
+        if normalised.is_empty() {
+            return Ok(PostcodeLookup { coordinates });
+        }
+        if db.postcodes().estimated_document_count(None).await? == 0 {
+            bail!("No postcodes found, run the update-postcodes task first!");
+        }
+        for batch in normalised.chunks(LOOKUP_BATCH_SIZE) {
+            let found = db
+                .postcodes()
+                .find_filtered_to_vec(doc! {"_id": {"$in": batch}}, None)
+                .await;
+            coordinates.extend(
+                found
+                    .into_iter()
+                    .map(|postcode| (postcode.postcode, postcode.coordinates)),
+            );
+        }
+        Ok(PostcodeLookup { coordinates })
+    }
+
+    pub fn coordinates(&self, postcode: &str) -> Option<(f64, f64)> {
+        self.coordinates.get(&normalise(postcode)?).copied()
+    }
+}
+
+/// Postcodes in an area, gridded for reverse geocoding in tasks.
+pub struct PostcodeIndex {
+    postcodes: Vec<Postcode>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl PostcodeIndex {
+    pub fn new(postcodes: Vec<Postcode>) -> PostcodeIndex {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, postcode) in postcodes.iter().enumerate() {
+            cells.entry(cell(postcode.coordinates)).or_default().push(i);
+        }
+        PostcodeIndex { postcodes, cells }
+    }
+
+    /// Load postcodes between the south west and north east corners from the postcodes
+    /// collection, with a margin so that points near the edges still find their nearest.
+    pub async fn load(
+        db: &Db,
+        (south_west, north_east): ((f64, f64), (f64, f64)),
+    ) -> Result<PostcodeIndex> {
+        if db.postcodes().estimated_document_count(None).await? == 0 {
+            bail!("No postcodes found, run the update-postcodes task first!");
+        }
+        let margin = MAX_SNAP_CELLS as f64 * GRID_CELL_DEGREES;
+        let postcodes = db
+            .postcodes()
+            .find_filtered_to_vec(
+                doc! {"coordinates": {"$geoWithin": {"$box": [
+                    [south_west.0 - margin, south_west.1 - margin],
+                    [north_east.0 + margin, north_east.1 + margin],
+                ]}}},
+                None,
+            )
+            .await;
+        Ok(PostcodeIndex::new(postcodes))
+    }
+
+    pub fn num_postcodes(&self) -> usize {
+        self.postcodes.len()
+    }
+
+    /// Nearest postcode to the point.
+    pub fn nearest(&self, point: (f64, f64)) -> Option<&Postcode> {
+        let (x, y) = cell(point);
+        // The first ring of cells with any postcodes in it is near enough.
+        (1..=MAX_SNAP_CELLS).find_map(|radius| {
+            (x - radius..=x + radius)
+                .flat_map(|cx| (y - radius..=y + radius).map(move |cy| (cx, cy)))
+                .filter_map(|c| self.cells.get(&c))
+                .flatten()
+                .map(|&i| &self.postcodes[i])
+                .min_by(|a, b| {
+                    haversine_miles(point, a.coordinates)
+                        .total_cmp(&haversine_miles(point, b.coordinates))
+                })
+        })
+    }
+}
+
+fn cell((longitude, latitude): (f64, f64)) -> (i64, i64) {
+    (
+        (longitude / GRID_CELL_DEGREES).floor() as i64,
+        (latitude / GRID_CELL_DEGREES).floor() as i64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalise, prefix_regex, Postcode, PostcodeIndex};
+
+    #[test]
+    fn test_normalise() {
+        assert_eq!(normalise("n19al"), Some("N1 9AL".to_owned()));
+        assert_eq!(normalise(" EC1A  1BB "), Some("EC1A 1BB".to_owned()));
+        assert_eq!(normalise("W1"), None);
+        assert_eq!(normalise("N1 9A1"), None);
+        assert_eq!(normalise("91 9AL"), None);
+
+        let postcode = Postcode::new("sw1a 2aa", (-0.127695, 51.50354)).unwrap();
+        assert_eq!(postcode.postcode, "SW1A 2AA");
+        assert_eq!(postcode.outcode, "SW1A");
+        assert_eq!(postcode.sector, "SW1A 2");
+
+        assert_eq!(prefix_regex("n1 9"), "^N ?1 ?9");
+        assert_eq!(prefix_regex("n1.*"), "^N ?1");
+    }
+
+    #[test]
+    fn test_nearest() {
+        let index = PostcodeIndex::new(vec![
+            Postcode::new("N1 9AL", (-0.123795, 51.530312)).unwrap(),
+            Postcode::new("NW1 2DU", (-0.133068, 51.528055)).unwrap(),
+        ]);
+        let nearest = |point| index.nearest(point).map(|p| p.postcode.as_str());
+        assert_eq!(nearest((-0.125, 51.531)), Some("N1 9AL"));
+        assert_eq!(nearest((-0.132, 51.528)), Some("NW1 2DU"));
+        assert_eq!(nearest((-0.1, 51.6)), None);
+    }
+}
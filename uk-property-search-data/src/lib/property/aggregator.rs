@@ -1,71 +1,447 @@
-use std::cmp::min;
+use std::{
+    cmp::min,
+    collections::{BTreeMap, HashSet},
+};
 
-use crate::lib::math::stats::Stats;
+use crate::lib::{
+    math::{
+        stats::{
+            BelowMinCount, Bootstrap, ConfidenceInterval, Percentile, Stats, StatsOptions, Trimming,
+        },
+        tdigest::{TDigest, DEFAULT_COMPRESSION},
+    },
+    util::globals::Globals,
+};
 
-use super::{estate_agents::rightmove::RightmoveProperty, property::PropertyStats};
+use super::{
+    distance::{DistanceRingStats, DistanceRings},
+    estate_agents::rightmove::RightmoveProperty,
+    listing_details::Tenure,
+    price::PriceQualifier,
+    property::{EpcBandSplit, PropertyStats, PropertyType, TenureSplit},
+    rollup::PropertySketch,
+};
+use anyhow::{anyhow, Result};
 use chrono::Utc;
 use itertools::Itertools;
 
 pub struct BuyAndRentPropertyStats {
     pub buy_stats: PropertyStats,
     pub rent_stats: PropertyStats,
+    pub buy_stats_by_type: BTreeMap<PropertyType, PropertyStats>,
+    pub rent_stats_by_type: BTreeMap<PropertyType, PropertyStats>,
 }
 
-pub struct PropertyAggregator {}
+// Values the stats and sketches are worked out from, leaving out excluded price qualifiers.
+struct PropertyValues {
+    num_listings: usize,
+    num_transacted: usize,
+    num_reduced: usize,
+    prices: Vec<f64>,
+    listed_days: Vec<f64>,
+    square_feet: Vec<f64>,
+    square_feet_with_imputed: Vec<f64>,
+    price_per_square_foot: Vec<f64>,
+    price_per_square_foot_with_imputed: Vec<f64>,
+    tenure: TenureSplit,
+    service_charge: Vec<f64>,
+    ground_rent: Vec<f64>,
+    epc_band: EpcBandSplit,
+    prices_by_qualifier: BTreeMap<PriceQualifier, Vec<f64>>, // before exclusion
+}
+
+pub struct PropertyAggregator {
+    excluded_price_qualifiers: HashSet<PriceQualifier>,
+    stats_options: StatsOptions,
+}
 
 impl PropertyAggregator {
+    pub fn new(globals: &Globals) -> Result<PropertyAggregator> {
+        Ok(PropertyAggregator {
+            excluded_price_qualifiers: PriceQualifier::parse_list(
+                &globals
+                    .properties
+                    .get_string("property.price.excluded.qualifiers"),
+            )?
+            .into_iter()
+            .collect(),
+            stats_options: StatsOptions {
+                percentiles: globals
+                    .properties
+                    .get_string("property.stats.percentiles")
+                    .split(',')
+                    .map(|p| {
+                        p.trim()
+                            .parse()
+                            .map_err(|_| anyhow!("[{}] is not a valid percentile!", p))
+                    })
+                    .collect::<Result<_>>()?,
+                histogram_bins: globals.properties.get_int("property.stats.histogram.bins")
+                    as usize,
+                trimming: {
+                    let trimming = globals.properties.get_string("property.stats.trimming");
+                    Trimming::parse(&trimming).ok_or_else(|| {
+                        anyhow!("[{}] is not a valid stats trimming mode!", trimming)
+                    })?
+                },
+                bootstrap: Some(Bootstrap {
+                    resamples: globals
+                        .properties
+                        .get_int("property.stats.bootstrap.resamples")
+                        as usize,
+                    level: globals
+                        .properties
+                        .get_float("property.stats.bootstrap.confidence"),
+                }),
+                min_count: globals.properties.get_int("property.stats.min.count") as usize,
+                below_min_count: {
+                    let below_min_count = globals
+                        .properties
+                        .get_string("property.stats.below.min.count");
+                    BelowMinCount::parse(&below_min_count).ok_or_else(|| {
+                        anyhow!(
+                            "[{}] is not a valid stats below min count mode!",
+                            below_min_count
+                        )
+                    })?
+                },
+            },
+        })
+    }
+
+    #[cfg(test)]
+    pub fn with_options(
+        excluded_price_qualifiers: HashSet<PriceQualifier>,
+        stats_options: StatsOptions,
+    ) -> PropertyAggregator {
+        PropertyAggregator {
+            excluded_price_qualifiers,
+            stats_options,
+        }
+    }
+
     pub fn calculate_buy_and_rent_property_stats(
         &self,
         buy_properties: Vec<RightmoveProperty>,
         rent_properties: Vec<RightmoveProperty>,
     ) -> BuyAndRentPropertyStats {
+        // Compare like with like, so yields are also worked out within each property type.
+        let mut buy_properties_by_type = buy_properties
+            .iter()
+            .cloned()
+            .into_group_map_by(|p| p.property_type);
+        let mut rent_properties_by_type = rent_properties
+            .iter()
+            .cloned()
+            .into_group_map_by(|p| p.property_type);
+        let property_types = buy_properties_by_type
+            .keys()
+            .chain(rent_properties_by_type.keys())
+            .copied()
+            .unique()
+            .collect_vec();
+
+        let mut buy_stats_by_type = BTreeMap::new();
+        let mut rent_stats_by_type = BTreeMap::new();
+        for property_type in property_types {
+            let (buy_stats, rent_stats) = self.calculate_buy_and_rent_pair(
+                buy_properties_by_type
+                    .remove(&property_type)
+                    .unwrap_or_default(),
+                rent_properties_by_type
+                    .remove(&property_type)
+                    .unwrap_or_default(),
+            );
+            buy_stats_by_type.insert(property_type, buy_stats);
+            rent_stats_by_type.insert(property_type, rent_stats);
+        }
+
+        let (buy_stats, rent_stats) =
+            self.calculate_buy_and_rent_pair(buy_properties, rent_properties);
+        BuyAndRentPropertyStats {
+            buy_stats,
+            rent_stats,
+            buy_stats_by_type,
+            rent_stats_by_type,
+        }
+    }
+
+    /// Stats within each ring around the station, with rental yields paired up per ring.
+    pub fn calculate_buy_and_rent_ring_stats(
+        &self,
+        rings: &DistanceRings,
+        station: (f64, f64),
+        buy_properties: &[RightmoveProperty],
+        rent_properties: &[RightmoveProperty],
+    ) -> (Vec<DistanceRingStats>, Vec<DistanceRingStats>) {
+        rings
+            .bounds()
+            .into_iter()
+            .zip(rings.split(station, buy_properties))
+            .zip(rings.split(station, rent_properties))
+            .map(
+                |(((from_miles, to_miles), buy_properties), rent_properties)| {
+                    let (buy_stats, rent_stats) =
+                        self.calculate_buy_and_rent_pair(buy_properties, rent_properties);
+                    let ring_stats = |stats| DistanceRingStats {
+                        from_miles,
+                        to_miles,
+                        stats,
+                    };
+                    (ring_stats(buy_stats), ring_stats(rent_stats))
+                },
+            )
+            .unzip()
+    }
+
+    /// Asking price stats, leaving out listings with excluded price qualifiers.
+    pub fn calculate_asking_price_stats(&self, properties: Vec<RightmoveProperty>) -> Stats {
+        self.calculate_partial_stats(properties).price
+    }
+
+    fn calculate_buy_and_rent_pair(
+        &self,
+        buy_properties: Vec<RightmoveProperty>,
+        rent_properties: Vec<RightmoveProperty>,
+    ) -> (PropertyStats, PropertyStats) {
         let buy_stats = self.calculate_partial_stats(buy_properties);
         let rent_stats = self.calculate_partial_stats(rent_properties);
-        let rental_yield = self.calculate_rental_yield(buy_stats, rent_stats);
+        let rental_yield = self.calculate_rental_yield(&buy_stats, &rent_stats);
 
-        BuyAndRentPropertyStats {
-            buy_stats: PropertyStats {
-                rental_yield: rental_yield,
+        (
+            PropertyStats {
+                rental_yield: rental_yield.clone(),
                 ..buy_stats
             },
-            rent_stats: PropertyStats {
+            PropertyStats {
                 rental_yield: rental_yield,
                 ..rent_stats
             },
-        }
+        )
     }
 
     fn calculate_partial_stats(&self, properties: Vec<RightmoveProperty>) -> PropertyStats {
-        let percent_transacted_value = if properties.is_empty() {
+        let values = self.property_values(properties);
+        let percent = |count: usize| {
+            if values.num_listings == 0 {
+                0f64
+            } else {
+                count as f64 / values.num_listings as f64
+            }
+        };
+        let percent_square_feet_imputed = if values.square_feet_with_imputed.is_empty() {
             0f64
         } else {
-            (properties.iter().filter(|p| p.transacted).count() as f64) / (properties.len() as f64)
+            1.0 - (values.square_feet.len() as f64) / (values.square_feet_with_imputed.len() as f64)
         };
+        let stats = |vec: &Vec<f64>| Stats::from_vec_with(vec, &self.stats_options);
 
-        let prices = properties.iter().map(|p| p.price).collect_vec();
-        let listed_days = properties
-            .iter()
-            .map(|p| (Utc::now() - p.post_date).num_days() as f64)
-            .collect_vec();
-        let percent_transacted = properties
-            .iter()
-            .map(|_| percent_transacted_value)
-            .collect_vec();
-        let square_feet = properties
-            .iter()
-            .filter_map(|p| p.square_feet)
-            .collect_vec();
+        PropertyStats {
+            price: stats(&values.prices),
+            listed_days: stats(&values.listed_days),
+            percent_transacted: percent(values.num_transacted),
+            percent_reduced: percent(values.num_reduced),
+            square_feet: stats(&values.square_feet),
+            square_feet_with_imputed: stats(&values.square_feet_with_imputed),
+            price_per_square_foot: stats(&values.price_per_square_foot),
+            price_per_square_foot_with_imputed: stats(&values.price_per_square_foot_with_imputed),
+            percent_square_feet_imputed,
+            rental_yield: Stats::nan(),
+            tenure: values.tenure,
+            service_charge: stats(&values.service_charge),
+            ground_rent: stats(&values.ground_rent),
+            epc_band: values.epc_band,
+            price_by_qualifier: values
+                .prices_by_qualifier
+                .iter()
+                .map(|(&qualifier, prices)| (qualifier, stats(prices)))
+                .collect(),
+        }
+    }
+
+    /// Sketch of the same values as `PropertyStats`, so that searches can be combined later.
+    pub fn calculate_sketch(&self, properties: Vec<RightmoveProperty>) -> PropertySketch {
+        let values = self.property_values(properties);
+        let digest = |vec: &Vec<f64>| TDigest::from_values(vec, DEFAULT_COMPRESSION);
+        PropertySketch {
+            num_listings: values.num_listings,
+            num_transacted: values.num_transacted,
+            num_reduced: values.num_reduced,
+            price: digest(&values.prices),
+            listed_days: digest(&values.listed_days),
+            square_feet: digest(&values.square_feet),
+            square_feet_with_imputed: digest(&values.square_feet_with_imputed),
+            price_per_square_foot: digest(&values.price_per_square_foot),
+            price_per_square_foot_with_imputed: digest(&values.price_per_square_foot_with_imputed),
+            service_charge: digest(&values.service_charge),
+            ground_rent: digest(&values.ground_rent),
+            tenure: values.tenure,
+            epc_band: values.epc_band,
+            price_by_qualifier: values
+                .prices_by_qualifier
+                .iter()
+                .map(|(&qualifier, prices)| (qualifier, digest(prices)))
+                .collect(),
+        }
+    }
+
+    #[allow(dead_code)] // only used by the server
+    pub fn merge_sketches(&self, sketches: &[&PropertySketch]) -> PropertySketch {
+        PropertySketch::merge(sketches, DEFAULT_COMPRESSION)
+    }
+
+    /// Estimated stats for combined searches, from their merged sketches.
+    #[allow(dead_code)] // only used by the server
+    pub fn calculate_buy_and_rent_rollup_stats(
+        &self,
+        buy_sketch: &PropertySketch,
+        rent_sketch: &PropertySketch,
+    ) -> (PropertyStats, PropertyStats) {
+        let buy_stats = self.calculate_sketch_stats(buy_sketch);
+        let rent_stats = self.calculate_sketch_stats(rent_sketch);
+        let rental_yield = self.calculate_rental_yield(&buy_stats, &rent_stats);
+        (
+            PropertyStats {
+                rental_yield: rental_yield.clone(),
+                ..buy_stats
+            },
+            PropertyStats {
+                rental_yield,
+                ..rent_stats
+            },
+        )
+    }
+
+    #[allow(dead_code)] // only used by the server
+    fn calculate_sketch_stats(&self, sketch: &PropertySketch) -> PropertyStats {
+        let percent = |count: usize| {
+            if sketch.num_listings == 0 {
+                0f64
+            } else {
+                count as f64 / sketch.num_listings as f64
+            }
+        };
+        let percent_square_feet_imputed = if sketch.square_feet_with_imputed.count == 0 {
+            0f64
+        } else {
+            1.0 - (sketch.square_feet.count as f64) / (sketch.square_feet_with_imputed.count as f64)
+        };
+        let stats = |digest: &TDigest| Stats::from_digest(digest, &self.stats_options);
 
         PropertyStats {
-            price: Stats::from_vec(&prices),
-            listed_days: Stats::from_vec(&listed_days),
-            percent_transacted: Stats::from_vec(&percent_transacted),
-            square_feet: Stats::from_vec(&square_feet),
+            price: stats(&sketch.price),
+            listed_days: stats(&sketch.listed_days),
+            percent_transacted: percent(sketch.num_transacted),
+            percent_reduced: percent(sketch.num_reduced),
+            square_feet: stats(&sketch.square_feet),
+            square_feet_with_imputed: stats(&sketch.square_feet_with_imputed),
+            price_per_square_foot: stats(&sketch.price_per_square_foot),
+            price_per_square_foot_with_imputed: stats(&sketch.price_per_square_foot_with_imputed),
+            percent_square_feet_imputed,
             rental_yield: Stats::nan(),
+            tenure: sketch.tenure,
+            service_charge: stats(&sketch.service_charge),
+            ground_rent: stats(&sketch.ground_rent),
+            epc_band: sketch.epc_band,
+            price_by_qualifier: sketch
+                .price_by_qualifier
+                .iter()
+                .map(|(&qualifier, digest)| (qualifier, stats(digest)))
+                .collect(),
+        }
+    }
+
+    fn property_values(&self, properties: Vec<RightmoveProperty>) -> PropertyValues {
+        let prices_by_qualifier = properties
+            .iter()
+            .map(|p| (p.price_qualifier, p.price as f64))
+            .into_group_map()
+            .into_iter()
+            .collect();
+        let properties = properties
+            .into_iter()
+            .filter(|p| !self.excluded_price_qualifiers.contains(&p.price_qualifier))
+            .collect_vec();
+
+        let mut tenure = TenureSplit::default();
+        for details in properties.iter().filter_map(|p| p.details.as_ref()) {
+            match details.tenure {
+                Some(Tenure::Freehold) => tenure.freehold += 1,
+                Some(Tenure::Leasehold) => tenure.leasehold += 1,
+                Some(Tenure::ShareOfFreehold) => tenure.share_of_freehold += 1,
+                Some(Tenure::Commonhold) => tenure.commonhold += 1,
+                None => tenure.unknown += 1,
+            }
+        }
+        let mut epc_band = EpcBandSplit::default();
+        for property in &properties {
+            epc_band.add(
+                property
+                    .epc
+                    .as_ref()
+                    .map(|epc| epc.current_rating)
+                    .or_else(|| property.details.as_ref().and_then(|d| d.epc_band)),
+            );
+        }
+
+        PropertyValues {
+            num_listings: properties.len(),
+            num_transacted: properties.iter().filter(|p| p.transacted).count(),
+            num_reduced: properties
+                .iter()
+                .filter(|p| p.reduced_date.is_some())
+                .count(),
+            prices: properties.iter().map(|p| p.price as f64).collect_vec(),
+            listed_days: properties
+                .iter()
+                .map(|p| (Utc::now() - p.post_date).num_days() as f64)
+                .collect_vec(),
+            square_feet: properties
+                .iter()
+                .filter_map(|p| p.known_square_feet().map(|s| s as f64))
+                .collect_vec(),
+            square_feet_with_imputed: properties
+                .iter()
+                .filter_map(|p| {
+                    p.known_square_feet()
+                        .or(p.imputed_square_feet)
+                        .map(|s| s as f64)
+                })
+                .collect_vec(),
+            price_per_square_foot: properties
+                .iter()
+                .filter_map(|p| p.known_square_feet().map(|s| p.price as f64 / s as f64))
+                .collect_vec(),
+            price_per_square_foot_with_imputed: properties
+                .iter()
+                .filter_map(|p| {
+                    p.known_square_feet()
+                        .or(p.imputed_square_feet)
+                        .map(|s| p.price as f64 / s as f64)
+                })
+                .collect_vec(),
+            tenure,
+            service_charge: properties
+                .iter()
+                .filter_map(|p| p.details.as_ref().and_then(|d| d.annual_service_charge))
+                .collect_vec(),
+            ground_rent: properties
+                .iter()
+                .filter_map(|p| p.details.as_ref().and_then(|d| d.annual_ground_rent))
+                .collect_vec(),
+            epc_band,
+            prices_by_qualifier,
         }
     }
 
-    fn calculate_rental_yield(&self, buy_stats: PropertyStats, rent_stats: PropertyStats) -> Stats {
+    // Rents and prices aren't paired by listing, so each yield is the ratio of the same statistic
+    // for both. There are no individual yields to spread, so std dev and histogram are left empty.
+    fn calculate_rental_yield(
+        &self,
+        buy_stats: &PropertyStats,
+        rent_stats: &PropertyStats,
+    ) -> Stats {
+        let (rent, buy) = (&rent_stats.price, &buy_stats.price);
         Stats {
             min: rent_stats.price.min * 12.0 / buy_stats.price.min,
             q1: rent_stats.price.q1 * 12.0 / buy_stats.price.q1,
@@ -73,48 +449,64 @@ impl PropertyAggregator {
             q3: rent_stats.price.q3 * 12.0 / buy_stats.price.q3,
             max: rent_stats.price.max * 12.0 / buy_stats.price.max,
             count: min(rent_stats.price.count, buy_stats.price.count),
+            mean: rent.mean * 12.0 / buy.mean,
+            percentiles: rent
+                .percentiles
+                .iter()
+                .zip(&buy.percentiles)
+                .filter(|(rent, buy)| rent.percentile == buy.percentile)
+                .map(|(rent, buy)| Percentile {
+                    percentile: rent.percentile,
+                    value: rent.value * 12.0 / buy.value,
+                })
+                .collect(),
+            trimming: buy_stats.price.trimming,
+            // Conservative: cheapest rents over dearest prices, and vice versa.
+            median_ci: rent_stats
+                .price
+                .median_ci
+                .zip(buy_stats.price.median_ci)
+                .map(|(rent, buy)| ConfidenceInterval {
+                    level: rent.level,
+                    lower: rent.lower * 12.0 / buy.upper,
+                    upper: rent.upper * 12.0 / buy.lower,
+                }),
+            ..Stats::nan()
         }
+        .with_min_count(&self.stats_options)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::lib::{
-        math::stats::Stats,
-        property::{aggregator::PropertyAggregator, estate_agents::rightmove::RightmoveProperty},
+        math::stats::{Stats, StatsOptions},
+        property::{
+            aggregator::PropertyAggregator, estate_agents::rightmove::RightmoveProperty,
+            price::PriceQualifier,
+        },
     };
     use chrono::{TimeZone, Utc};
+    use std::collections::HashSet;
 
     #[tokio::test]
     async fn test_get_stats() {
-        let aggregator = PropertyAggregator {};
+        let aggregator = PropertyAggregator {
+            excluded_price_qualifiers: HashSet::new(),
+            stats_options: StatsOptions::default(),
+        };
         let properties = vec![
             RightmoveProperty {
-                id: 105233438,
-                coordinates: (-0.122191, 51.53419),
-                price: 3600000,
-                square_feet: None,
                 post_date: Utc.with_ymd_and_hms(2021, 4, 8, 19, 28, 38).unwrap(),
-                reduced_date: None,
-                transacted: false,
+                ..RightmoveProperty::test_listing(105233438, (-0.122191, 51.53419), 3600000)
             },
             RightmoveProperty {
-                id: 136850450,
-                coordinates: (-0.125412, 51.529891),
-                price: 3550000,
-                square_feet: None,
                 post_date: Utc.with_ymd_and_hms(2023, 7, 3, 0, 33, 55).unwrap(),
-                reduced_date: None,
-                transacted: false,
+                ..RightmoveProperty::test_listing(136850450, (-0.125412, 51.529891), 3550000)
             },
             RightmoveProperty {
-                id: 131749937,
-                coordinates: (-0.125412, 51.529891),
-                price: 1500000,
-                square_feet: None,
                 post_date: Utc.with_ymd_and_hms(2023, 2, 15, 21, 9, 3).unwrap(),
-                reduced_date: None,
-                transacted: false,
+                ..RightmoveProperty::test_listing(131749937, (-0.125412, 51.529891), 1500000)
             },
         ];
 
@@ -128,19 +520,64 @@ mod tests {
                 median: 3550000.0,
                 q3: 3591666.6666666665,
                 max: 3600000.0,
-                count: 3
+                count: 3,
+                ..stats.price.clone()
             }
         );
+        assert_eq!(stats.percent_transacted, 0.0);
+    }
+
+    #[test]
+    fn test_price_by_qualifier_includes_excluded() {
+        let aggregator = PropertyAggregator::with_options(
+            HashSet::from([PriceQualifier::Poa]),
+            StatsOptions::default(),
+        );
+        let listing = |id: u32, price: u32, price_qualifier: PriceQualifier| RightmoveProperty {
+            price_qualifier,
+            ..RightmoveProperty::test_listing(id, (-0.122191, 51.53419), price)
+        };
+        let properties = vec![
+            listing(1, 500000, PriceQualifier::GuidePrice),
+            listing(2, 600000, PriceQualifier::GuidePrice),
+            listing(3, 700000, PriceQualifier::OffersOver),
+            listing(4, 1, PriceQualifier::Poa),
+        ];
+
+        let stats = aggregator.calculate_partial_stats(properties);
+
+        assert_eq!(stats.price.count, 3);
+        assert_eq!(stats.price_by_qualifier.len(), 3);
         assert_eq!(
-            stats.percent_transacted,
-            Stats {
-                min: 0.0,
-                q1: 0.0,
-                median: 0.0,
-                q3: 0.0,
-                max: 0.0,
-                count: 3
-            }
+            stats.price_by_qualifier[&PriceQualifier::GuidePrice].count,
+            2
+        );
+        assert_eq!(
+            stats.price_by_qualifier[&PriceQualifier::GuidePrice].median,
+            550000.0
+        );
+        assert_eq!(stats.price_by_qualifier[&PriceQualifier::Poa].count, 1);
+    }
+
+    #[test]
+    fn test_rental_yield() {
+        let aggregator = PropertyAggregator::with_options(HashSet::new(), StatsOptions::default());
+        let listing =
+            |id: u32, price: u32| RightmoveProperty::test_listing(id, (-0.122191, 51.53419), price);
+
+        let stats = aggregator.calculate_buy_and_rent_property_stats(
+            vec![listing(1, 400000), listing(2, 600000)],
+            vec![listing(3, 2000), listing(4, 3000)],
+        );
+
+        let rental_yield = &stats.buy_stats.rental_yield;
+        assert_eq!(rental_yield.median, 0.06);
+        assert_eq!(rental_yield.mean, 0.06);
+        assert_eq!(rental_yield.percentiles.len(), 9);
+        assert!(rental_yield.percentiles.iter().all(|p| p.value.is_finite()));
+        assert_eq!(
+            stats.rent_stats.rental_yield.percentiles,
+            rental_yield.percentiles
         );
     }
 }
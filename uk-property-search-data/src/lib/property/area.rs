@@ -0,0 +1,322 @@
+use super::{
+    aggregator::PropertyAggregator,
+    estate_agents::rightmove::RightmoveProperty,
+    property::{PropertyAction, PropertyStats, PropertyType},
+};
+use crate::lib::{
+    math::geo::haversine_miles,
+    postcode::{Postcode, PostcodeIndex},
+};
+use itertools::Itertools;
+use rocket::FromFormField;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Areas are sampled on a grid to measure how much of them was searched, roughly 140m by 220m in
+// London.
+const SAMPLE_DEGREES: f64 = 0.002;
+// Sampled beyond the searches too, so that areas reaching past them aren't counted as covered.
+const SAMPLE_MARGIN_DEGREES: f64 = 0.03;
+const MILES_PER_DEGREE_LATITUDE: f64 = 69.0;
+
+/// Part of the postcode that listings are grouped by, instead of by station.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, FromFormField)]
+#[serde(rename_all = "camelCase")]
+pub enum AreaLevel {
+    // Outcode, e.g. N1
+    #[field(value = "district")]
+    District,
+    // Outcode and the first digit of the incode, e.g. N1 9
+    #[field(value = "sector")]
+    Sector,
+}
+
+impl AreaLevel {
+    /// Comma separated levels, e.g. "district,sector", or none if empty.
+    pub fn parse_list(s: &str) -> Option<Vec<AreaLevel>> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|level| !level.is_empty())
+            .map(|level| match level {
+                "district" => Some(AreaLevel::District),
+                "sector" => Some(AreaLevel::Sector),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn area(self, postcode: &Postcode) -> &str {
+        match self {
+            AreaLevel::District => &postcode.outcode,
+            AreaLevel::Sector => &postcode.sector,
+        }
+    }
+}
+
+/// How much of an area was searched, as areas are only covered where they overlap station
+/// searches. Measured by points sampled on a grid, so small areas are approximate.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AreaCoverage {
+    pub num_samples: u32,  // sampled points in the area
+    pub num_searched: u32, // of those within a station search's radius
+}
+
+/// Stats for the listings in an area, e.g. a postcode district or borough, for each action and
+/// number of beds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AreaPropertySummary {
+    pub area: String,            // e.g. N1, N1 9, or the borough GSS code
+    pub coordinates: (f64, f64), // (long, lat), centre of the listings
+    pub action: u8,
+    pub num_beds: u32,
+    pub stats: PropertyStats,
+    pub stats_by_type: BTreeMap<PropertyType, PropertyStats>,
+    pub coverage: AreaCoverage,
+}
+
+/// Listings from every station search, each counted once, to be grouped by area instead.
+#[derive(Default)]
+pub struct AreaListings {
+    listings: HashMap<(u8, u32), HashMap<u32, RightmoveProperty>>, // by (action, num beds), then id
+    searches: Vec<((f64, f64), f64)>,                              // (centre, radius in miles)
+}
+
+impl AreaListings {
+    /// Record a station search, to measure how much of each area was covered.
+    pub fn add_search(&mut self, centre: (f64, f64), radius: f64) {
+        self.searches.push((centre, radius));
+    }
+
+    pub fn add(
+        &mut self,
+        action: PropertyAction,
+        num_beds: u32,
+        properties: impl IntoIterator<Item = RightmoveProperty>,
+    ) {
+        let listings = self.listings.entry((action as u8, num_beds)).or_default();
+        for property in properties {
+            listings.entry(property.id).or_insert(property);
+        }
+    }
+
+    pub fn num_listings(&self) -> usize {
+        self.listings.values().map(|listings| listings.len()).sum()
+    }
+
+    /// Area of the postcode nearest the point.
+    pub fn postcode_area(
+        index: &PostcodeIndex,
+        level: AreaLevel,
+    ) -> impl Fn((f64, f64)) -> Option<String> + '_ {
+        move |point| {
+            index
+                .nearest(point)
+                .map(|postcode| level.area(postcode).to_owned())
+        }
+    }
+
+    /// South west and north east corners of the box around the searches, with a margin, which
+    /// also holds every listing.
+    pub fn bounds(&self) -> Option<((f64, f64), (f64, f64))> {
+        let samples = self.samples();
+        let (west, east) = samples.iter().map(|s| s.0 .0).minmax().into_option()?;
+        let (south, north) = samples.iter().map(|s| s.0 .1).minmax().into_option()?;
+        Some(((west, south), (east, north)))
+    }
+
+    /// Grid points around the searches, and whether each is within one of them.
+    fn samples(&self) -> Vec<((f64, f64), bool)> {
+        let mut searched = HashSet::new();
+        for &(centre, radius) in &self.searches {
+            let latitude_delta = radius / MILES_PER_DEGREE_LATITUDE;
+            let longitude_delta = latitude_delta / centre.1.to_radians().cos();
+            let (west, south) = sample((centre.0 - longitude_delta, centre.1 - latitude_delta));
+            let (east, north) = sample((centre.0 + longitude_delta, centre.1 + latitude_delta));
+            for x in west..=east {
+                for y in south..=north {
+                    if haversine_miles(centre, sample_point((x, y))) <= radius {
+                        searched.insert((x, y));
+                    }
+                }
+            }
+        }
+        let (Some((west, east)), Some((south, north))) = (
+            searched.iter().map(|s| s.0).minmax().into_option(),
+            searched.iter().map(|s| s.1).minmax().into_option(),
+        ) else {
+            return vec![];
+        };
+        let margin = (SAMPLE_MARGIN_DEGREES / SAMPLE_DEGREES).ceil() as i64;
+        (west - margin..=east + margin)
+            .flat_map(|x| (south - margin..=north + margin).map(move |y| (x, y)))
+            .map(|s| (sample_point(s), searched.contains(&s)))
+            .collect()
+    }
+
+    /// A buy and rent summary for each area and number of beds with listings, where
+    /// `area_of` names the area a point is in, e.g. by reverse geocoding. Areas aren't searched
+    /// themselves, so coverage is the share of each area within the station searches.
+    pub fn summaries(
+        &self,
+        aggregator: &PropertyAggregator,
+        area_of: impl Fn((f64, f64)) -> Option<String>,
+    ) -> Vec<AreaPropertySummary> {
+        let mut coverage_by_area: HashMap<String, AreaCoverage> = HashMap::new();
+        for (point, searched) in self.samples() {
+            if let Some(area) = area_of(point) {
+                let coverage = coverage_by_area.entry(area).or_default();
+                coverage.num_samples += 1;
+                coverage.num_searched += searched as u32;
+            }
+        }
+        let mut by_area: BTreeMap<(String, u32), [Vec<RightmoveProperty>; 2]> = BTreeMap::new();
+        for (&(action, num_beds), listings) in &self.listings {
+            for property in listings.values() {
+                if let Some(area) = area_of(property.coordinates) {
+                    let action_index = (action != PropertyAction::Buy as u8) as usize;
+                    by_area.entry((area, num_beds)).or_default()[action_index]
+                        .push(property.clone());
+                }
+            }
+        }
+        by_area
+            .into_iter()
+            .flat_map(|((area, num_beds), [buy_properties, rent_properties])| {
+                // Centre of the listings, as areas have no single point like a station.
+                let coordinates = {
+                    let all = buy_properties.iter().chain(rent_properties.iter());
+                    let n = (buy_properties.len() + rent_properties.len()) as f64;
+                    let (longitude, latitude) = all.fold((0.0, 0.0), |(x, y), p| {
+                        (x + p.coordinates.0, y + p.coordinates.1)
+                    });
+                    (longitude / n, latitude / n)
+                };
+                let coverage = coverage_by_area.get(&area).copied().unwrap_or_default();
+                let stats = aggregator
+                    .calculate_buy_and_rent_property_stats(buy_properties, rent_properties);
+                let summary = |action: PropertyAction, stats, stats_by_type| AreaPropertySummary {
+                    area: area.clone(),
+                    coordinates,
+                    action: action as u8,
+                    num_beds,
+                    stats,
+                    stats_by_type,
+                    coverage,
+                };
+                [
+                    summary(
+                        PropertyAction::Buy,
+                        stats.buy_stats,
+                        stats.buy_stats_by_type,
+                    ),
+                    summary(
+                        PropertyAction::Rent,
+                        stats.rent_stats,
+                        stats.rent_stats_by_type,
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+fn sample((longitude, latitude): (f64, f64)) -> (i64, i64) {
+    (
+        (longitude / SAMPLE_DEGREES).round() as i64,
+        (latitude / SAMPLE_DEGREES).round() as i64,
+    )
+}
+
+fn sample_point((x, y): (i64, i64)) -> (f64, f64) {
+    (x as f64 * SAMPLE_DEGREES, y as f64 * SAMPLE_DEGREES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AreaLevel, AreaListings};
+    use crate::lib::{
+        math::stats::StatsOptions,
+        postcode::{Postcode, PostcodeIndex},
+        property::{
+            aggregator::PropertyAggregator, estate_agents::rightmove::RightmoveProperty,
+            property::PropertyAction,
+        },
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_summaries() {
+        let property = RightmoveProperty::test_listing;
+        let index = PostcodeIndex::new(vec![
+            Postcode::new("N1 9AL", (-0.123795, 51.530312)).unwrap(),
+            Postcode::new("N1 0AA", (-0.120000, 51.535000)).unwrap(),
+            Postcode::new("NW1 2DU", (-0.133068, 51.528055)).unwrap(),
+        ]);
+        let mut listings = AreaListings::default();
+        // Listing 2 was returned by two station searches.
+        listings.add(
+            PropertyAction::Buy,
+            1,
+            [
+                property(1, (-0.1238, 51.5303), 500000),
+                property(2, (-0.1200, 51.5350), 600000),
+            ],
+        );
+        listings.add(
+            PropertyAction::Buy,
+            1,
+            [
+                property(2, (-0.1200, 51.5350), 600000),
+                property(3, (-0.1331, 51.5281), 700000),
+            ],
+        );
+        listings.add(
+            PropertyAction::Rent,
+            1,
+            [property(4, (-0.1238, 51.5303), 2000)],
+        );
+        assert_eq!(listings.num_listings(), 4);
+        // Only searched around King's Cross.
+        listings.add_search((-0.123795, 51.530312), 0.5);
+
+        let aggregator = PropertyAggregator::with_options(HashSet::new(), StatsOptions::default());
+        let districts = listings.summaries(
+            &aggregator,
+            AreaListings::postcode_area(&index, AreaLevel::District),
+        );
+        let areas = districts
+            .iter()
+            .map(|s| (s.area.as_str(), s.action, s.stats.price.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            areas,
+            vec![("N1", 1, 2), ("N1", 2, 1), ("NW1", 1, 1), ("NW1", 2, 0)]
+        );
+
+        let sectors = listings.summaries(
+            &aggregator,
+            AreaListings::postcode_area(&index, AreaLevel::Sector),
+        );
+        let areas = sectors
+            .iter()
+            .filter(|s| s.action == PropertyAction::Buy as u8)
+            .map(|s| (s.area.as_str(), s.stats.price.count))
+            .collect::<Vec<_>>();
+        assert_eq!(areas, vec![("N1 0", 1), ("N1 9", 1), ("NW1 2", 1)]);
+
+        // Most of the points sampled near N1's postcodes are outside the search.
+        let coverage = districts[0].coverage;
+        assert!(coverage.num_searched > 0);
+        assert!(coverage.num_searched < coverage.num_samples);
+        assert_eq!(districts[0].coverage, districts[1].coverage);
+
+        assert_eq!(
+            AreaLevel::parse_list("district, sector"),
+            Some(vec![AreaLevel::District, AreaLevel::Sector])
+        );
+        assert_eq!(AreaLevel::parse_list(""), Some(vec![]));
+        assert_eq!(AreaLevel::parse_list("ward"), None);
+    }
+}
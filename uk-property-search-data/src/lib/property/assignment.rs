@@ -0,0 +1,137 @@
+use super::estate_agents::rightmove::RightmoveProperty;
+use crate::lib::math::geo::haversine_miles;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// How listings returned by the searches of more than one station are counted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StationAssignment {
+    // Every station counts every listing its search returned.
+    Radius,
+    // Each listing only counts towards the nearest station that searched.
+    Nearest,
+}
+
+/// Listings returned by the searches of both stations, for the same action and number of beds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StationOverlap {
+    pub postcodes: (String, String), // in order
+    pub action: u8,
+    pub num_beds: u32,
+    pub num_shared: usize,
+    pub num_listings: (usize, usize), // returned by each station's search
+    pub overlap: f64,                 // shared over listings returned by either
+}
+
+impl StationAssignment {
+    pub fn parse(s: &str) -> Option<StationAssignment> {
+        match s.trim() {
+            "radius" => Some(StationAssignment::Radius),
+            "nearest" => Some(StationAssignment::Nearest),
+            _ => None,
+        }
+    }
+}
+
+/// Overlap between every pair of searches sharing at least one listing, where `listings[i]`
+/// were returned by the search for the station at `postcodes[i]`.
+pub fn overlaps(
+    action: u8,
+    num_beds: u32,
+    postcodes: &[String],
+    listings: &[Vec<RightmoveProperty>],
+) -> Vec<StationOverlap> {
+    let ids = listings
+        .iter()
+        .map(|listings| listings.iter().map(|p| p.id).collect::<HashSet<_>>())
+        .collect_vec();
+    let mut searches_by_id: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, ids) in ids.iter().enumerate() {
+        for &id in ids {
+            searches_by_id.entry(id).or_default().push(i);
+        }
+    }
+    let mut num_shared: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for searches in searches_by_id.values() {
+        for (&a, &b) in searches.iter().tuple_combinations() {
+            *num_shared.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    num_shared
+        .into_iter()
+        .map(|((a, b), num_shared)| {
+            let (a, b) = if postcodes[a] <= postcodes[b] {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            let num_listings = (ids[a].len(), ids[b].len());
+            StationOverlap {
+                postcodes: (postcodes[a].clone(), postcodes[b].clone()),
+                action,
+                num_beds,
+                num_shared,
+                num_listings,
+                overlap: num_shared as f64 / (num_listings.0 + num_listings.1 - num_shared) as f64,
+            }
+        })
+        .collect()
+}
+
+/// Reassign listings so that each is only kept by the search for its nearest station, by
+/// crow-flies distance. `listings[i]` were returned by the search around `coordinates[i]`.
+pub fn assign_to_nearest(
+    coordinates: &[(f64, f64)],
+    listings: Vec<Vec<RightmoveProperty>>,
+) -> Vec<Vec<RightmoveProperty>> {
+    let mut assigned = vec![vec![]; listings.len()];
+    let mut seen = HashSet::new();
+    for property in listings.into_iter().flatten() {
+        if !seen.insert(property.id) {
+            continue;
+        }
+        let nearest = coordinates
+            .iter()
+            .map(|&station| haversine_miles(station, property.coordinates))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i);
+        if let Some(nearest) = nearest {
+            assigned[nearest].push(property);
+        }
+    }
+    assigned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assign_to_nearest, overlaps};
+    use crate::lib::property::estate_agents::rightmove::RightmoveProperty;
+
+    #[test]
+    fn test_overlaps_and_assign_to_nearest() {
+        let property = |id: u32, coordinates: (f64, f64)| {
+            RightmoveProperty::test_listing(id, coordinates, 500000)
+        };
+        let kings_cross = (-0.123795, 51.530312);
+        let euston = (-0.133068, 51.528055);
+        let postcodes = vec!["N1 9AL".to_owned(), "NW1 2DU".to_owned()];
+        let listings = vec![
+            vec![property(1, (-0.124, 51.531)), property(2, (-0.132, 51.528))],
+            vec![property(2, (-0.132, 51.528)), property(3, (-0.134, 51.527))],
+        ];
+
+        let overlaps = overlaps(1, 2, &postcodes, &listings);
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].num_shared, 1);
+        assert_eq!(overlaps[0].num_listings, (2, 2));
+        assert_eq!(overlaps[0].overlap, 1.0 / 3.0);
+
+        let assigned = assign_to_nearest(&[kings_cross, euston], listings);
+        let ids = |i: usize| assigned[i].iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(0), vec![1]);
+        assert_eq!(ids(1), vec![2, 3]);
+    }
+}
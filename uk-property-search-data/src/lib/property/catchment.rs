@@ -0,0 +1,114 @@
+use super::estate_agents::rightmove::RightmoveProperty;
+use crate::lib::{
+    math::geo::haversine_miles,
+    routing::{graph::PedestrianGraph, isochrone::walking_miles},
+    tube::TubeStation,
+};
+use log::warn;
+use std::collections::HashMap;
+
+/// Which listings count as near a station.
+pub enum Catchment {
+    // Within this many miles as the crow flies.
+    Radius(f64),
+    // Within a walk of this many miles along the street network. Stations that aren't near the
+    // network fall back to a radius of the same miles.
+    Walking {
+        graph: PedestrianGraph,
+        max_miles: f64,
+        distances: HashMap<String, HashMap<usize, f64>>, // by station postcode, from the graph
+    },
+}
+
+impl Catchment {
+    /// Walks from every station, which takes a while over a large network, so best run on a
+    /// blocking thread.
+    pub fn walking(
+        graph: PedestrianGraph,
+        stations: &[TubeStation],
+        minutes: f64,
+        speed_mph: f64,
+    ) -> Catchment {
+        let max_miles = walking_miles(minutes, speed_mph);
+        let distances = stations
+            .iter()
+            .filter_map(|station| {
+                if graph.nearest(station.coordinates).is_none() {
+                    warn!(
+                        "Station [{}] isn't near the street network, so using a [{}] mile radius instead.",
+                        station.name, max_miles
+                    );
+                    return None;
+                }
+                let distances = graph.distances_from(station.coordinates, max_miles);
+                Some((station.postcode.clone(), distances))
+            })
+            .collect();
+        Catchment::Walking {
+            graph,
+            max_miles,
+            distances,
+        }
+    }
+
+    pub fn contains(&self, station: &TubeStation, property: &RightmoveProperty) -> bool {
+        match self {
+            Catchment::Radius(radius) => {
+                haversine_miles(station.coordinates, property.coordinates) <= *radius
+            }
+            Catchment::Walking {
+                graph,
+                max_miles,
+                distances,
+            } => match distances.get(&station.postcode) {
+                Some(distances) => graph
+                    .distance_to(distances, property.coordinates)
+                    .is_some_and(|miles| miles <= *max_miles),
+                None => haversine_miles(station.coordinates, property.coordinates) <= *max_miles,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Catchment;
+    use crate::lib::{
+        property::estate_agents::rightmove::RightmoveProperty,
+        routing::{graph::PedestrianGraph, pbf::OsmWay},
+        tube::TubeStation,
+    };
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_walking_falls_back_to_radius() {
+        let station = |postcode: &str, coordinates: (f64, f64)| TubeStation {
+            name: postcode.to_owned(),
+            zone: vec![1],
+            postcode: postcode.to_owned(),
+            coordinates,
+            lines: HashSet::new(),
+            borough: None,
+            ward: None,
+            lsoa: None,
+        };
+        // A street north from Kings Cross, about 0.2 miles long, and nothing near Aldgate.
+        let nodes = HashMap::from([(1, (-0.123795, 51.530312)), (2, (-0.123795, 51.533208))]);
+        let ways = vec![OsmWay {
+            id: 1,
+            node_ids: vec![1, 2],
+        }];
+        let graph = PedestrianGraph::new(&ways, &nodes);
+        let kings_cross = station("N1 9AL", (-0.123795, 51.530312));
+        let aldgate = station("EC3N 1AH", (-0.075600, 51.514200));
+        // 6 minutes at 3 mph is 0.3 miles.
+        let catchment =
+            Catchment::walking(graph, &[kings_cross.clone(), aldgate.clone()], 6.0, 3.0);
+
+        let near = |coordinates| RightmoveProperty::test_listing(1, coordinates, 500000);
+        assert!(catchment.contains(&kings_cross, &near((-0.123795, 51.533208))));
+        // Off the network, so found by crow-flies distance, about 0.1 miles away.
+        assert!(catchment.contains(&aldgate, &near((-0.075600, 51.515650))));
+        assert!(!catchment.contains(&aldgate, &near((-0.075600, 51.530000))));
+    }
+}
@@ -0,0 +1,158 @@
+use super::property::PropertyType;
+use crate::lib::math::stats::Stats;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+const MONTH_MS: f64 = 365.25 / 12.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Asking prices near a station compared with recent completed sales of the same property type,
+/// at one point in time. Asking prices only come from the studio to 3 bed searches, whereas Price
+/// Paid doesn't record the number of beds, so sales of every size are included. The gap is
+/// therefore not a like-for-like discount, and a market with larger houses sold than listed will
+/// show a smaller or negative gap.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceDiscountSnapshot {
+    pub date: i64, // unix milliseconds
+    pub postcode: String,
+    pub coordinates: (f64, f64),             // (longitude, latitude)
+    pub property_type: Option<PropertyType>, // None in older snapshots, for all property types
+    pub asking: Stats,
+    pub sold: Stats,
+    #[serde(alias = "discount")]
+    pub asking_gap: f64, // 1 - median sold / median asking, positive when asking above sold
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceDiscountTrend {
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub property_type: Option<PropertyType>,
+    pub latest: PriceDiscountSnapshot,
+    pub trend_per_month: Option<f64>, // change in asking gap per month, if there are enough snapshots
+    pub num_snapshots: usize,
+}
+
+impl PriceDiscountSnapshot {
+    /// Returns None if there are no asking or sold prices to compare.
+    pub fn new(
+        date: i64,
+        postcode: &str,
+        coordinates: (f64, f64),
+        property_type: Option<PropertyType>,
+        asking: Stats,
+        sold: Stats,
+    ) -> Option<PriceDiscountSnapshot> {
+        if asking.count == 0 || sold.count == 0 {
+            return None;
+        }
+        Some(PriceDiscountSnapshot {
+            date,
+            postcode: postcode.to_owned(),
+            coordinates,
+            property_type,
+            asking_gap: 1.0 - sold.median / asking.median,
+            asking,
+            sold,
+        })
+    }
+}
+
+#[allow(dead_code)] // only used by the server
+impl PriceDiscountTrend {
+    /// Latest asking gap for each station and property type, with its trend over the past year.
+    /// Older snapshots comparing all property types at once are skipped.
+    pub fn from_snapshots(snapshots: Vec<PriceDiscountSnapshot>) -> Vec<PriceDiscountTrend> {
+        const TREND_WINDOW_MS: i64 = 365 * 24 * 60 * 60 * 1000;
+        snapshots
+            .into_iter()
+            .filter(|s| s.property_type.is_some())
+            .into_group_map_by(|s| (s.postcode.clone(), s.property_type))
+            .into_values()
+            .filter_map(|mut group| {
+                group.sort_by_key(|s| s.date);
+                let latest = group.pop()?;
+                let recent = group
+                    .into_iter()
+                    .filter(|s| latest.date - s.date <= TREND_WINDOW_MS)
+                    .chain([latest.clone()])
+                    .collect_vec();
+                Some(PriceDiscountTrend {
+                    postcode: latest.postcode.clone(),
+                    coordinates: latest.coordinates,
+                    property_type: latest.property_type,
+                    trend_per_month: trend_per_month(&recent),
+                    num_snapshots: recent.len(),
+                    latest,
+                })
+            })
+            .sorted_by(|a, b| (&a.postcode, a.property_type).cmp(&(&b.postcode, b.property_type)))
+            .collect()
+    }
+}
+
+/// Least squares slope of asking gap against time, per month.
+#[allow(dead_code)] // only used by the server
+fn trend_per_month(snapshots: &[PriceDiscountSnapshot]) -> Option<f64> {
+    if snapshots.len() < 2 {
+        return None;
+    }
+    let n = snapshots.len() as f64;
+    let xs = snapshots
+        .iter()
+        .map(|s| s.date as f64 / MONTH_MS)
+        .collect_vec();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = snapshots.iter().map(|s| s.asking_gap).sum::<f64>() / n;
+    let covariance: f64 = xs
+        .iter()
+        .zip(snapshots)
+        .map(|(x, s)| (x - mean_x) * (s.asking_gap - mean_y))
+        .sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        None
+    } else {
+        Some(covariance / variance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PriceDiscountSnapshot, PriceDiscountTrend, MONTH_MS};
+    use crate::lib::{math::stats::Stats, property::property::PropertyType};
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_from_snapshots() {
+        let snapshot = |month: f64, property_type: Option<PropertyType>, sold_median: f64| {
+            let stats = |median: f64| Stats {
+                median,
+                ..Stats::from_vec(&vec![median])
+            };
+            PriceDiscountSnapshot::new(
+                (month * MONTH_MS) as i64,
+                "N1 9AL",
+                (-0.123795, 51.530312),
+                property_type,
+                stats(500000.0),
+                stats(sold_median),
+            )
+            .unwrap()
+        };
+        let flat = Some(PropertyType::Flat);
+        let snapshots = vec![
+            snapshot(0.0, flat, 490000.0),
+            snapshot(2.0, flat, 480000.0),
+            snapshot(1.0, flat, 485000.0),
+            snapshot(2.0, None, 400000.0),
+        ];
+
+        let trends = PriceDiscountTrend::from_snapshots(snapshots);
+        assert_eq!(trends.len(), 1);
+        assert_almost_eq!(trends[0].latest.asking_gap, 0.04, 1e-9);
+        assert_almost_eq!(trends[0].trend_per_month.unwrap(), 0.01, 1e-9);
+        assert_eq!(trends[0].num_snapshots, 3);
+    }
+}
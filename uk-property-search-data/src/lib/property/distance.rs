@@ -0,0 +1,108 @@
+use super::{estate_agents::rightmove::RightmoveProperty, property::PropertyStats};
+use crate::lib::math::geo::haversine_miles;
+use serde::{Deserialize, Serialize};
+
+/// Concentric rings around a station, by crow-flies distance in miles, e.g. 0-0.25, 0.25-0.5
+/// and 0.5-1 for outer edges [0.25, 0.5, 1].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceRings {
+    outer_edges: Vec<f64>, // ascending
+}
+
+/// Stats for the listings in one ring around a station.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DistanceRingStats {
+    pub from_miles: f64, // inclusive
+    pub to_miles: f64,   // exclusive, except for the outermost ring
+    pub stats: PropertyStats,
+}
+
+impl DistanceRings {
+    /// Parse comma separated outer edges, e.g. "0.25,0.5,1", or no rings if empty.
+    pub fn parse(s: &str) -> Option<DistanceRings> {
+        if s.trim().is_empty() {
+            return Some(DistanceRings {
+                outer_edges: vec![],
+            });
+        }
+        let outer_edges: Vec<f64> = s
+            .split(',')
+            .map(|edge| edge.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        let ascending = outer_edges.windows(2).all(|pair| pair[0] < pair[1]);
+        (ascending && outer_edges.first().is_some_and(|&edge| edge > 0.0))
+            .then_some(DistanceRings { outer_edges })
+    }
+
+    /// Outer edge of the outermost ring, or 0 without any rings.
+    pub fn outer_radius(&self) -> f64 {
+        self.outer_edges.last().copied().unwrap_or(0.0)
+    }
+
+    /// (from, to) miles of each ring, innermost first.
+    pub fn bounds(&self) -> Vec<(f64, f64)> {
+        let mut from = 0.0;
+        self.outer_edges
+            .iter()
+            .map(|&to| {
+                let bounds = (from, to);
+                from = to;
+                bounds
+            })
+            .collect()
+    }
+
+    /// Listings in each ring around the station, innermost first. Listings beyond the outermost
+    /// ring are left out.
+    pub fn split(
+        &self,
+        station: (f64, f64),
+        properties: &[RightmoveProperty],
+    ) -> Vec<Vec<RightmoveProperty>> {
+        let mut rings = vec![vec![]; self.outer_edges.len()];
+        for property in properties {
+            let distance = haversine_miles(station, property.coordinates);
+            let ring = self
+                .outer_edges
+                .iter()
+                .position(|&edge| distance < edge)
+                .or_else(|| (distance <= self.outer_radius()).then_some(rings.len() - 1));
+            if let Some(ring) = ring {
+                rings[ring].push(property.clone());
+            }
+        }
+        rings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DistanceRings;
+    use crate::lib::property::estate_agents::rightmove::RightmoveProperty;
+
+    #[test]
+    fn test_split() {
+        let property = |id: u32, latitude: f64| {
+            RightmoveProperty::test_listing(id, (-0.123795, latitude), 500000)
+        };
+        let rings = DistanceRings::parse("0.25, 0.5, 1").unwrap();
+        assert_eq!(rings.bounds(), vec![(0.0, 0.25), (0.25, 0.5), (0.5, 1.0)]);
+        assert_eq!(DistanceRings::parse("0.5,0.25"), None);
+        let no_rings = DistanceRings::parse("").unwrap();
+        assert!(no_rings.bounds().is_empty());
+        assert_eq!(no_rings.outer_radius(), 0.0);
+
+        // Roughly 0.07, 0.35 and 1.4 miles north of the station.
+        let properties = vec![
+            property(1, 51.5313),
+            property(2, 51.5354),
+            property(3, 51.5503),
+        ];
+        let split = rings.split((-0.123795, 51.530312), &properties);
+        let ids = |i: usize| split[i].iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(0), vec![1]);
+        assert_eq!(ids(1), vec![2]);
+        assert!(ids(2).is_empty());
+    }
+}
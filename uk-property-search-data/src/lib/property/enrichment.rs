@@ -0,0 +1,132 @@
+use super::{
+    estate_agents::{
+        property_log::PropertyLog,
+        rightmove::{Rightmove, RightmoveProperty},
+    },
+    listing_details::{ListingDetails, PriceHistoryRecord},
+};
+use crate::lib::util::{
+    error::{ScraperError, ScraperErrorAction},
+    ext::MongoCollectionExt,
+    globals::Globals,
+};
+use futures::future::join_all;
+use log::warn;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// Attaches detail page fields to listings, reusing details cached from previous runs
+/// and only fetching a bounded number of new listings per run.
+pub struct ListingEnricher<'a> {
+    rightmove: &'a Rightmove,
+    property_log: &'a PropertyLog,
+    cached: HashMap<u32, ListingDetails>,
+    seen: Mutex<HashMap<u32, ListingDetails>>,
+    remaining_fetches: AtomicUsize,
+}
+
+impl<'a> ListingEnricher<'a> {
+    pub async fn new(
+        globals: &Globals,
+        rightmove: &'a Rightmove,
+        property_log: &'a PropertyLog,
+    ) -> ListingEnricher<'a> {
+        let cached = globals
+            .db
+            .listing_details()
+            .find_to_vec()
+            .await
+            .into_iter()
+            .map(|details| (details.id, details))
+            .collect();
+        ListingEnricher {
+            rightmove,
+            property_log,
+            cached,
+            seen: Mutex::new(HashMap::new()),
+            remaining_fetches: AtomicUsize::new(
+                globals
+                    .properties
+                    .get_int("rightmove.max.detail.fetches.per.run") as usize,
+            ),
+        }
+    }
+
+    /// Fill in details for each listing where available. Only fails if further requests
+    /// should be aborted; other failures leave the listing without details.
+    pub async fn enrich(
+        &self,
+        properties: Vec<RightmoveProperty>,
+    ) -> Result<Vec<RightmoveProperty>, ScraperError> {
+        join_all(properties.into_iter().map(|mut property| async move {
+            property.details = self.get_details(property.id).await?;
+            Ok(property)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Details of every listing seen this run, to replace the cache with.
+    pub fn into_seen(self) -> Vec<ListingDetails> {
+        self.seen.into_inner().unwrap().into_values().collect()
+    }
+
+    async fn get_details(&self, id: u32) -> Result<Option<ListingDetails>, ScraperError> {
+        // Nearby stations share listings, so check those fetched earlier this run too.
+        let known = self
+            .cached
+            .get(&id)
+            .cloned()
+            .or_else(|| self.seen.lock().unwrap().get(&id).cloned());
+        if let Some(details) = known {
+            self.seen.lock().unwrap().insert(id, details.clone());
+            return Ok(Some(details));
+        }
+        let has_budget = self
+            .remaining_fetches
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        if !has_budget {
+            return Ok(None);
+        }
+        match self.rightmove.get_listing_details(id).await {
+            Ok(details) => {
+                let details = ListingDetails {
+                    price_history: self.get_price_history(id).await?,
+                    ..details
+                };
+                self.seen.lock().unwrap().insert(id, details.clone());
+                Ok(Some(details))
+            }
+            Err(err) if err.action() == ScraperErrorAction::Abort => Err(err),
+            Err(err) => {
+                warn!("Skipping details for listing [{}]: {}", id, err);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn get_price_history(&self, id: u32) -> Result<Vec<PriceHistoryRecord>, ScraperError> {
+        match self.property_log.get_history(vec![id]).await {
+            Ok(histories) => Ok(histories
+                .into_iter()
+                .flat_map(|history| history.records)
+                .map(|record| PriceHistoryRecord {
+                    date: record.date.timestamp_millis(),
+                    price: record.price,
+                })
+                .collect()),
+            Err(err) if err.action() == ScraperErrorAction::Abort => Err(err),
+            Err(err) => {
+                warn!("Skipping price history for listing [{}]: {}", id, err);
+                Ok(vec![])
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use super::{
+    estate_agents::rightmove::RightmoveProperty, listing_details::EpcBand, property::PropertyType,
+};
+use crate::lib::math::geo::haversine_miles;
+use itertools::{iproduct, Itertools};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Listing coordinates are only approximate, so look for certificates at postcodes this close by.
+pub const EPC_MATCH_RADIUS: f64 = 0.05;
+
+// Size of the grid cells (in degrees) that postcodes are bucketed into for nearby lookups.
+const GRID_CELL_DEGREES: f64 = 0.001;
+
+/// The latest domestic Energy Performance Certificate lodged for an address.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EpcCertificate {
+    pub postcode: String,
+    pub address: String,         // normalised, see `normalise_address`
+    pub coordinates: (f64, f64), // (longitude, latitude) of the postcode
+    pub property_type: String,   // as registered, e.g. "Flat", "House", "Maisonette"
+    pub floor_area_square_feet: i32,
+    pub current_rating: EpcBand,
+    pub potential_rating: Option<EpcBand>,
+    pub lodgement_date: String, // yyyy-mm-dd
+}
+
+impl EpcCertificate {
+    /// Whether a listing of the given type could be this certificate's property.
+    pub fn is_compatible(&self, property_type: PropertyType) -> bool {
+        match property_type {
+            PropertyType::Flat => self.property_type == "Flat",
+            PropertyType::Maisonette => self.property_type == "Maisonette",
+            PropertyType::Bungalow => self.property_type == "Bungalow",
+            PropertyType::Terraced | PropertyType::SemiDetached | PropertyType::Detached => {
+                self.property_type == "House"
+            }
+            PropertyType::Other => true,
+        }
+    }
+}
+
+/// Upper case alphanumeric words separated by single spaces, e.g. "12a, Caledonian Rd." =>
+/// "12A CALEDONIAN RD".
+pub fn normalise_address(address: &str) -> String {
+    address
+        .to_uppercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .join(" ")
+}
+
+/// The street in a listing's display address, e.g. "Flat 3, 12 Caledonian Road, London N1" =>
+/// "CALEDONIAN ROAD". Display addresses rarely give the house number, so the street is the most
+/// specific part that can be matched against the register.
+pub fn street_of(display_address: &str) -> Option<String> {
+    display_address.split(',').find_map(|part| {
+        let street = normalise_address(part)
+            .split(' ')
+            .skip_while(|word| {
+                word.chars().any(|c| c.is_ascii_digit())
+                    || *word == "FLAT"
+                    || *word == "APARTMENT"
+                    || *word == "UNIT"
+            })
+            .join(" ");
+        (!street.is_empty()).then_some(street)
+    })
+}
+
+/// Certificates indexed by postcode and normalised address, with postcodes bucketed by location.
+pub struct EpcIndex {
+    by_postcode: HashMap<String, Vec<EpcCertificate>>,
+    postcodes_by_cell: HashMap<(i64, i64), Vec<String>>,
+}
+
+impl EpcIndex {
+    pub fn new(certificates: Vec<EpcCertificate>) -> EpcIndex {
+        let by_postcode: HashMap<String, Vec<EpcCertificate>> = certificates
+            .into_iter()
+            .into_group_map_by(|certificate| certificate.postcode.clone());
+        let postcodes_by_cell = by_postcode
+            .iter()
+            .map(|(postcode, certificates)| {
+                (
+                    EpcIndex::cell(certificates[0].coordinates),
+                    postcode.clone(),
+                )
+            })
+            .into_group_map();
+        EpcIndex {
+            by_postcode,
+            postcodes_by_cell,
+        }
+    }
+
+    pub fn num_certificates(&self) -> usize {
+        self.by_postcode.values().map(|c| c.len()).sum()
+    }
+
+    fn cell((longitude, latitude): (f64, f64)) -> (i64, i64) {
+        (
+            (longitude / GRID_CELL_DEGREES).floor() as i64,
+            (latitude / GRID_CELL_DEGREES).floor() as i64,
+        )
+    }
+
+    /// The certificate for a listing, if exactly one certificate on its street nearby fits.
+    /// A rating advertised on the listing is used to tell apart otherwise identical candidates.
+    pub fn find(&self, property: &RightmoveProperty) -> Option<&EpcCertificate> {
+        let street = street_of(&property.display_address)?;
+        let padded_street = format!(" {} ", street);
+        // At London's latitude a cell is ~0.04 miles wide and ~0.07 miles tall, so two cells
+        // either side covers the match radius in both directions.
+        let (x, y) = EpcIndex::cell(property.coordinates);
+        let candidates = iproduct!((x - 2)..=(x + 2), (y - 2)..=(y + 2))
+            .filter_map(|cell| self.postcodes_by_cell.get(&cell))
+            .flatten()
+            .filter_map(|postcode| self.by_postcode.get(postcode))
+            .filter(|certificates| {
+                haversine_miles(property.coordinates, certificates[0].coordinates)
+                    <= EPC_MATCH_RADIUS
+            })
+            .flatten()
+            .filter(|c| format!(" {} ", c.address).contains(&padded_street))
+            .filter(|c| c.is_compatible(property.property_type))
+            .collect_vec();
+        let advertised_rating = property.details.as_ref().and_then(|d| d.epc_band);
+        match (candidates.as_slice(), advertised_rating) {
+            ([certificate], _) => Some(certificate),
+            (_, Some(rating)) => candidates
+                .into_iter()
+                .filter(|c| c.current_rating == rating)
+                .exactly_one()
+                .ok(),
+            _ => None,
+        }
+    }
+
+    /// Attach matching certificates, with their floor area where the listing doesn't give one.
+    pub fn fill(&self, properties: &mut [RightmoveProperty]) {
+        for property in properties.iter_mut() {
+            if let Some(certificate) = self.find(property) {
+                property.epc_square_feet = property
+                    .square_feet
+                    .is_none()
+                    .then_some(certificate.floor_area_square_feet);
+                property.epc = Some(certificate.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalise_address, street_of, EpcCertificate, EpcIndex};
+    use crate::lib::property::{
+        estate_agents::rightmove::RightmoveProperty,
+        listing_details::{EpcBand, ListingDetails},
+    };
+
+    fn certificate(address: &str, floor_area: i32, rating: EpcBand) -> EpcCertificate {
+        EpcCertificate {
+            postcode: "N1 9AL".to_owned(),
+            address: normalise_address(address),
+            coordinates: (-0.123795, 51.530312),
+            property_type: "Flat".to_owned(),
+            floor_area_square_feet: floor_area,
+            current_rating: rating,
+            potential_rating: Some(EpcBand::B),
+            lodgement_date: "2023-01-01".to_owned(),
+        }
+    }
+
+    fn property(display_address: &str, epc_band: Option<EpcBand>) -> RightmoveProperty {
+        RightmoveProperty {
+            display_address: display_address.to_owned(),
+            details: epc_band.map(|epc_band| ListingDetails {
+                id: 0,
+                fetched: 0,
+                tenure: None,
+                lease_years_remaining: None,
+                annual_service_charge: None,
+                annual_ground_rent: None,
+                council_tax_band: None,
+                epc_band: Some(epc_band),
+                price_history: vec![],
+            }),
+            ..RightmoveProperty::test_listing(0, (-0.1238, 51.5305), 500000)
+        }
+    }
+
+    #[test]
+    fn test_street_of() {
+        assert_eq!(
+            street_of("Flat 3, 12 Caledonian Road, London N1"),
+            Some("CALEDONIAN ROAD".to_owned())
+        );
+        assert_eq!(
+            street_of("York Way, Kings Cross"),
+            Some("YORK WAY".to_owned())
+        );
+        assert_eq!(street_of(""), None);
+    }
+
+    #[test]
+    fn test_fill() {
+        let index = EpcIndex::new(vec![
+            certificate("12 Caledonian Road", 600, EpcBand::C),
+            certificate("Flat 1, 20 York Way", 500, EpcBand::C),
+            certificate("Flat 2, 20 York Way", 700, EpcBand::D),
+        ]);
+        let mut properties = vec![
+            property("Caledonian Road, London N1", None),
+            property("York Way, London N1", None),
+            property("York Way, London N1", Some(EpcBand::D)),
+        ];
+        index.fill(&mut properties);
+
+        assert_eq!(properties[0].square_feet, None);
+        assert_eq!(properties[0].epc_square_feet, Some(600));
+        // Two flats on York Way, so only match when the advertised rating tells them apart.
+        assert_eq!(properties[1].epc, None);
+        assert_eq!(properties[2].known_square_feet(), Some(700));
+        assert_eq!(
+            properties[2].epc.as_ref().map(|c| c.potential_rating),
+            Some(Some(EpcBand::B))
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{bail, Result};
+use chrono::Utc;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Mutex};
+
+/// Fields and enum values we know about in an estate agent response record.
+pub struct DriftSchema {
+    pub name: &'static str,
+    pub known_fields: &'static [&'static str],
+    pub required_fields: &'static [&'static str],
+    pub enum_fields: &'static [(&'static str, &'static [&'static str])], // (field, known values)
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum DriftKind {
+    UnknownValue,
+    UnexpectedField,
+    MissingField,
+}
+
+impl DriftKind {
+    /// Whether this drift means records may be decoded wrongly. New fields are harmless until we
+    /// need them, so they are only reported.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            DriftKind::UnknownValue | DriftKind::MissingField => true,
+            DriftKind::UnexpectedField => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftFinding {
+    pub schema: String,
+    pub kind: DriftKind,
+    pub field: String,
+    pub value: Option<String>, // only for unknown values
+    pub occurrences: usize,
+    pub sample: String, // json payload of the first affected record
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDriftSummary {
+    pub schema: String,
+    pub records_seen: usize,
+    pub records_affected: usize, // only counting breaking drift
+}
+
+impl SchemaDriftSummary {
+    pub fn proportion_affected(&self) -> f64 {
+        if self.records_seen == 0 {
+            0.0
+        } else {
+            (self.records_affected as f64) / (self.records_seen as f64)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftReport {
+    pub date: i64, // unix milliseconds
+    pub schemas: Vec<SchemaDriftSummary>,
+    pub findings: Vec<DriftFinding>,
+}
+
+impl DriftReport {
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// One line per finding, most frequent first.
+    pub fn describe_findings(&self) -> String {
+        self.findings
+            .iter()
+            .map(|f| {
+                format!(
+                    "- {} {:?} [{}] {:?} x{}",
+                    f.schema, f.kind, f.field, f.value, f.occurrences
+                )
+            })
+            .join("\n")
+    }
+
+    /// Combine reports from several estate agents seen during the same run.
+    pub fn merge(reports: Vec<DriftReport>) -> DriftReport {
+        DriftReport {
+            date: Utc::now().timestamp_millis(),
+            schemas: reports
+                .iter()
+                .flat_map(|r| r.schemas.iter().cloned())
+                .sorted_by(|a, b| a.schema.cmp(&b.schema))
+                .collect(),
+            findings: reports
+                .into_iter()
+                .flat_map(|r| r.findings)
+                .sorted_by(|a, b| b.occurrences.cmp(&a.occurrences))
+                .collect(),
+        }
+    }
+
+    /// Fail if the proportion of records with breaking drift for any schema exceeds the given maximum.
+    pub fn check(&self, max_affected_proportion: f64) -> Result<()> {
+        let exceeded = self
+            .schemas
+            .iter()
+            .filter(|s| s.proportion_affected() > max_affected_proportion)
+            .collect_vec();
+        if !exceeded.is_empty() {
+            bail!(
+                "Schema drift affects more than [{:.1}%] of records for: [{}]\nFindings:\n{}",
+                max_affected_proportion * 100.0,
+                exceeded
+                    .iter()
+                    .map(|s| format!("{} ({}/{})", s.schema, s.records_affected, s.records_seen))
+                    .join(", "),
+                self.describe_findings()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Records unknown enum values and unexpected or missing fields seen during a run.
+#[derive(Default)]
+pub struct DriftMonitor {
+    state: Mutex<DriftState>,
+}
+
+#[derive(Default)]
+struct DriftState {
+    records_seen: HashMap<&'static str, usize>,
+    records_affected: HashMap<&'static str, usize>,
+    findings: HashMap<(&'static str, DriftKind, String, Option<String>), DriftFinding>,
+}
+
+impl DriftMonitor {
+    pub fn new() -> DriftMonitor {
+        DriftMonitor::default()
+    }
+
+    /// Check a record against its schema, returning true if any drift was found.
+    pub fn inspect(&self, schema: &DriftSchema, record: &Value) -> bool {
+        let mut drifts: Vec<(DriftKind, String, Option<String>)> = vec![];
+        match record.as_object() {
+            Some(object) => {
+                drifts.extend(
+                    object
+                        .keys()
+                        .filter(|key| !schema.known_fields.contains(&key.as_str()))
+                        .map(|key| (DriftKind::UnexpectedField, key.to_owned(), None)),
+                );
+                drifts.extend(
+                    schema
+                        .required_fields
+                        .iter()
+                        .filter(|&&field| object.get(field).is_none_or(|v| v.is_null()))
+                        .map(|&field| (DriftKind::MissingField, field.to_owned(), None)),
+                );
+                drifts.extend(
+                    schema
+                        .enum_fields
+                        .iter()
+                        .filter_map(|&(field, known_values)| match object.get(field) {
+                            Some(Value::String(value))
+                                if !known_values.contains(&value.as_str()) =>
+                            {
+                                Some((
+                                    DriftKind::UnknownValue,
+                                    field.to_owned(),
+                                    Some(value.to_owned()),
+                                ))
+                            }
+                            _ => None,
+                        }),
+                );
+            }
+            None => drifts.push((DriftKind::MissingField, "*".to_owned(), None)),
+        }
+
+        let mut state = self.state.lock().unwrap();
+        *state.records_seen.entry(schema.name).or_default() += 1;
+        if drifts.is_empty() {
+            return false;
+        }
+        if drifts.iter().any(|(kind, _, _)| kind.is_breaking()) {
+            *state.records_affected.entry(schema.name).or_default() += 1;
+        }
+        for (kind, field, value) in drifts {
+            state
+                .findings
+                .entry((schema.name, kind, field.clone(), value.clone()))
+                .or_insert_with(|| DriftFinding {
+                    schema: schema.name.to_owned(),
+                    kind,
+                    field,
+                    value,
+                    occurrences: 0,
+                    sample: record.to_string(),
+                })
+                .occurrences += 1;
+        }
+        true
+    }
+
+    pub fn report(&self) -> DriftReport {
+        let state = self.state.lock().unwrap();
+        DriftReport {
+            date: Utc::now().timestamp_millis(),
+            schemas: state
+                .records_seen
+                .iter()
+                .map(|(&schema, &records_seen)| SchemaDriftSummary {
+                    schema: schema.to_owned(),
+                    records_seen,
+                    records_affected: state.records_affected.get(schema).copied().unwrap_or(0),
+                })
+                .sorted_by(|a, b| a.schema.cmp(&b.schema))
+                .collect(),
+            findings: state
+                .findings
+                .values()
+                .cloned()
+                .sorted_by(|a, b| b.occurrences.cmp(&a.occurrences))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DriftKind, DriftMonitor, DriftSchema};
+    use serde_json::json;
+
+    const SCHEMA: DriftSchema = DriftSchema {
+        name: "Price",
+        known_fields: &["amount", "frequency"],
+        required_fields: &["amount"],
+        enum_fields: &[("frequency", &["weekly", "monthly"])],
+    };
+
+    #[test]
+    fn test_inspect() {
+        let monitor = DriftMonitor::new();
+        assert!(!monitor.inspect(&SCHEMA, &json!({"amount": 1, "frequency": "weekly"})));
+        assert!(monitor.inspect(&SCHEMA, &json!({"amount": 1, "frequency": "fortnightly"})));
+        assert!(monitor.inspect(&SCHEMA, &json!({"frequency": "monthly", "currency": "GBP"})));
+        assert!(monitor.inspect(&SCHEMA, &json!({"amount": 2, "frequency": "fortnightly"})));
+
+        let report = monitor.report();
+        assert_eq!(report.schemas[0].records_seen, 4);
+        assert_eq!(report.schemas[0].records_affected, 3);
+        assert_eq!(report.findings.len(), 3);
+        assert_eq!(report.findings[0].kind, DriftKind::UnknownValue);
+        assert_eq!(report.findings[0].value, Some("fortnightly".to_owned()));
+        assert_eq!(report.findings[0].occurrences, 2);
+    }
+
+    #[test]
+    fn test_check() {
+        let monitor = DriftMonitor::new();
+        monitor.inspect(&SCHEMA, &json!({"amount": 1, "frequency": "weekly"}));
+        monitor.inspect(&SCHEMA, &json!({"amount": 1, "frequency": "fortnightly"}));
+
+        let report = monitor.report();
+        assert!(report.check(0.5).is_ok());
+        assert!(report.check(0.25).is_err());
+    }
+
+    #[test]
+    fn test_check_ignores_unexpected_fields() {
+        let monitor = DriftMonitor::new();
+        monitor.inspect(&SCHEMA, &json!({"amount": 1, "currency": "GBP"}));
+        monitor.inspect(&SCHEMA, &json!({"amount": 2, "currency": "GBP"}));
+
+        let report = monitor.report();
+        assert!(!report.is_empty());
+        assert_eq!(report.schemas[0].records_affected, 0);
+        assert!(report.check(0.0).is_ok());
+    }
+}
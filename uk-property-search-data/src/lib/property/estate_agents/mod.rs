@@ -1,2 +1,3 @@
+pub mod drift;
 pub mod property_log;
 pub mod rightmove;
@@ -1,5 +1,7 @@
+use super::drift::{DriftMonitor, DriftSchema};
 use crate::lib::util::{
-    ext::DecodeJsonResponseExt,
+    error::{ScraperError, ScraperErrorAction},
+    ext::DecodeResponseExt,
     globals::Globals,
     http::{Http, HttpOptions},
 };
@@ -10,6 +12,7 @@ use lazy_static::lazy_static;
 use log::warn;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{collections::HashMap, iter::once, time::Duration};
 use tokio::time::sleep;
 
@@ -18,9 +21,16 @@ pub struct PropertyLog {
     user: String,
     max_retry_count: u32,
     retry_delay: Duration,
+    drift: DriftMonitor,
 }
 
-#[allow(dead_code)]
+const PRICE_SCHEMA: DriftSchema = DriftSchema {
+    name: "PropertyLog Price",
+    known_fields: &["date", "price"],
+    required_fields: &["date", "price"],
+    enum_fields: &[],
+};
+
 impl PropertyLog {
     pub fn new(globals: &Globals) -> PropertyLog {
         PropertyLog {
@@ -44,10 +54,19 @@ impl PropertyLog {
                     .properties
                     .get_int("propertylog.retry.delay.seconds") as u64,
             ),
+            drift: DriftMonitor::new(),
         }
     }
 
-    pub async fn get_history(&self, ids: Vec<u32>) -> Result<Vec<PropertyLogHistory>> {
+    /// Schema drift seen in responses so far.
+    pub fn drift(&self) -> &DriftMonitor {
+        &self.drift
+    }
+
+    pub async fn get_history(
+        &self,
+        ids: Vec<u32>,
+    ) -> Result<Vec<PropertyLogHistory>, ScraperError> {
         #[derive(Debug, Serialize, Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct PropertiesResponse {
@@ -57,7 +76,7 @@ impl PropertyLog {
         #[derive(Debug, Serialize, Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct Property {
-            prices: Vec<Price>,
+            prices: Vec<Value>, // checked for schema drift before decoding into Price
         }
 
         #[derive(Debug, Serialize, Deserialize)]
@@ -108,16 +127,18 @@ impl PropertyLog {
             .collect_vec();
         let mut retries_left = self.max_retry_count;
         let response = loop {
-            let result: Result<PropertiesResponse> = self
-                .http
-                .post_with_form("https://api.propertylog.net/api/properties", &form)
-                .await?
-                .json_or_err(&format!("Property log query: [{:?}]", &form))
-                .await;
+            let result: Result<PropertiesResponse, ScraperError> = async {
+                self.http
+                    .post_with_form("https://api.propertylog.net/api/properties", &form)
+                    .await?
+                    .json_or_err(&format!("Property log query: [{:?}]", &form))
+                    .await
+            }
+            .await;
             match result {
                 Ok(r) => break r,
                 Err(err) => {
-                    if retries_left > 0 {
+                    if retries_left > 0 && err.action() == ScraperErrorAction::Backoff {
                         warn!(
                             "{}\n{} attempts left, retrying in {} seconds...",
                             err,
@@ -128,7 +149,7 @@ impl PropertyLog {
                         sleep(self.retry_delay).await;
                         continue;
                     } else {
-                        warn!("Ran out of retries!");
+                        warn!("Giving up after error: {}", err);
                         return Err(err);
                     }
                 }
@@ -141,6 +162,12 @@ impl PropertyLog {
                 let records = property
                     .prices
                     .into_iter()
+                    .filter_map(|value| {
+                        self.drift.inspect(&PRICE_SCHEMA, &value);
+                        serde_json::from_value::<Price>(value)
+                            .map_err(|e| warn!("Skipping undecodable PropertyLog price: {}", e))
+                            .ok()
+                    })
                     .filter_map(|price| {
                         match (parse_date(&price.date), parse_price(&price.price)) {
                             (date, Ok(p)) => Some(PropertyLogRecord {
@@ -1,34 +1,331 @@
+use super::drift::{DriftMonitor, DriftSchema};
 use crate::lib::{
-    property::property::PropertyAction,
+    property::{
+        epc::EpcCertificate,
+        listing_details::{EpcBand, ListingDetails, Tenure},
+        price::{parse_shared_ownership_share, PriceQualifier, RentFrequency},
+        property::{ListingStatus, PropertyAction, PropertyType, SearchCoverage},
+    },
     util::{
-        ext::{DecodeJsonResponseExt, VecResultExt},
+        error::{ScraperError, ScraperErrorAction},
+        ext::DecodeResponseExt,
         globals::Globals,
         http::{Http, HttpOptions},
     },
 };
-use anyhow::{bail, Result};
+use anyhow::Result;
 use chrono::{DateTime, Utc};
-use futures::future::join_all;
+use futures::future::{join_all, try_join_all};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use log::warn;
 use regex::Regex;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, iter};
+use serde_json::Value;
+use std::{collections::HashSet, time::Duration};
+use tokio::time::sleep;
 
 pub struct Rightmove {
     http: Http,
+    drift: DriftMonitor,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct RightmoveProperty {
     pub id: u32,
     pub coordinates: (f64, f64), // (longitude, latitude)
-    pub price: u32,              // total (if buy) / monthly (if rent)
-    pub square_feet: Option<i32>,
+    pub display_address: String,
+    pub price: u32, // total (if buy) / monthly (if rent)
+    pub price_qualifier: PriceQualifier,
+    pub shared_ownership_share: Option<f64>, // fraction of the property being sold
+    pub property_type: PropertyType,
+    pub square_feet: Option<i32>,         // as advertised
+    pub epc_square_feet: Option<i32>, // from the EPC register, if the listing doesn't advertise it
+    pub imputed_square_feet: Option<i32>, // estimated from similar listings if square feet is missing
     pub post_date: DateTime<Utc>,
     pub reduced_date: Option<DateTime<Utc>>,
     pub transacted: bool,
+    pub status: ListingStatus,
+    pub details: Option<ListingDetails>, // filled in by enrichment, if fetched
+    pub epc: Option<EpcCertificate>,     // matched from the EPC register, if found
+    pub borough: Option<String>,         // GSS code, None outside the borough boundaries
+    pub ward: Option<String>,            // GSS code
+    pub lsoa: Option<String>,            // GSS code
+}
+
+impl RightmoveProperty {
+    /// Floor area as advertised, or else from the EPC register.
+    pub fn known_square_feet(&self) -> Option<i32> {
+        self.square_feet.or(self.epc_square_feet)
+    }
+}
+
+#[cfg(test)]
+impl RightmoveProperty {
+    /// A standard priced flat listed today with nothing else known, for tests to build on.
+    pub fn test_listing(id: u32, coordinates: (f64, f64), price: u32) -> RightmoveProperty {
+        RightmoveProperty {
+            id,
+            coordinates,
+            display_address: String::new(),
+            price,
+            price_qualifier: PriceQualifier::Standard,
+            shared_ownership_share: None,
+            property_type: PropertyType::Flat,
+            square_feet: None,
+            epc_square_feet: None,
+            imputed_square_feet: None,
+            post_date: Utc::now(),
+            reduced_date: None,
+            transacted: false,
+            status: ListingStatus::Available,
+            details: None,
+            epc: None,
+            borough: None,
+            ward: None,
+            lsoa: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RightmoveSearchResult {
+    pub properties: Vec<RightmoveProperty>,
+    pub coverage: SearchCoverage,
+}
+
+// Price filter values offered by Rightmove, used to split queries that exceed the result cap.
+const BUY_PRICE_LADDER: &[u32] = &[
+    50000, 60000, 70000, 80000, 90000, 100000, 110000, 120000, 125000, 130000, 140000, 150000,
+    160000, 170000, 175000, 180000, 190000, 200000, 210000, 220000, 230000, 240000, 250000, 260000,
+    270000, 280000, 290000, 300000, 325000, 350000, 375000, 400000, 425000, 450000, 475000, 500000,
+    550000, 600000, 650000, 700000, 800000, 900000, 1000000, 1250000, 1500000, 1750000, 2000000,
+    2500000, 3000000, 4000000, 5000000, 7500000, 10000000, 15000000, 20000000,
+];
+const RENT_PRICE_LADDER: &[u32] = &[
+    100, 150, 200, 250, 300, 350, 400, 450, 500, 600, 700, 800, 900, 1000, 1100, 1200, 1250, 1300,
+    1400, 1500, 1750, 2000, 2250, 2500, 2750, 3000, 3500, 4000, 4500, 5000, 5500, 6000, 6500, 7000,
+    8000, 9000, 10000, 12500, 15000, 17500, 20000, 25000, 30000, 35000, 40000,
+];
+// Search radius values offered by Rightmove, in miles.
+const SEARCH_RADII: &[f64] = &[0.25, 0.5, 1.0, 3.0, 5.0, 10.0, 15.0, 20.0, 30.0, 40.0];
+const PROPERTY_SCHEMA: DriftSchema = DriftSchema {
+    name: "Rightmove PropertyResponse",
+    known_fields: &[
+        "addedOrReduced",
+        "auction",
+        "bathrooms",
+        "bedrooms",
+        "channel",
+        "commercial",
+        "contactUrl",
+        "countryCode",
+        "customer",
+        "development",
+        "displayAddress",
+        "displaySize",
+        "displayStatus",
+        "distance",
+        "enhancedListing",
+        "enquiredTimestamp",
+        "enquiryAddedTimestamp",
+        "enquiryCalledTimestamp",
+        "feesApply",
+        "feesApplyText",
+        "featuredProperty",
+        "firstVisibleDate",
+        "formattedBranchName",
+        "formattedDistance",
+        "hasBrandPlus",
+        "heading",
+        "hidden",
+        "id",
+        "isRecent",
+        "keywordMatchType",
+        "keywords",
+        "listingUpdate",
+        "location",
+        "lozengeModel",
+        "numberOfFloorplans",
+        "numberOfImages",
+        "numberOfVirtualTours",
+        "onlineViewingsAvailable",
+        "premiumListing",
+        "price",
+        "productLabel",
+        "propertyImages",
+        "propertySubType",
+        "propertyTypeFullDescription",
+        "propertyUrl",
+        "residential",
+        "saved",
+        "showOnMap",
+        "staticMapUrl",
+        "students",
+        "summary",
+        "transactionType",
+    ],
+    required_fields: &[
+        "id",
+        "location",
+        "price",
+        "firstVisibleDate",
+        "listingUpdate",
+        "propertySubType",
+        "displayStatus",
+    ],
+    enum_fields: &[(
+        "displayStatus",
+        &["", "Let agreed", "Reserved", "Sold STC", "Under offer"],
+    )],
+};
+const PRICE_SCHEMA: DriftSchema = DriftSchema {
+    name: "Rightmove PriceResponse",
+    known_fields: &[
+        "amount",
+        "currencyCode",
+        "displayPrices",
+        "frequency",
+        "qualifier",
+    ],
+    required_fields: &["amount", "frequency", "currencyCode"],
+    enum_fields: &[(
+        "frequency",
+        &[
+            "daily",
+            "monthly",
+            "not specified",
+            "quarterly",
+            "weekly",
+            "yearly",
+        ],
+    )],
+};
+const DISPLAY_PRICE_SCHEMA: DriftSchema = DriftSchema {
+    name: "Rightmove DisplayPriceResponse",
+    known_fields: &["displayPrice", "displayPriceQualifier"],
+    required_fields: &["displayPrice"],
+    enum_fields: &[(
+        "displayPriceQualifier",
+        &[
+            "",
+            "Auction Guide Price",
+            "Fixed Price",
+            "From",
+            "Guide Price",
+            "Offers in Excess of",
+            "Offers in Region of",
+            "Offers Over",
+            "POA",
+            "Shared Ownership",
+        ],
+    )],
+};
+const LISTING_UPDATE_SCHEMA: DriftSchema = DriftSchema {
+    name: "Rightmove ListingUpdateResponse",
+    known_fields: &["listingUpdateDate", "listingUpdateReason"],
+    required_fields: &[],
+    enum_fields: &[("listingUpdateReason", &["new", "price_reduced"])],
+};
+
+const TENURE_SCHEMA: DriftSchema = DriftSchema {
+    name: "Rightmove TenureResponse",
+    known_fields: &["message", "tenureType", "yearsRemainingOnLease"],
+    required_fields: &[],
+    enum_fields: &[(
+        "tenureType",
+        &[
+            "COMMONHOLD",
+            "FEUDAL",
+            "FREEHOLD",
+            "LEASEHOLD",
+            "NON_SCOTTISH",
+            "SHARE_OF_FREEHOLD",
+        ],
+    )],
+};
+
+const PROPERTY_TYPES: &[&str] = &[
+    "bungalow",
+    "detached",
+    "flat",
+    "land",
+    "park-home",
+    "semi-detached",
+    "terraced",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+struct SearchQuery<'a> {
+    location_identifier: &'a str,
+    action: PropertyAction,
+    num_beds: u32,
+    radius: f64,
+    min_price: Option<u32>,
+    max_price: Option<u32>,
+    property_type: Option<&'static str>,
+}
+
+impl<'a> SearchQuery<'a> {
+    fn new(
+        location_identifier: &'a str,
+        action: PropertyAction,
+        num_beds: u32,
+        radius: f64,
+    ) -> SearchQuery<'a> {
+        SearchQuery {
+            location_identifier,
+            action,
+            num_beds,
+            radius,
+            min_price: None,
+            max_price: None,
+            property_type: None,
+        }
+    }
+
+    /// Split a truncated query into narrower queries that together cover the same listings.
+    /// Price bands are halved first, then the narrowest band is split by property type.
+    /// Returns None if the query cannot be narrowed any further.
+    fn split(&self) -> Option<Vec<SearchQuery<'a>>> {
+        let ladder = match self.action {
+            PropertyAction::Buy => BUY_PRICE_LADDER,
+            PropertyAction::Rent => RENT_PRICE_LADDER,
+        };
+        let inner_prices = ladder
+            .iter()
+            .filter(|&&price| self.min_price.is_none_or(|min| price > min))
+            .filter(|&&price| self.max_price.is_none_or(|max| price < max))
+            .collect_vec();
+
+        if self.property_type.is_none() {
+            if let Some(&&pivot) = inner_prices.get(inner_prices.len() / 2) {
+                // Rightmove price bounds are inclusive, so listings priced at the pivot
+                // appear in both halves and are de-duplicated later.
+                return Some(vec![
+                    SearchQuery {
+                        max_price: Some(pivot),
+                        ..self.clone()
+                    },
+                    SearchQuery {
+                        min_price: Some(pivot),
+                        ..self.clone()
+                    },
+                ]);
+            }
+            return Some(
+                PROPERTY_TYPES
+                    .iter()
+                    .map(|&property_type| SearchQuery {
+                        property_type: Some(property_type),
+                        ..self.clone()
+                    })
+                    .collect(),
+            );
+        }
+        None
+    }
 }
 
 impl Rightmove {
@@ -47,10 +344,25 @@ impl Rightmove {
                     referer: None,
                 }),
             ),
+            drift: DriftMonitor::new(),
         }
     }
 
-    pub async fn get_location_identifier(&self, postcode: String) -> Result<String> {
+    /// Smallest search radius Rightmove offers that covers `miles`.
+    pub fn search_radius_covering(miles: f64) -> f64 {
+        SEARCH_RADII
+            .iter()
+            .copied()
+            .find(|&radius| radius >= miles)
+            .unwrap_or(SEARCH_RADII[SEARCH_RADII.len() - 1])
+    }
+
+    /// Schema drift seen in responses so far.
+    pub fn drift(&self) -> &DriftMonitor {
+        &self.drift
+    }
+
+    pub async fn get_location_identifier(&self, postcode: String) -> Result<String, ScraperError> {
         #[derive(Debug, Serialize, Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct LocationIdentifierResponse {
@@ -66,24 +378,25 @@ impl Rightmove {
             "https://www.rightmove.co.uk/property-for-sale/search.html?searchLocation={}",
             &postcode
         );
-        let html = self.http.get(url).await?.text().await?;
+        let html = self.http.get(&url).await?.text_or_err().await?;
         lazy_static! {
             static ref SELECTOR: Selector = Selector::parse("#locationIdentifier").unwrap();
         }
-        match Html::parse_document(&html)
+        let maybe_element = Html::parse_document(&html)
             .select(&SELECTOR)
             .next()
-            .unwrap()
-            .value()
-            .attr("value")
-            .filter(|location_identifier| !location_identifier.is_empty())
-            .map(|location_identifier| location_identifier.to_owned())
-        {
-            Some(location_identifier) => Ok(location_identifier),
-            None => bail!(
-                "Location identifier not found for postcode: [{}]!",
-                postcode
-            ),
+            .map(|element| element.value().attr("value").map(|v| v.to_owned()));
+        match maybe_element {
+            Some(Some(location_identifier)) if !location_identifier.is_empty() => {
+                Ok(location_identifier)
+            }
+            Some(_) => Err(ScraperError::NotFound { url }),
+            None => Err(ScraperError::schema_changed(
+                &url,
+                "Location identifier input missing from search page".to_owned(),
+                &format!("Postcode: [{}]", postcode),
+                &html,
+            )),
         }
     }
 
@@ -93,12 +406,12 @@ impl Rightmove {
         action: PropertyAction,
         num_beds: u32,
         radius: f64,
-    ) -> Result<Vec<RightmoveProperty>> {
+    ) -> Result<RightmoveSearchResult, ScraperError> {
         #[derive(Debug, Serialize, Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct SearchResponse {
             result_count: String,
-            properties: Vec<PropertyResponse>,
+            properties: Vec<Value>, // checked for schema drift before decoding into PropertyResponse
             pagination: PaginationResponse,
         }
 
@@ -107,12 +420,18 @@ impl Rightmove {
         struct PropertyResponse {
             id: u32,
             location: LocationResponse,
+            #[serde(default)]
+            display_address: String,
             price: PriceResponse,
             display_size: Option<String>,
             first_visible_date: String,
             listing_update: ListingUpdateResponse,
             property_sub_type: String,
             display_status: String,
+            #[serde(default)]
+            auction: bool,
+            #[serde(default)]
+            summary: String,
         }
 
         #[derive(Debug, Serialize, Deserialize)]
@@ -128,6 +447,16 @@ impl Rightmove {
             amount: u32,
             frequency: String,
             currency_code: String,
+            #[serde(default)]
+            display_prices: Vec<DisplayPriceResponse>,
+        }
+
+        #[derive(Debug, Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DisplayPriceResponse {
+            display_price: String,
+            #[serde(default)]
+            display_price_qualifier: String,
         }
 
         #[derive(Debug, Serialize, Deserialize)]
@@ -143,35 +472,34 @@ impl Rightmove {
             total: u32,
         }
 
+        const SEARCH_URL: &str = "https://www.rightmove.co.uk/api/_search";
+
         async fn search_pagination(
             _self: &Rightmove,
-            location_identifier: &str,
-            action: PropertyAction,
-            num_beds: u32,
-            radius: f64,
+            search_query: &SearchQuery<'_>,
             pagination_index: u32,
-        ) -> Result<SearchResponse> {
+        ) -> Result<SearchResponse, ScraperError> {
             const NUM_PROPERTIES_PER_PAGE: u32 = 24;
-            let url = "https://www.rightmove.co.uk/api/_search";
-            let query = &[
-                ("locationIdentifier", location_identifier),
-                ("maxBedrooms", &num_beds.to_string()),
-                ("minBedrooms", &num_beds.to_string()),
-                (
-                    "numberOfPropertiesPerPage",
-                    &NUM_PROPERTIES_PER_PAGE.to_string(),
-                ),
-                ("radius", &radius.to_string()),
-                (
-                    "index",
-                    &(pagination_index * NUM_PROPERTIES_PER_PAGE).to_string(),
-                ),
+            const DEFAULT_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(10);
+            let num_beds = search_query.num_beds.to_string();
+            let radius = search_query.radius.to_string();
+            let num_properties_per_page = NUM_PROPERTIES_PER_PAGE.to_string();
+            let index = (pagination_index * NUM_PROPERTIES_PER_PAGE).to_string();
+            let min_price = search_query.min_price.map(|p| p.to_string());
+            let max_price = search_query.max_price.map(|p| p.to_string());
+            let mut query = vec![
+                ("locationIdentifier", search_query.location_identifier),
+                ("maxBedrooms", &num_beds),
+                ("minBedrooms", &num_beds),
+                ("numberOfPropertiesPerPage", &num_properties_per_page),
+                ("radius", &radius),
+                ("index", &index),
                 ("includeSSTC", "true"),
                 ("includeLetAgreed", "true"),
                 ("viewType", "LIST"),
                 (
                     "channel",
-                    match action {
+                    match search_query.action {
                         PropertyAction::Buy => "BUY",
                         PropertyAction::Rent => "RENT",
                     },
@@ -179,36 +507,88 @@ impl Rightmove {
                 ("areaSizeUnit", "sqft"),
                 ("currencyCode", "GBP"),
                 ("dontShow", "retirement"),
-                ("dontShow", "sharedOwnership"),
             ];
+            if let Some(min_price) = &min_price {
+                query.push(("minPrice", min_price));
+            }
+            if let Some(max_price) = &max_price {
+                query.push(("maxPrice", max_price));
+            }
+            if let Some(property_type) = search_query.property_type {
+                query.push(("propertyTypes", property_type));
+            }
             // Sometimes rightmove returns 400, so we allow retries.
             let mut remaining_tries = 3;
             loop {
-                let result = _self
-                    .http
-                    .get_with_options(url, query, true)
-                    .await?
-                    .json_or_err(&format!("Rightmove query [{:?}]", &query))
-                    .await;
-                remaining_tries = remaining_tries - 1;
-
-                if result.as_ref().is_ok() || remaining_tries == 0 {
-                    return result;
+                let result: Result<SearchResponse, ScraperError> = async {
+                    _self
+                        .http
+                        .get_with_options(SEARCH_URL, &query, true)
+                        .await?
+                        .json_or_err(&format!("Rightmove query [{:?}]", &query))
+                        .await
+                }
+                .await;
+                remaining_tries -= 1;
+
+                match result {
+                    Err(err)
+                        if err.action() == ScraperErrorAction::Backoff && remaining_tries > 0 =>
+                    {
+                        if let ScraperError::RateLimited { retry_after, .. } = err {
+                            sleep(retry_after.unwrap_or(DEFAULT_RATE_LIMIT_BACKOFF)).await;
+                        }
+                    }
+                    _ => return result,
                 }
             }
         }
 
-        let response =
-            search_pagination(&self, &location_identifier, action, num_beds, radius, 0).await?;
-        let more_responses = join_all(
-            (1..response.pagination.total)
+        /// Fetch every page of a query, returning the reported result count and all listings.
+        async fn search_all_pages(
+            _self: &Rightmove,
+            search_query: &SearchQuery<'_>,
+        ) -> Result<(u32, Vec<PropertyResponse>), ScraperError> {
+            let response = search_pagination(_self, search_query, 0).await?;
+            let more_responses = try_join_all(
+                (1..response.pagination.total)
+                    .map(|index| search_pagination(_self, search_query, index)),
+            )
+            .await?;
+            let result_count = parse_result_count(&response.result_count)?;
+            let properties = response
+                .properties
                 .into_iter()
-                .map(|index| {
-                    search_pagination(&self, &location_identifier, action, num_beds, radius, index)
-                })
-                .collect_vec(),
-        )
-        .await;
+                .chain(more_responses.into_iter().flat_map(|r| r.properties))
+                .filter_map(|value| decode_property(&_self.drift, value))
+                .collect_vec();
+            Ok((result_count, properties))
+        }
+
+        fn decode_property(drift: &DriftMonitor, value: Value) -> Option<PropertyResponse> {
+            drift.inspect(&PROPERTY_SCHEMA, &value);
+            drift.inspect(&PRICE_SCHEMA, &value["price"]);
+            drift.inspect(&LISTING_UPDATE_SCHEMA, &value["listingUpdate"]);
+            if let Some(display_prices) = value["price"]["displayPrices"].as_array() {
+                for display_price in display_prices {
+                    drift.inspect(&DISPLAY_PRICE_SCHEMA, display_price);
+                }
+            }
+            serde_json::from_value(value)
+                .map_err(|e| warn!("Skipping undecodable Rightmove property: {}", e))
+                .ok()
+        }
+
+        fn parse_result_count(result_count: &str) -> Result<u32, ScraperError> {
+            result_count.replace(',', "").parse::<u32>().map_err(|e| {
+                ScraperError::schema_changed(
+                    SEARCH_URL,
+                    format!("Failed to parse result count: [{result_count}]: {e}"),
+                    "Rightmove search result count",
+                    result_count,
+                )
+            })
+        }
 
         fn parse_square_feet(maybe_display_size: Option<String>) -> Option<i32> {
             lazy_static! {
@@ -225,12 +605,38 @@ impl Rightmove {
             DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
         }
 
-        fn parse_price(price: PriceResponse) -> u32 {
-            match price.frequency.as_str() {
-                "weekly" => price.amount * 52 / 12,
-                "yearly" => price.amount / 12,
-                "monthly" | "not specified" => price.amount,
-                _ => panic!("Unrecognised frequency in price response: {:?}", price),
+        /// Normalise the advertised price into a total (if buy) / monthly (if rent) price for
+        /// the whole property, along with how it was qualified and any shared ownership share.
+        /// Unrecognised frequencies are recorded as schema drift, so just skip those listings.
+        /// Shared ownership listings without a known share are skipped too, as only the share's
+        /// price is advertised.
+        fn parse_price(
+            property: &PropertyResponse,
+            num_beds: u32,
+        ) -> Option<(u32, PriceQualifier, Option<f64>)> {
+            let frequency = RentFrequency::parse(&property.price.frequency)?;
+            let display_price = property.price.display_prices.first();
+            let qualifier = if property.auction {
+                PriceQualifier::Auction
+            } else if property.summary.to_lowercase().contains("shared ownership") {
+                PriceQualifier::SharedOwnership
+            } else {
+                display_price.map_or(PriceQualifier::Standard, |d| {
+                    PriceQualifier::parse(&d.display_price_qualifier, &d.display_price)
+                })
+            };
+            let amount = frequency.to_monthly(property.price.amount);
+            match qualifier {
+                PriceQualifier::SharedOwnership => {
+                    let share = parse_shared_ownership_share(&property.summary).or_else(|| {
+                        display_price.and_then(|d| parse_shared_ownership_share(&d.display_price))
+                    });
+                    let share = share?;
+                    let full_amount = (amount as f64 / share).round() as u32;
+                    Some((full_amount, qualifier, Some(share)))
+                }
+                PriceQualifier::PerPerson => Some((amount * num_beds.max(1), qualifier, None)),
+                _ => Some((amount, qualifier, None)),
             }
         }
 
@@ -252,40 +658,215 @@ impl Rightmove {
             return BLACKLISTED_PROPERTY_SUBTYPES.contains(&property_response.property_sub_type);
         }
 
-        let properties: Vec<RightmoveProperty> = iter::once(response)
-            .chain(more_responses.unwrap_all().into_iter())
-            .flat_map(|r| r.properties.into_iter())
-            .filter(|property| !is_blacklisted(property))
+        // Rightmove caps the number of results a single query can page through, so keep
+        // splitting queries whose retrieved listings fall short of the reported result count.
+        let mut coverage = SearchCoverage::default();
+        let mut property_responses = vec![];
+        let mut pending_queries = vec![SearchQuery::new(
+            &location_identifier,
+            action,
+            num_beds,
+            radius,
+        )];
+        while !pending_queries.is_empty() {
+            let results = join_all(
+                pending_queries
+                    .iter()
+                    .map(|search_query| search_all_pages(self, search_query)),
+            )
+            .await;
+            let mut next_queries = vec![];
+            for (search_query, result) in pending_queries.iter().zip(results) {
+                let (result_count, responses) = result?;
+                if coverage.num_queries == 0 {
+                    coverage.result_count = result_count;
+                }
+                coverage.num_queries += 1;
+
+                let retrieved = responses.iter().map(|r| r.id).unique().count() as u32;
+                if retrieved < result_count {
+                    match search_query.split() {
+                        Some(narrower_queries) => next_queries.extend(narrower_queries),
+                        None => coverage.num_truncated += 1,
+                    }
+                }
+                property_responses.extend(responses);
+            }
+            pending_queries = next_queries;
+        }
+
+        let unique_responses = property_responses
+            .into_iter()
             .sorted_by_key(|property| property.id)
             .dedup_by(|p1, p2| p1.id == p2.id)
-            .map(|property| RightmoveProperty {
-                id: property.id,
-                coordinates: (property.location.longitude, property.location.latitude),
-                price: parse_price(property.price),
-                square_feet: parse_square_feet(property.display_size),
-                post_date: parse_date(&property.first_visible_date),
-                reduced_date: property
-                    .listing_update
-                    .listing_update_reason
-                    .and_then(|reason| match reason.as_str() {
-                        "price_reduced" => property
-                            .listing_update
-                            .listing_update_date
-                            .map(|date| parse_date(&date)),
-                        _ => None,
-                    }),
-                transacted: property.display_status == "Let agreed"
-                    || property.display_status == "Sold STC"
-                    || property.display_status == "Under offer",
+            .collect_vec();
+        coverage.retrieved = unique_responses.len() as u32;
+
+        let properties: Vec<RightmoveProperty> = unique_responses
+            .into_iter()
+            .filter(|property| !is_blacklisted(property))
+            .filter_map(|property| {
+                let (price, price_qualifier, shared_ownership_share) =
+                    parse_price(&property, num_beds)?;
+                Some(RightmoveProperty {
+                    id: property.id,
+                    coordinates: (property.location.longitude, property.location.latitude),
+                    display_address: property.display_address.clone(),
+                    price,
+                    price_qualifier,
+                    shared_ownership_share,
+                    property_type: PropertyType::from_sub_type(&property.property_sub_type),
+                    square_feet: parse_square_feet(property.display_size),
+                    epc_square_feet: None,
+                    imputed_square_feet: None,
+                    post_date: parse_date(&property.first_visible_date),
+                    reduced_date: property.listing_update.listing_update_reason.and_then(
+                        |reason| match reason.as_str() {
+                            "price_reduced" => property
+                                .listing_update
+                                .listing_update_date
+                                .map(|date| parse_date(&date)),
+                            _ => None,
+                        },
+                    ),
+                    transacted: property.display_status == "Let agreed"
+                        || property.display_status == "Sold STC"
+                        || property.display_status == "Under offer",
+                    status: ListingStatus::parse(&property.display_status),
+                    details: None,
+                    epc: None,
+                    borough: None,
+                    ward: None,
+                    lsoa: None,
+                })
             })
             .collect();
-        Ok(properties)
+        Ok(RightmoveSearchResult {
+            properties,
+            coverage,
+        })
+    }
+
+    /// Fetch the fields only shown on a listing's detail page.
+    pub async fn get_listing_details(&self, id: u32) -> Result<ListingDetails, ScraperError> {
+        let url = format!("https://www.rightmove.co.uk/properties/{}", id);
+        let html = self.http.get(&url).await?.text_or_err().await?;
+        parse_listing_details(&self.drift, &url, id, &html)
     }
 }
 
+/// Parse the page model embedded in a listing's detail page.
+fn parse_listing_details(
+    drift: &DriftMonitor,
+    url: &str,
+    id: u32,
+    html: &str,
+) -> Result<ListingDetails, ScraperError> {
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PageModel {
+        property_data: PropertyDataResponse,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PropertyDataResponse {
+        #[serde(default)]
+        tenure: Value, // checked for schema drift before decoding into TenureResponse
+        #[serde(default)]
+        living_costs: Option<LivingCostsResponse>,
+        #[serde(default)]
+        key_features: Vec<String>,
+        #[serde(default)]
+        text: Option<TextResponse>,
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TenureResponse {
+        tenure_type: Option<String>,
+        years_remaining_on_lease: Option<u32>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct LivingCostsResponse {
+        annual_service_charge: Option<f64>,
+        annual_ground_rent: Option<f64>,
+        council_tax_band: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TextResponse {
+        #[serde(default)]
+        description: String,
+    }
+
+    lazy_static! {
+        static ref PAGE_MODEL_RE: Regex =
+            Regex::new(r"(?s)window\.PAGE_MODEL\s*=\s*(\{.*?\})\s*;?\s*</script>").unwrap();
+    }
+    let context = format!("Listing id: [{}]", id);
+    let page_model_json = PAGE_MODEL_RE
+        .captures(html)
+        .map(|caps| caps.get(1).unwrap().as_str())
+        .ok_or_else(|| {
+            ScraperError::schema_changed(
+                url,
+                "Page model missing from listing page".to_owned(),
+                &context,
+                html,
+            )
+        })?;
+    let page_model: PageModel = serde_json::from_str(page_model_json)
+        .map_err(|e| ScraperError::schema_changed(url, e.to_string(), &context, page_model_json))?;
+    let property_data = page_model.property_data;
+
+    let tenure = if property_data.tenure.is_null() {
+        TenureResponse::default()
+    } else {
+        drift.inspect(&TENURE_SCHEMA, &property_data.tenure);
+        serde_json::from_value(property_data.tenure).unwrap_or_default()
+    };
+    let living_costs = property_data.living_costs;
+    let description = property_data
+        .text
+        .map(|t| t.description)
+        .unwrap_or_default();
+    let epc_band = property_data
+        .key_features
+        .iter()
+        .chain([&description])
+        .find_map(|text| EpcBand::find_in_text(text));
+
+    Ok(ListingDetails {
+        id,
+        fetched: Utc::now().timestamp_millis(),
+        tenure: tenure.tenure_type.as_deref().and_then(Tenure::parse),
+        lease_years_remaining: tenure.years_remaining_on_lease,
+        annual_service_charge: living_costs
+            .as_ref()
+            .and_then(|c| c.annual_service_charge)
+            .filter(|&c| c > 0.0),
+        annual_ground_rent: living_costs
+            .as_ref()
+            .and_then(|c| c.annual_ground_rent)
+            .filter(|&c| c > 0.0),
+        council_tax_band: living_costs
+            .and_then(|c| c.council_tax_band)
+            .filter(|band| !band.is_empty()),
+        epc_band,
+        price_history: vec![],
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{PropertyAction, Rightmove};
+    use super::{
+        parse_listing_details, DriftMonitor, EpcBand, PropertyAction, Rightmove, SearchQuery,
+        Tenure, PROPERTY_TYPES,
+    };
     use crate::lib::util::globals::Globals;
     use itertools::Itertools;
     use more_asserts::assert_gt;
@@ -305,11 +886,12 @@ mod tests {
     async fn test_search() {
         let globals = Globals::new().await;
         let rightmove = Rightmove::new(&globals);
-        let properties = rightmove
+        let result = rightmove
             .search("POSTCODE^544984".to_owned(), PropertyAction::Buy, 2, 0.25)
             .await
             .unwrap();
-        assert_gt!(properties.len(), 10);
+        assert_gt!(result.properties.len(), 10);
+        assert_eq!(result.coverage.num_truncated, 0);
     }
 
     #[tokio::test]
@@ -319,10 +901,53 @@ mod tests {
         let properties = rightmove
             .search("POSTCODE^544984".to_owned(), PropertyAction::Buy, 2, 0.25)
             .await
-            .unwrap();
+            .unwrap()
+            .properties;
         assert_eq!(
             properties.iter().map(|p| p.id).sorted().dedup().count(),
             properties.len()
         );
     }
+
+    #[test]
+    fn test_search_query_split() {
+        let query = SearchQuery::new("POSTCODE^544984", PropertyAction::Rent, 1, 0.25);
+
+        let halves = query.split().unwrap();
+        assert_eq!(halves.len(), 2);
+        assert_eq!(halves[0].min_price, None);
+        assert_eq!(halves[0].max_price, halves[1].min_price);
+        assert_eq!(halves[1].max_price, None);
+
+        let narrowest = SearchQuery {
+            min_price: Some(1200),
+            max_price: Some(1250),
+            ..query.clone()
+        };
+        let by_type = narrowest.split().unwrap();
+        assert_eq!(by_type.len(), PROPERTY_TYPES.len());
+        assert!(by_type
+            .iter()
+            .all(|q| q.min_price == Some(1200) && q.max_price == Some(1250)));
+
+        assert_eq!(by_type[0].split(), None);
+    }
+
+    #[test]
+    fn test_parse_listing_details() {
+        let html = r#"<html><script>window.PAGE_MODEL = {"propertyData":{"tenure":{"tenureType":"LEASEHOLD","yearsRemainingOnLease":95,"message":null},"livingCosts":{"councilTaxBand":"C","annualServiceCharge":1800.0,"annualGroundRent":250.0},"keyFeatures":["Two double bedrooms","EPC Rating: C"],"text":{"description":"A lovely flat."}}}</script></html>"#;
+        let drift = DriftMonitor::new();
+        let details =
+            parse_listing_details(&drift, "https://www.rightmove.co.uk/properties/1", 1, html)
+                .unwrap();
+        assert_eq!(details.tenure, Some(Tenure::Leasehold));
+        assert_eq!(details.lease_years_remaining, Some(95));
+        assert_eq!(details.annual_service_charge, Some(1800.0));
+        assert_eq!(details.annual_ground_rent, Some(250.0));
+        assert_eq!(details.council_tax_band, Some("C".to_owned()));
+        assert_eq!(details.epc_band, Some(EpcBand::C));
+        assert!(drift.report().is_empty());
+
+        assert!(parse_listing_details(&drift, "", 1, "<html></html>").is_err());
+    }
 }
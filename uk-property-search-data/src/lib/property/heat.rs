@@ -0,0 +1,180 @@
+use super::property::PropertySummary;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// How hot the market near a station is in one run, relative to all stations. Each component is
+/// standardised against the other stations for the same action and number of beds, as e.g. studios
+/// let faster than 3 beds everywhere. The score is their mean with signs chosen so that higher is
+/// hotter: more new listings, fewer reductions, more transacted and less time on market.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketHeat {
+    pub date: i64, // unix milliseconds
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub action: u8,
+    pub num_beds: u32,
+    pub score: Option<f64>, // None if no component could be measured
+    pub new_listing_velocity: Option<f64>, // new or relisted listings per week, over those available
+    pub percent_reduced: f64,
+    pub percent_transacted: f64,
+    pub median_listed_days: f64,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketHeatPoint {
+    pub date: i64, // unix milliseconds
+    pub score: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketHeatHistory {
+    pub latest: MarketHeat,
+    pub history: Vec<MarketHeatPoint>, // oldest first, including the latest
+}
+
+impl MarketHeat {
+    pub fn from_summaries(date: i64, summaries: &[PropertySummary]) -> Vec<MarketHeat> {
+        let mut heats = summaries
+            .iter()
+            .map(|summary| MarketHeat {
+                date,
+                postcode: summary.postcode.clone(),
+                coordinates: summary.coordinates,
+                action: summary.action,
+                num_beds: summary.num_beds,
+                score: None,
+                new_listing_velocity: summary.market_speed.weekly_new_supply_rate,
+                percent_reduced: summary.stats.percent_reduced,
+                percent_transacted: summary.stats.percent_transacted,
+                median_listed_days: summary.stats.listed_days.median,
+            })
+            .collect_vec();
+        MarketHeat::score_all(&mut heats);
+        heats
+    }
+
+    /// Score each heat against the others for the same action and number of beds.
+    fn score_all(heats: &mut [MarketHeat]) {
+        // Standardise against the finite values, or None if there are fewer than two of them.
+        fn standardiser(values: Vec<f64>) -> impl Fn(f64) -> Option<f64> {
+            let values = values.into_iter().filter(|v| v.is_finite()).collect_vec();
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+            move |value: f64| {
+                let z = if std_dev > 0.0 {
+                    (value - mean) / std_dev
+                } else {
+                    0.0
+                };
+                (n >= 2.0 && value.is_finite()).then_some(z)
+            }
+        }
+
+        let groups = heats
+            .iter()
+            .map(|heat| (heat.action, heat.num_beds))
+            .unique()
+            .collect_vec();
+        for group in groups {
+            let in_group = |heat: &MarketHeat| (heat.action, heat.num_beds) == group;
+            let component = |f: fn(&MarketHeat) -> f64| {
+                standardiser(heats.iter().filter(|h| in_group(h)).map(f).collect_vec())
+            };
+            let velocity = component(|heat| heat.new_listing_velocity.unwrap_or(f64::NAN));
+            let reduced = component(|heat| heat.percent_reduced);
+            let transacted = component(|heat| heat.percent_transacted);
+            let listed_days = component(|heat| heat.median_listed_days);
+
+            for heat in heats.iter_mut().filter(|heat| in_group(heat)) {
+                let components = [
+                    velocity(heat.new_listing_velocity.unwrap_or(f64::NAN)),
+                    reduced(heat.percent_reduced).map(|z| -z),
+                    transacted(heat.percent_transacted),
+                    listed_days(heat.median_listed_days).map(|z| -z),
+                ]
+                .into_iter()
+                .flatten()
+                .collect_vec();
+                heat.score = (!components.is_empty())
+                    .then(|| components.iter().sum::<f64>() / components.len() as f64);
+            }
+        }
+    }
+}
+
+#[allow(dead_code)] // only used by the server
+impl MarketHeatHistory {
+    pub fn from_heats(heats: Vec<MarketHeat>) -> Vec<MarketHeatHistory> {
+        heats
+            .into_iter()
+            .into_group_map_by(|heat| (heat.postcode.clone(), heat.action, heat.num_beds))
+            .into_values()
+            .filter_map(|mut group| {
+                group.sort_by_key(|heat| heat.date);
+                let history = group
+                    .iter()
+                    .map(|heat| MarketHeatPoint {
+                        date: heat.date,
+                        score: heat.score,
+                    })
+                    .collect_vec();
+                Some(MarketHeatHistory {
+                    latest: group.pop()?,
+                    history,
+                })
+            })
+            .sorted_by(|a, b| {
+                (&a.latest.postcode, a.latest.action, a.latest.num_beds).cmp(&(
+                    &b.latest.postcode,
+                    b.latest.action,
+                    b.latest.num_beds,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MarketHeat;
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_score_all() {
+        let heat =
+            |postcode: &str, num_beds: u32, percent_reduced: f64, median_listed_days: f64| {
+                MarketHeat {
+                    date: 0,
+                    postcode: postcode.to_owned(),
+                    coordinates: (-0.123795, 51.530312),
+                    action: 1,
+                    num_beds,
+                    score: None,
+                    new_listing_velocity: None,
+                    percent_reduced,
+                    percent_transacted: 0.2,
+                    median_listed_days,
+                }
+            };
+        let mut heats = vec![
+            heat("N1 9AL", 2, 0.1, 10.0),
+            heat("E1 6AN", 2, 0.3, 30.0),
+            // Larger homes sit on the market longer everywhere, so only compare them with each
+            // other.
+            heat("N1 9AL", 3, 0.2, 40.0),
+            heat("E1 6AN", 3, 0.4, 60.0),
+        ];
+
+        MarketHeat::score_all(&mut heats);
+        // Fewer reductions and less time on market, so hotter. Transacted shares are the same, so
+        // that component is neutral.
+        assert_almost_eq!(heats[0].score.unwrap(), 2.0 / 3.0, 1e-9);
+        assert_almost_eq!(heats[1].score.unwrap(), -2.0 / 3.0, 1e-9);
+        assert_almost_eq!(heats[2].score.unwrap(), 2.0 / 3.0, 1e-9);
+        assert_almost_eq!(heats[3].score.unwrap(), -2.0 / 3.0, 1e-9);
+    }
+}
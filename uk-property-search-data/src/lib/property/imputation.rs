@@ -0,0 +1,127 @@
+use super::{estate_agents::rightmove::RightmoveProperty, property::PropertyType};
+use crate::lib::math::stats::Stats;
+use itertools::Itertools;
+use std::{collections::HashMap, hash::Hash};
+
+// Fewer observations than this are too noisy to estimate from, so fall back to a broader group.
+const MIN_OBSERVATIONS: usize = 3;
+
+/// Estimates missing square footage from the median of similar listings with a known size,
+/// trying the same station, beds and type first, then beds and type anywhere, then beds anywhere.
+pub struct SquareFeetImputer {
+    by_station_beds_type: HashMap<(String, u32, PropertyType), f64>,
+    by_beds_type: HashMap<(u32, PropertyType), f64>,
+    by_beds: HashMap<u32, f64>,
+}
+
+impl SquareFeetImputer {
+    /// Fit on (station postcode, num beds, listing) for all listings in the run.
+    pub fn fit<'a>(listings: impl Iterator<Item = (&'a str, u32, &'a RightmoveProperty)>) -> Self {
+        let observed = listings
+            .filter_map(|(station, num_beds, p)| {
+                p.known_square_feet()
+                    .map(|square_feet| (station, num_beds, p.property_type, square_feet))
+            })
+            .collect_vec();
+
+        fn medians<K: Eq + Hash>(groups: impl Iterator<Item = (K, i32)>) -> HashMap<K, f64> {
+            groups
+                .into_group_map()
+                .into_iter()
+                .filter(|(_, values)| values.len() >= MIN_OBSERVATIONS)
+                .map(|(key, values)| (key, Stats::from_vec(&values).median))
+                .collect()
+        }
+
+        SquareFeetImputer {
+            by_station_beds_type: medians(observed.iter().map(
+                |&(station, num_beds, property_type, square_feet)| {
+                    ((station.to_owned(), num_beds, property_type), square_feet)
+                },
+            )),
+            by_beds_type: medians(observed.iter().map(
+                |&(_, num_beds, property_type, square_feet)| {
+                    ((num_beds, property_type), square_feet)
+                },
+            )),
+            by_beds: medians(
+                observed
+                    .iter()
+                    .map(|&(_, num_beds, _, square_feet)| (num_beds, square_feet)),
+            ),
+        }
+    }
+
+    pub fn estimate(
+        &self,
+        station: &str,
+        num_beds: u32,
+        property_type: PropertyType,
+    ) -> Option<f64> {
+        self.by_station_beds_type
+            .get(&(station.to_owned(), num_beds, property_type))
+            .or_else(|| self.by_beds_type.get(&(num_beds, property_type)))
+            .or_else(|| self.by_beds.get(&num_beds))
+            .copied()
+    }
+
+    /// Fill in imputed square footage for listings without a known size.
+    pub fn impute(&self, station: &str, num_beds: u32, properties: &mut [RightmoveProperty]) {
+        for property in properties
+            .iter_mut()
+            .filter(|p| p.known_square_feet().is_none())
+        {
+            property.imputed_square_feet = self
+                .estimate(station, num_beds, property.property_type)
+                .map(|square_feet| square_feet.round() as i32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SquareFeetImputer;
+    use crate::lib::property::{
+        estate_agents::rightmove::RightmoveProperty, property::PropertyType,
+    };
+    use itertools::Itertools;
+
+    fn property(property_type: PropertyType, square_feet: Option<i32>) -> RightmoveProperty {
+        RightmoveProperty {
+            property_type,
+            square_feet,
+            ..RightmoveProperty::test_listing(0, (-0.122191, 51.53419), 500000)
+        }
+    }
+
+    #[test]
+    fn test_estimate_falls_back_to_broader_groups() {
+        let flats = [500, 600, 700].map(|sqft| property(PropertyType::Flat, Some(sqft)));
+        let houses = [1000, 1100].map(|sqft| property(PropertyType::Terraced, Some(sqft)));
+        let listings = flats
+            .iter()
+            .map(|p| ("N1 9AL", 2, p))
+            .chain(houses.iter().map(|p| ("E1 6AN", 2, p)))
+            .collect_vec();
+        let imputer = SquareFeetImputer::fit(listings.into_iter());
+
+        assert_eq!(
+            imputer.estimate("N1 9AL", 2, PropertyType::Flat),
+            Some(600.0)
+        );
+        assert_eq!(
+            imputer.estimate("E1 6AN", 2, PropertyType::Flat),
+            Some(600.0)
+        );
+        // Too few terraced houses, so use all 2 bed listings instead.
+        assert_eq!(
+            imputer.estimate("E1 6AN", 2, PropertyType::Terraced),
+            Some(700.0)
+        );
+        assert_eq!(imputer.estimate("E1 6AN", 3, PropertyType::Flat), None);
+
+        let mut missing = vec![property(PropertyType::Flat, None)];
+        imputer.impute("N1 9AL", 2, &mut missing);
+        assert_eq!(missing[0].imputed_square_feet, Some(600));
+    }
+}
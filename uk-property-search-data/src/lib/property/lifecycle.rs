@@ -0,0 +1,287 @@
+use super::{estate_agents::rightmove::RightmoveProperty, property::ListingStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Forget withdrawn listings after a year, after which a reappearance counts as new.
+const WITHDRAWN_RETENTION_MS: i64 = 365 * 24 * 60 * 60 * 1000;
+const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// The last known state of a listing near a station, carried between runs.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListingSnapshot {
+    pub id: u32,
+    pub postcode: String,        // of the station the listing was found near
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub action: u8,
+    pub num_beds: u32,
+    pub price: u32,
+    pub status: ListingStatus,
+    pub reduced_date: Option<i64>, // unix milliseconds
+    pub first_seen: i64,           // unix milliseconds
+    pub last_seen: i64,            // unix milliseconds
+    pub withdrawn: bool,
+    #[serde(default)]
+    pub borough: Option<String>, // GSS code, None outside the borough boundaries
+    #[serde(default)]
+    pub ward: Option<String>, // GSS code
+    #[serde(default)]
+    pub lsoa: Option<String>, // GSS code
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ListingEventKind {
+    New,
+    PriceChange,
+    Reduced,
+    UnderOffer,
+    SoldStc,
+    LetAgreed,
+    Withdrawn,
+    Relisted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListingEvent {
+    pub id: u32,
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub action: u8,
+    pub num_beds: u32,
+    pub date: i64, // unix milliseconds
+    pub kind: ListingEventKind,
+    pub price: u32,
+    pub previous_price: Option<u32>, // for price changes and reductions
+    pub listed_days: Option<f64>,    // since first seen, for Sold STC / Let agreed
+}
+
+/// (station postcode, action, num beds) of a search.
+pub type SearchKey = (String, u8, u32);
+
+impl ListingSnapshot {
+    pub fn new(
+        postcode: &str,
+        action: u8,
+        num_beds: u32,
+        property: &RightmoveProperty,
+        now: i64,
+    ) -> ListingSnapshot {
+        ListingSnapshot {
+            id: property.id,
+            postcode: postcode.to_owned(),
+            coordinates: property.coordinates,
+            action,
+            num_beds,
+            price: property.price,
+            status: property.status,
+            reduced_date: property.reduced_date.map(|date| date.timestamp_millis()),
+            first_seen: now,
+            last_seen: now,
+            withdrawn: false,
+            borough: property.borough.clone(),
+            ward: property.ward.clone(),
+            lsoa: property.lsoa.clone(),
+        }
+    }
+
+    fn search_key(&self) -> SearchKey {
+        (self.postcode.clone(), self.action, self.num_beds)
+    }
+
+    fn event(
+        &self,
+        kind: ListingEventKind,
+        date: i64,
+        previous_price: Option<u32>,
+    ) -> ListingEvent {
+        ListingEvent {
+            id: self.id,
+            postcode: self.postcode.clone(),
+            coordinates: self.coordinates,
+            action: self.action,
+            num_beds: self.num_beds,
+            date,
+            kind,
+            price: self.price,
+            previous_price,
+            listed_days: match kind {
+                ListingEventKind::SoldStc | ListingEventKind::LetAgreed => {
+                    Some((date - self.first_seen) as f64 / DAY_MS)
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Compare this run's listings with the previous run's, returning the snapshots to keep for the
+/// next run and the events in between. Listings are only treated as withdrawn if they were
+/// missing from a search that completed in full, so failed or truncated searches aren't
+/// mistaken for listings leaving the market. The first run has nothing to compare with, so it
+/// only records the baseline, rather than every listing already on the market being new.
+pub fn diff(
+    previous: Vec<ListingSnapshot>,
+    current: Vec<ListingSnapshot>,
+    complete_searches: &HashSet<SearchKey>,
+    now: i64,
+) -> (Vec<ListingSnapshot>, Vec<ListingEvent>) {
+    if previous.is_empty() {
+        return (current, vec![]);
+    }
+    let mut previous_by_key: HashMap<(String, u8, u32), ListingSnapshot> = previous
+        .into_iter()
+        .map(|snapshot| {
+            (
+                (snapshot.postcode.clone(), snapshot.action, snapshot.id),
+                snapshot,
+            )
+        })
+        .collect();
+    let mut snapshots = vec![];
+    let mut events = vec![];
+
+    for mut snapshot in current {
+        let key = (snapshot.postcode.clone(), snapshot.action, snapshot.id);
+        match previous_by_key.remove(&key) {
+            None => events.push(snapshot.event(ListingEventKind::New, now, None)),
+            Some(before) => {
+                snapshot.first_seen = before.first_seen;
+                if before.withdrawn {
+                    events.push(snapshot.event(ListingEventKind::Relisted, now, None));
+                }
+                let newly_reduced = snapshot
+                    .reduced_date
+                    .filter(|&reduced_date| Some(reduced_date) != before.reduced_date);
+                if let Some(reduced_date) = newly_reduced {
+                    events.push(snapshot.event(
+                        ListingEventKind::Reduced,
+                        reduced_date,
+                        Some(before.price),
+                    ));
+                } else if snapshot.price != before.price {
+                    events.push(snapshot.event(
+                        ListingEventKind::PriceChange,
+                        now,
+                        Some(before.price),
+                    ));
+                }
+                if snapshot.status != before.status {
+                    let kind = match snapshot.status {
+                        ListingStatus::UnderOffer | ListingStatus::Reserved => {
+                            Some(ListingEventKind::UnderOffer)
+                        }
+                        ListingStatus::SoldStc => Some(ListingEventKind::SoldStc),
+                        ListingStatus::LetAgreed => Some(ListingEventKind::LetAgreed),
+                        ListingStatus::Available => None,
+                    };
+                    if let Some(kind) = kind {
+                        events.push(snapshot.event(kind, now, None));
+                    }
+                }
+            }
+        }
+        snapshots.push(snapshot);
+    }
+
+    for (_, mut before) in previous_by_key {
+        if !before.withdrawn && complete_searches.contains(&before.search_key()) {
+            before.withdrawn = true;
+            events.push(before.event(ListingEventKind::Withdrawn, now, None));
+        }
+        if !before.withdrawn || now - before.last_seen <= WITHDRAWN_RETENTION_MS {
+            snapshots.push(before);
+        }
+    }
+
+    events.sort_by_key(|event| (event.date, event.id));
+    (snapshots, events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, ListingEventKind, ListingSnapshot};
+    use crate::lib::property::property::ListingStatus;
+    use std::collections::HashSet;
+
+    fn snapshot(id: u32, price: u32, status: ListingStatus) -> ListingSnapshot {
+        ListingSnapshot {
+            id,
+            postcode: "N1 9AL".to_owned(),
+            coordinates: (-0.123795, 51.530312),
+            action: 1,
+            num_beds: 2,
+            price,
+            status,
+            reduced_date: None,
+            first_seen: 0,
+            last_seen: 0,
+            withdrawn: false,
+            borough: None,
+            ward: None,
+            lsoa: None,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let previous = vec![
+            snapshot(1, 500000, ListingStatus::Available),
+            snapshot(2, 600000, ListingStatus::Available),
+            snapshot(3, 700000, ListingStatus::Available),
+            ListingSnapshot {
+                withdrawn: true,
+                ..snapshot(4, 800000, ListingStatus::Available)
+            },
+            snapshot(5, 900000, ListingStatus::Available),
+        ];
+        let current = vec![
+            ListingSnapshot {
+                reduced_date: Some(5),
+                ..snapshot(1, 480000, ListingStatus::Available)
+            },
+            snapshot(2, 600000, ListingStatus::SoldStc),
+            snapshot(4, 800000, ListingStatus::Available),
+            snapshot(6, 400000, ListingStatus::Available),
+            snapshot(5, 950000, ListingStatus::Available),
+        ];
+        let complete_searches = HashSet::from([("N1 9AL".to_owned(), 1, 2)]);
+
+        let (snapshots, events) = diff(previous, current, &complete_searches, 10);
+        let kinds = events
+            .iter()
+            .map(|event| (event.id, event.kind))
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            kinds,
+            HashSet::from([
+                (1, ListingEventKind::Reduced),
+                (2, ListingEventKind::SoldStc),
+                (3, ListingEventKind::Withdrawn),
+                (4, ListingEventKind::Relisted),
+                (5, ListingEventKind::PriceChange),
+                (6, ListingEventKind::New),
+            ])
+        );
+        assert_eq!(snapshots.len(), 6);
+        assert!(snapshots.iter().any(|s| s.id == 3 && s.withdrawn));
+
+        // Without a complete search, a missing listing might just not have been retrieved.
+        let (_, events) = diff(snapshots, vec![], &HashSet::new(), 20);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_diff_first_run() {
+        let current = vec![
+            snapshot(1, 500000, ListingStatus::Available),
+            snapshot(2, 600000, ListingStatus::SoldStc),
+        ];
+        let complete_searches = HashSet::from([("N1 9AL".to_owned(), 1, 2)]);
+
+        let (snapshots, events) = diff(vec![], current.clone(), &complete_searches, 10);
+        assert_eq!(snapshots, current);
+        assert!(events.is_empty());
+    }
+}
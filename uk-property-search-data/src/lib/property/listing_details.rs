@@ -0,0 +1,98 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Fields only shown on a listing's detail page, cached by listing id.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListingDetails {
+    pub id: u32,
+    pub fetched: i64, // unix milliseconds
+    pub tenure: Option<Tenure>,
+    pub lease_years_remaining: Option<u32>,
+    pub annual_service_charge: Option<f64>,
+    pub annual_ground_rent: Option<f64>,
+    pub council_tax_band: Option<String>,
+    pub epc_band: Option<EpcBand>,
+    #[serde(default)]
+    pub price_history: Vec<PriceHistoryRecord>, // from PropertyLog, oldest first
+}
+
+/// An asking price a listing was advertised at, as recorded by PropertyLog.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceHistoryRecord {
+    pub date: i64, // unix milliseconds
+    pub price: u32,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Tenure {
+    Freehold,
+    Leasehold,
+    ShareOfFreehold,
+    Commonhold,
+}
+
+impl Tenure {
+    pub fn parse(tenure_type: &str) -> Option<Tenure> {
+        match tenure_type {
+            "FREEHOLD" => Some(Tenure::Freehold),
+            "LEASEHOLD" => Some(Tenure::Leasehold),
+            "SHARE_OF_FREEHOLD" => Some(Tenure::ShareOfFreehold),
+            "COMMONHOLD" => Some(Tenure::Commonhold),
+            _ => None,
+        }
+    }
+}
+
+/// Energy efficiency band from an Energy Performance Certificate.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EpcBand {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+}
+
+impl EpcBand {
+    pub fn parse(band: &str) -> Option<EpcBand> {
+        match band.trim().to_uppercase().as_str() {
+            "A" => Some(EpcBand::A),
+            "B" => Some(EpcBand::B),
+            "C" => Some(EpcBand::C),
+            "D" => Some(EpcBand::D),
+            "E" => Some(EpcBand::E),
+            "F" => Some(EpcBand::F),
+            "G" => Some(EpcBand::G),
+            _ => None,
+        }
+    }
+
+    /// Find the EPC rating mentioned in free text, e.g. "EPC Rating: C" => C.
+    pub fn find_in_text(text: &str) -> Option<EpcBand> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"(?i)\bEPC(?:\s+rating)?\s*(?:[:\-]|of|is)?\s*([A-G])\b").unwrap();
+        }
+        RE.captures(text)
+            .and_then(|caps| EpcBand::parse(caps.get(1).unwrap().as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EpcBand;
+
+    #[test]
+    fn test_find_epc_band_in_text() {
+        assert_eq!(EpcBand::find_in_text("EPC Rating: C"), Some(EpcBand::C));
+        assert_eq!(EpcBand::find_in_text("Garden, EPC - b"), Some(EpcBand::B));
+        assert_eq!(EpcBand::find_in_text("EPC rating of D"), Some(EpcBand::D));
+        assert_eq!(EpcBand::find_in_text("Epic views"), None);
+    }
+}
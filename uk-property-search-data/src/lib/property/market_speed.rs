@@ -0,0 +1,188 @@
+use super::{
+    lifecycle::{ListingEvent, ListingEventKind, ListingSnapshot, SearchKey},
+    property::ListingStatus,
+};
+use crate::lib::math::stats::Stats;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Measure over the last 12 weeks, or since tracking began if that's more recent.
+pub const MARKET_SPEED_WINDOW_WEEKS: i64 = 12;
+pub const WEEK_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+const WEEKS_PER_MONTH: f64 = 365.25 / 12.0 / 7.0;
+
+/// How quickly listings from a search are being agreed and replaced, from successive runs.
+/// Rates are None until there's at least a week of observations.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketSpeed {
+    pub days_to_agree: Stats, // from first seen to Sold STC / Let agreed
+    pub weekly_absorption_rate: Option<f64>, // listings agreed per week, over those available
+    pub months_of_inventory: Option<f64>, // to agree every available listing at the current pace
+    pub weekly_new_supply_rate: Option<f64>, // new or relisted listings per week, over those available
+    pub num_available: usize,
+    pub window_weeks: f64,
+}
+
+impl MarketSpeed {
+    fn new(events: &[&ListingEvent], num_available: usize, window_weeks: f64) -> MarketSpeed {
+        let agreed = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event.kind,
+                    ListingEventKind::SoldStc | ListingEventKind::LetAgreed
+                )
+            })
+            .collect_vec();
+        let days_to_agree = agreed
+            .iter()
+            .filter_map(|event| event.listed_days)
+            .collect_vec();
+        let num_new = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event.kind,
+                    ListingEventKind::New | ListingEventKind::Relisted
+                )
+            })
+            .count();
+
+        let has_window = window_weeks >= 1.0;
+        let agreed_per_week = agreed.len() as f64 / window_weeks;
+        let per_available = |count_per_week: f64| {
+            (has_window && num_available > 0).then_some(count_per_week / num_available as f64)
+        };
+        MarketSpeed {
+            days_to_agree: Stats::from_vec(&days_to_agree),
+            weekly_absorption_rate: per_available(agreed_per_week),
+            months_of_inventory: (has_window && agreed_per_week > 0.0)
+                .then_some(num_available as f64 / (agreed_per_week * WEEKS_PER_MONTH)),
+            weekly_new_supply_rate: per_available(num_new as f64 / window_weeks),
+            num_available,
+            window_weeks,
+        }
+    }
+
+    pub fn empty() -> MarketSpeed {
+        MarketSpeed::new(&[], 0, 0.0)
+    }
+
+    /// Market speed for each search, from the events since `window_start` and the listings
+    /// still available in this run. Listings new to a search on its first run were already on
+    /// the market, so they don't count towards new supply.
+    pub fn by_search(
+        events: &[ListingEvent],
+        snapshots: &[ListingSnapshot],
+        window_start: i64,
+        now: i64,
+    ) -> HashMap<SearchKey, MarketSpeed> {
+        let window_weeks = (now - window_start) as f64 / WEEK_MS as f64;
+        let first_run_by_search = snapshots
+            .iter()
+            .map(|s| ((s.postcode.clone(), s.action, s.num_beds), s.first_seen))
+            .into_grouping_map()
+            .min();
+        let mut events_by_search = events
+            .iter()
+            .filter(|event| event.date >= window_start)
+            .filter(|event| {
+                let key = (event.postcode.clone(), event.action, event.num_beds);
+                event.kind != ListingEventKind::New
+                    || first_run_by_search
+                        .get(&key)
+                        .is_none_or(|&first_run| event.date > first_run)
+            })
+            .into_group_map_by(|event| (event.postcode.clone(), event.action, event.num_beds));
+        let num_available_by_search = snapshots
+            .iter()
+            .filter(|s| !s.withdrawn && s.last_seen == now && s.status == ListingStatus::Available)
+            .counts_by(|s| (s.postcode.clone(), s.action, s.num_beds));
+        events_by_search
+            .keys()
+            .chain(num_available_by_search.keys())
+            .cloned()
+            .unique()
+            .collect_vec()
+            .into_iter()
+            .map(|key| {
+                let events = events_by_search.remove(&key).unwrap_or_default();
+                let num_available = num_available_by_search.get(&key).copied().unwrap_or(0);
+                let market_speed = MarketSpeed::new(&events, num_available, window_weeks);
+                (key, market_speed)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MarketSpeed, WEEK_MS};
+    use crate::lib::property::{
+        lifecycle::{ListingEvent, ListingEventKind, ListingSnapshot},
+        property::ListingStatus,
+    };
+    use statrs::assert_almost_eq;
+
+    #[test]
+    fn test_by_search() {
+        let now = 4 * WEEK_MS;
+        let event = |id: u32, kind: ListingEventKind, listed_days: Option<f64>| ListingEvent {
+            id,
+            postcode: "N1 9AL".to_owned(),
+            coordinates: (-0.123795, 51.530312),
+            action: 1,
+            num_beds: 2,
+            date: now - WEEK_MS,
+            kind,
+            price: 500000,
+            previous_price: None,
+            listed_days,
+        };
+        let events = vec![
+            event(1, ListingEventKind::SoldStc, Some(10.0)),
+            event(2, ListingEventKind::SoldStc, Some(30.0)),
+            event(3, ListingEventKind::New, None),
+            event(4, ListingEventKind::New, None),
+            event(5, ListingEventKind::Reduced, None),
+            // Listed when the search first ran, so not new supply.
+            ListingEvent {
+                date: 0,
+                ..event(6, ListingEventKind::New, None)
+            },
+        ];
+        let snapshots = (3..=6)
+            .map(|id| ListingSnapshot {
+                id,
+                postcode: "N1 9AL".to_owned(),
+                coordinates: (-0.123795, 51.530312),
+                action: 1,
+                num_beds: 2,
+                price: 500000,
+                status: ListingStatus::Available,
+                reduced_date: None,
+                first_seen: 0,
+                last_seen: now,
+                withdrawn: false,
+                borough: None,
+                ward: None,
+                lsoa: None,
+            })
+            .collect::<Vec<_>>();
+
+        let market_speeds = MarketSpeed::by_search(&events, &snapshots, 0, now);
+        let market_speed = &market_speeds[&("N1 9AL".to_owned(), 1, 2)];
+        assert_eq!(market_speed.days_to_agree.median, 20.0);
+        assert_eq!(market_speed.num_available, 4);
+        // 2 agreed over 4 weeks, with 4 listings available.
+        assert_almost_eq!(market_speed.weekly_absorption_rate.unwrap(), 0.125, 1e-9);
+        assert_almost_eq!(
+            market_speed.months_of_inventory.unwrap(),
+            8.0 / (365.25 / 12.0 / 7.0),
+            1e-9
+        );
+        assert_almost_eq!(market_speed.weekly_new_supply_rate.unwrap(), 0.125, 1e-9);
+    }
+}
@@ -1,3 +1,18 @@
 pub mod aggregator;
+pub mod area;
+pub mod assignment;
+pub mod catchment;
+pub mod discount;
+pub mod distance;
+pub mod enrichment;
+pub mod epc;
 pub mod estate_agents;
+pub mod heat;
+pub mod imputation;
+pub mod lifecycle;
+pub mod listing_details;
+pub mod market_speed;
+pub mod price;
 pub mod property;
+pub mod rollup;
+pub mod sold_price;
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RentFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+    NotSpecified, // buy prices, or rents advertised without a period (treated as monthly)
+}
+
+impl RentFrequency {
+    pub fn parse(frequency: &str) -> Option<RentFrequency> {
+        match frequency {
+            "daily" => Some(RentFrequency::Daily),
+            "weekly" => Some(RentFrequency::Weekly),
+            "monthly" => Some(RentFrequency::Monthly),
+            "quarterly" => Some(RentFrequency::Quarterly),
+            "yearly" => Some(RentFrequency::Yearly),
+            "not specified" => Some(RentFrequency::NotSpecified),
+            _ => None,
+        }
+    }
+
+    /// Convert an amount charged at this frequency into a monthly amount, rounded to the nearest pound.
+    pub fn to_monthly(self, amount: u32) -> u32 {
+        let periods_per_year = match self {
+            RentFrequency::Daily => 365.0,
+            RentFrequency::Weekly => 52.0,
+            RentFrequency::Monthly | RentFrequency::NotSpecified => 12.0,
+            RentFrequency::Quarterly => 4.0,
+            RentFrequency::Yearly => 1.0,
+        };
+        (amount as f64 * periods_per_year / 12.0).round() as u32
+    }
+}
+
+/// How the advertised price should be read, as shown next to it on the listing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PriceQualifier {
+    Standard,
+    GuidePrice,
+    OffersOver,
+    OffersInExcessOf,
+    OffersInRegionOf,
+    FixedPrice,
+    From,
+    Poa,             // price on application, amount is meaningless
+    Auction,         // amount is the auction guide
+    SharedOwnership, // amount is scaled up to the full market value
+    PerPerson,       // amount is scaled up to the whole property by number of bedrooms
+    Other,
+}
+
+impl PriceQualifier {
+    pub fn parse(display_price_qualifier: &str, display_price: &str) -> PriceQualifier {
+        let display_price = display_price.to_lowercase();
+        if display_price.contains("poa") || display_price.contains("price on application") {
+            return PriceQualifier::Poa;
+        }
+        if display_price.contains("pppw")
+            || display_price.contains("pppm")
+            || display_price.contains("per person")
+        {
+            return PriceQualifier::PerPerson;
+        }
+        match display_price_qualifier.trim().to_lowercase().as_str() {
+            "" => PriceQualifier::Standard,
+            "guide price" => PriceQualifier::GuidePrice,
+            "offers over" => PriceQualifier::OffersOver,
+            "offers in excess of" => PriceQualifier::OffersInExcessOf,
+            "offers in region of" | "offers in the region of" => PriceQualifier::OffersInRegionOf,
+            "fixed price" => PriceQualifier::FixedPrice,
+            "from" | "starting from" => PriceQualifier::From,
+            "poa" | "price on application" => PriceQualifier::Poa,
+            "auction guide price" | "auction" => PriceQualifier::Auction,
+            "shared ownership" => PriceQualifier::SharedOwnership,
+            _ => PriceQualifier::Other,
+        }
+    }
+
+    /// Parse a comma-separated list of qualifiers, as written in properties.toml.
+    pub fn parse_list(s: &str) -> Result<Vec<PriceQualifier>> {
+        s.split(',')
+            .map(|q| q.trim())
+            .filter(|q| !q.is_empty())
+            .map(|q| {
+                serde_json::from_value(serde_json::Value::String(q.to_owned()))
+                    .map_err(|_| anyhow!("[{}] is not a valid price qualifier!", q))
+            })
+            .collect()
+    }
+}
+
+/// Find the share being sold in shared ownership adverts, e.g. "25% share" => 0.25.
+pub fn parse_shared_ownership_share(text: &str) -> Option<f64> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"(?i)(\d+(?:\.\d+)?)\s*%\s*share").unwrap();
+    }
+    RE.captures(text)
+        .and_then(|caps| caps.get(1).unwrap().as_str().parse::<f64>().ok())
+        .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+        .map(|percent| percent / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_shared_ownership_share, PriceQualifier, RentFrequency};
+
+    #[test]
+    fn test_to_monthly() {
+        assert_eq!(RentFrequency::Weekly.to_monthly(400), 1733);
+        assert_eq!(RentFrequency::Daily.to_monthly(50), 1521);
+        assert_eq!(RentFrequency::Quarterly.to_monthly(6000), 2000);
+        assert_eq!(RentFrequency::Yearly.to_monthly(24000), 2000);
+        assert_eq!(RentFrequency::NotSpecified.to_monthly(2000), 2000);
+    }
+
+    #[test]
+    fn test_parse_qualifier() {
+        assert_eq!(
+            PriceQualifier::parse("", "£500,000"),
+            PriceQualifier::Standard
+        );
+        assert_eq!(
+            PriceQualifier::parse("Offers in Excess of", "£500,000"),
+            PriceQualifier::OffersInExcessOf
+        );
+        assert_eq!(PriceQualifier::parse("", "POA"), PriceQualifier::Poa);
+        assert_eq!(
+            PriceQualifier::parse("", "£200 pppw"),
+            PriceQualifier::PerPerson
+        );
+        assert_eq!(
+            PriceQualifier::parse_list("poa, sharedOwnership").unwrap(),
+            vec![PriceQualifier::Poa, PriceQualifier::SharedOwnership]
+        );
+        assert!(PriceQualifier::parse_list("poa, swap").is_err());
+    }
+
+    #[test]
+    fn test_parse_shared_ownership_share() {
+        assert_eq!(
+            parse_shared_ownership_share("Shared ownership - 25% share"),
+            Some(0.25)
+        );
+        assert_eq!(parse_shared_ownership_share("Two bedroom flat"), None);
+    }
+}
@@ -1,20 +1,166 @@
+use super::{
+    distance::DistanceRingStats, listing_details::EpcBand, market_speed::MarketSpeed,
+    price::PriceQualifier,
+};
 use crate::lib::math::stats::Stats;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PropertyAction {
     Buy = 1,
     Rent = 2,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum PropertyType {
+    Flat,
+    Maisonette,
+    Terraced,
+    SemiDetached,
+    Detached,
+    Bungalow,
+    Other,
+}
+
+impl PropertyType {
+    /// Normalise an estate agent's property sub type, e.g. "End of Terrace" => Terraced.
+    pub fn from_sub_type(sub_type: &str) -> PropertyType {
+        let sub_type = sub_type.to_lowercase();
+        if sub_type.contains("bungalow") {
+            PropertyType::Bungalow
+        } else if sub_type.contains("maisonette") {
+            PropertyType::Maisonette
+        } else if sub_type.contains("semi-detached") || sub_type.contains("semi detached") {
+            PropertyType::SemiDetached
+        } else if sub_type.contains("detached") {
+            PropertyType::Detached
+        } else if sub_type.contains("terrace") || sub_type == "town house" || sub_type == "mews" {
+            PropertyType::Terraced
+        } else if [
+            "flat",
+            "apartment",
+            "studio",
+            "penthouse",
+            "duplex",
+            "triplex",
+        ]
+        .iter()
+        .any(|t| sub_type.contains(t))
+        {
+            PropertyType::Flat
+        } else {
+            PropertyType::Other
+        }
+    }
+}
+
+/// Where a listing is in the sale or letting process, as displayed by the estate agent.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ListingStatus {
+    Available,
+    UnderOffer,
+    Reserved,
+    SoldStc,
+    LetAgreed,
+}
+
+impl ListingStatus {
+    pub fn parse(display_status: &str) -> ListingStatus {
+        match display_status {
+            "Under offer" => ListingStatus::UnderOffer,
+            "Reserved" => ListingStatus::Reserved,
+            "Sold STC" => ListingStatus::SoldStc,
+            "Let agreed" => ListingStatus::LetAgreed,
+            _ => ListingStatus::Available,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PropertyStats {
     pub price: Stats,
-    pub listed_days: Stats, // how long the advert has been on the market
-    pub percent_transacted: Stats, // percentage of properties "Let Agreed" / "Sold STC" / "Under offer"
-    pub square_feet: Stats,
+    pub listed_days: Stats,      // how long the advert has been on the market
+    pub percent_transacted: f64, // share of listings "Let Agreed" / "Sold STC" / "Under offer"
+    pub percent_reduced: f64,    // share of listings with a price reduction
+    pub square_feet: Stats,      // where advertised or on the EPC register
+    pub square_feet_with_imputed: Stats, // where known or estimated
+    pub price_per_square_foot: Stats, // where square feet is known
+    pub price_per_square_foot_with_imputed: Stats, // where square feet is known or estimated
+    pub percent_square_feet_imputed: f64, // share of square feet with imputed that were estimated
     pub rental_yield: Stats,
+    pub tenure: TenureSplit,    // among listings with fetched details
+    pub service_charge: Stats,  // annual, where advertised
+    pub ground_rent: Stats,     // annual, where advertised
+    pub epc_band: EpcBandSplit, // current rating, from the EPC register or else the listing
+    #[serde(default)]
+    pub price_by_qualifier: BTreeMap<PriceQualifier, Stats>, // including excluded qualifiers
+}
+
+/// Number of listings of each tenure.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TenureSplit {
+    pub freehold: usize,
+    pub leasehold: usize,
+    pub share_of_freehold: usize,
+    pub commonhold: usize,
+    pub unknown: usize, // details fetched but tenure not stated
+}
+
+/// Number of listings with each current EPC rating.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EpcBandSplit {
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    pub d: usize,
+    pub e: usize,
+    pub f: usize,
+    pub g: usize,
+    pub unknown: usize,
+}
+
+impl TenureSplit {
+    #[allow(dead_code)] // only used by the server
+    pub fn add_all(&mut self, other: &TenureSplit) {
+        self.freehold += other.freehold;
+        self.leasehold += other.leasehold;
+        self.share_of_freehold += other.share_of_freehold;
+        self.commonhold += other.commonhold;
+        self.unknown += other.unknown;
+    }
+}
+
+impl EpcBandSplit {
+    pub fn add(&mut self, band: Option<EpcBand>) {
+        match band {
+            Some(EpcBand::A) => self.a += 1,
+            Some(EpcBand::B) => self.b += 1,
+            Some(EpcBand::C) => self.c += 1,
+            Some(EpcBand::D) => self.d += 1,
+            Some(EpcBand::E) => self.e += 1,
+            Some(EpcBand::F) => self.f += 1,
+            Some(EpcBand::G) => self.g += 1,
+            None => self.unknown += 1,
+        }
+    }
+
+    #[allow(dead_code)] // only used by the server
+    pub fn add_all(&mut self, other: &EpcBandSplit) {
+        self.a += other.a;
+        self.b += other.b;
+        self.c += other.c;
+        self.d += other.d;
+        self.e += other.e;
+        self.f += other.f;
+        self.g += other.g;
+        self.unknown += other.unknown;
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,4 +171,89 @@ pub struct PropertySummary {
     pub action: u8,
     pub num_beds: u32,
     pub stats: PropertyStats,
+    pub stats_by_type: BTreeMap<PropertyType, PropertyStats>,
+    pub coverage: SearchCoverage,
+    pub market_speed: MarketSpeed,
+    #[serde(default)]
+    pub stats_by_distance: Vec<DistanceRingStats>, // innermost ring first
+}
+
+impl PropertySummary {
+    /// Pair up buy and rent summaries for the same station and number of beds, as (buy, rent).
+    pub fn pair_buy_and_rent(
+        summaries: Vec<PropertySummary>,
+    ) -> Vec<(PropertySummary, PropertySummary)> {
+        let mut rent_summaries: HashMap<(String, u32), PropertySummary> = HashMap::new();
+        let mut buy_summaries = vec![];
+        for summary in summaries {
+            if summary.action == PropertyAction::Rent as u8 {
+                rent_summaries.insert((summary.postcode.clone(), summary.num_beds), summary);
+            } else {
+                buy_summaries.push(summary);
+            }
+        }
+        let mut pairs = buy_summaries
+            .into_iter()
+            .filter_map(|buy_summary| {
+                rent_summaries
+                    .remove(&(buy_summary.postcode.clone(), buy_summary.num_beds))
+                    .map(|rent_summary| (buy_summary, rent_summary))
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_by(|(a, _), (b, _)| (&a.postcode, a.num_beds).cmp(&(&b.postcode, b.num_beds)));
+        pairs
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCoverage {
+    pub result_count: u32, // number of results the estate agent reports for the search
+    pub retrieved: u32,    // number of unique listings actually retrieved
+    pub num_queries: u32,  // number of (sub-)queries issued to work around the result cap
+    pub num_truncated: u32, // number of sub-queries still truncated after splitting
+}
+
+impl SearchCoverage {
+    pub fn ratio(&self) -> f64 {
+        if self.result_count == 0 {
+            1.0
+        } else {
+            (self.retrieved as f64) / (self.result_count as f64)
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.retrieved >= self.result_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PropertyType;
+
+    #[test]
+    fn test_from_sub_type() {
+        assert_eq!(PropertyType::from_sub_type("Apartment"), PropertyType::Flat);
+        assert_eq!(
+            PropertyType::from_sub_type("Ground Maisonette"),
+            PropertyType::Maisonette
+        );
+        assert_eq!(
+            PropertyType::from_sub_type("End of Terrace"),
+            PropertyType::Terraced
+        );
+        assert_eq!(
+            PropertyType::from_sub_type("Semi-Detached"),
+            PropertyType::SemiDetached
+        );
+        assert_eq!(
+            PropertyType::from_sub_type("Detached Bungalow"),
+            PropertyType::Bungalow
+        );
+        assert_eq!(
+            PropertyType::from_sub_type("Houseboat"),
+            PropertyType::Other
+        );
+    }
 }
@@ -0,0 +1,253 @@
+use super::{
+    aggregator::PropertyAggregator,
+    price::PriceQualifier,
+    property::{EpcBandSplit, PropertyAction, PropertyStats, TenureSplit},
+};
+use crate::lib::{math::tdigest::TDigest, tube::TubeStation};
+use itertools::Itertools;
+use rocket::FromFormField;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Mergeable summary of the listings from one search, from which `PropertyStats` can be
+/// estimated for any combination of searches.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertySketch {
+    pub num_listings: usize, // after excluded price qualifiers
+    pub num_transacted: usize,
+    pub num_reduced: usize,
+    pub price: TDigest,
+    pub listed_days: TDigest,
+    pub square_feet: TDigest,
+    pub square_feet_with_imputed: TDigest,
+    pub price_per_square_foot: TDigest,
+    pub price_per_square_foot_with_imputed: TDigest,
+    pub service_charge: TDigest,
+    pub ground_rent: TDigest,
+    pub tenure: TenureSplit,
+    pub epc_band: EpcBandSplit,
+    #[serde(default)]
+    pub price_by_qualifier: BTreeMap<PriceQualifier, TDigest>, // before exclusion
+}
+
+/// Sketch of the listings near a station, kept alongside its `PropertySummary`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StationSketch {
+    pub postcode: String,
+    pub action: u8,
+    pub num_beds: u32,
+    pub sketch: PropertySketch,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, FromFormField)]
+#[serde(rename_all = "camelCase")]
+pub enum RollupLevel {
+    #[field(value = "zone")]
+    Zone,
+    #[field(value = "line")]
+    Line,
+    #[field(value = "borough")]
+    Borough,
+}
+
+/// Stats for every station in an area, e.g. zone 2 or the Victoria line. Station searches
+/// overlap, so listings near more than one station in the area are counted more than once.
+/// Stats are estimated from the stations' merged sketches, so quantiles are approximate, but go
+/// through the same trimming, median bootstrap and minimum count as a station's.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyRollup {
+    pub level: RollupLevel,
+    pub area: String, // zone, line, or borough GSS code
+    pub action: u8,
+    pub num_beds: u32,
+    pub stations: Vec<String>, // postcodes
+    pub stats: PropertyStats,
+}
+
+#[allow(dead_code)] // only used by the server
+impl PropertySketch {
+    pub fn merge(sketches: &[&PropertySketch], compression: f64) -> PropertySketch {
+        let digests = |f: fn(&PropertySketch) -> &TDigest| {
+            TDigest::merge(sketches.iter().map(|sketch| f(sketch)), compression)
+        };
+        let mut tenure = TenureSplit::default();
+        let mut epc_band = EpcBandSplit::default();
+        for sketch in sketches {
+            tenure.add_all(&sketch.tenure);
+            epc_band.add_all(&sketch.epc_band);
+        }
+        PropertySketch {
+            num_listings: sketches.iter().map(|sketch| sketch.num_listings).sum(),
+            num_transacted: sketches.iter().map(|sketch| sketch.num_transacted).sum(),
+            num_reduced: sketches.iter().map(|sketch| sketch.num_reduced).sum(),
+            price: digests(|sketch| &sketch.price),
+            listed_days: digests(|sketch| &sketch.listed_days),
+            square_feet: digests(|sketch| &sketch.square_feet),
+            square_feet_with_imputed: digests(|sketch| &sketch.square_feet_with_imputed),
+            price_per_square_foot: digests(|sketch| &sketch.price_per_square_foot),
+            price_per_square_foot_with_imputed: digests(|sketch| {
+                &sketch.price_per_square_foot_with_imputed
+            }),
+            service_charge: digests(|sketch| &sketch.service_charge),
+            ground_rent: digests(|sketch| &sketch.ground_rent),
+            tenure,
+            epc_band,
+            price_by_qualifier: sketches
+                .iter()
+                .flat_map(|sketch| sketch.price_by_qualifier.iter())
+                .into_group_map_by(|(&qualifier, _)| qualifier)
+                .into_iter()
+                .map(|(qualifier, digests)| {
+                    let digests = digests.into_iter().map(|(_, digest)| digest);
+                    (qualifier, TDigest::merge(digests, compression))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[allow(dead_code)] // only used by the server
+impl RollupLevel {
+    fn areas(&self, station: &TubeStation) -> Vec<String> {
+        match self {
+            RollupLevel::Zone => station.zone.iter().map(|zone| zone.to_string()).collect(),
+            RollupLevel::Line => station.lines.iter().cloned().collect(),
+            RollupLevel::Borough => station.borough.iter().cloned().collect(),
+        }
+    }
+}
+
+#[allow(dead_code)] // only used by the server
+impl PropertyRollup {
+    /// Roll station sketches up to areas at `level`. Stations in more than one area, e.g. on
+    /// a zone boundary, count towards each of them.
+    pub fn from_sketches(
+        aggregator: &PropertyAggregator,
+        level: RollupLevel,
+        stations: &[TubeStation],
+        sketches: &[StationSketch],
+    ) -> Vec<PropertyRollup> {
+        let areas_by_postcode: HashMap<&str, Vec<String>> = stations
+            .iter()
+            .map(|station| (station.postcode.as_str(), level.areas(station)))
+            .collect();
+        let mut sketches_by_area: BTreeMap<(String, u32), Vec<&StationSketch>> = BTreeMap::new();
+        for sketch in sketches {
+            for area in areas_by_postcode
+                .get(sketch.postcode.as_str())
+                .into_iter()
+                .flatten()
+            {
+                sketches_by_area
+                    .entry((area.clone(), sketch.num_beds))
+                    .or_default()
+                    .push(sketch);
+            }
+        }
+
+        sketches_by_area
+            .into_iter()
+            .flat_map(|((area, num_beds), sketches)| {
+                let merge = |action: PropertyAction| {
+                    aggregator.merge_sketches(
+                        &sketches
+                            .iter()
+                            .filter(|sketch| sketch.action == action as u8)
+                            .map(|sketch| &sketch.sketch)
+                            .collect_vec(),
+                    )
+                };
+                let (buy_stats, rent_stats) = aggregator.calculate_buy_and_rent_rollup_stats(
+                    &merge(PropertyAction::Buy),
+                    &merge(PropertyAction::Rent),
+                );
+                let stations = sketches
+                    .iter()
+                    .map(|sketch| sketch.postcode.clone())
+                    .unique()
+                    .sorted()
+                    .collect_vec();
+                [
+                    (PropertyAction::Buy, buy_stats),
+                    (PropertyAction::Rent, rent_stats),
+                ]
+                .into_iter()
+                .map(move |(action, stats)| PropertyRollup {
+                    level,
+                    area: area.clone(),
+                    action: action as u8,
+                    num_beds,
+                    stations: stations.clone(),
+                    stats,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PropertyRollup, RollupLevel, StationSketch};
+    use crate::lib::{
+        math::stats::StatsOptions,
+        property::{
+            aggregator::PropertyAggregator, estate_agents::rightmove::RightmoveProperty,
+            property::PropertyAction,
+        },
+        tube::TubeStation,
+    };
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_from_sketches() {
+        let aggregator = PropertyAggregator::with_options(HashSet::new(), StatsOptions::default());
+        let station = |postcode: &str, zone: Vec<u8>| TubeStation {
+            name: postcode.to_owned(),
+            zone,
+            postcode: postcode.to_owned(),
+            coordinates: (-0.123795, 51.530312),
+            lines: HashSet::new(),
+            borough: None,
+            ward: None,
+            lsoa: None,
+        };
+        let sketch = |postcode: &str, action: PropertyAction, prices: &[u32]| StationSketch {
+            postcode: postcode.to_owned(),
+            action: action as u8,
+            num_beds: 1,
+            sketch: aggregator.calculate_sketch(
+                prices
+                    .iter()
+                    .enumerate()
+                    .map(|(id, &price)| {
+                        RightmoveProperty::test_listing(id as u32, (-0.123795, 51.530312), price)
+                    })
+                    .collect(),
+            ),
+        };
+        let stations = vec![station("N1 9AL", vec![1]), station("E1 6AN", vec![1, 2])];
+        let sketches = vec![
+            sketch("N1 9AL", PropertyAction::Buy, &[300000, 400000]),
+            sketch("E1 6AN", PropertyAction::Buy, &[500000, 600000]),
+            sketch("N1 9AL", PropertyAction::Rent, &[2000]),
+        ];
+
+        let rollups =
+            PropertyRollup::from_sketches(&aggregator, RollupLevel::Zone, &stations, &sketches);
+        let buy = |area: &str| {
+            rollups
+                .iter()
+                .find(|r| r.area == area && r.action == PropertyAction::Buy as u8)
+                .unwrap()
+        };
+        assert_eq!(rollups.len(), 4);
+        assert_eq!(buy("1").stations, vec!["E1 6AN", "N1 9AL"]);
+        assert_eq!(buy("1").stats.price.count, 4);
+        assert_eq!(buy("1").stats.price.median, 450000.0);
+        assert_eq!(buy("2").stats.price.median, 550000.0);
+        assert_eq!(buy("1").stats.rental_yield.median, 2000.0 * 12.0 / 450000.0);
+    }
+}
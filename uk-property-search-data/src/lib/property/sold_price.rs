@@ -0,0 +1,177 @@
+use super::property::PropertyType;
+use crate::lib::math::{geo::haversine_miles, stats::Stats};
+use chrono::{Months, NaiveDate};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// Roughly 700m by 1.1km in London, so a station's radius only spans a few cells.
+const GRID_CELL_DEGREES: f64 = 0.01;
+const MILES_PER_DEGREE_LATITUDE: f64 = 69.0;
+
+/// A completed sale from the HM Land Registry Price Paid data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sale {
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub price: u32,
+    pub date: NaiveDate,
+    pub property_type: PropertyType,
+}
+
+impl Sale {
+    /// Map a Price Paid property type code. Flats include maisonettes, and "O" covers everything
+    /// else, e.g. garages or commercial units sold as part of a residential transaction.
+    pub fn parse_property_type(code: &str) -> PropertyType {
+        match code {
+            "D" => PropertyType::Detached,
+            "S" => PropertyType::SemiDetached,
+            "T" => PropertyType::Terraced,
+            "F" => PropertyType::Flat,
+            _ => PropertyType::Other,
+        }
+    }
+
+    /// The Price Paid property type that a listing's property type is sold as, or none if sales
+    /// of it can't be told apart, e.g. bungalows are recorded by their build form.
+    pub fn comparable_property_type(property_type: PropertyType) -> Option<PropertyType> {
+        match property_type {
+            PropertyType::Flat | PropertyType::Maisonette => Some(PropertyType::Flat),
+            PropertyType::Terraced | PropertyType::SemiDetached | PropertyType::Detached => {
+                Some(property_type)
+            }
+            PropertyType::Bungalow | PropertyType::Other => None,
+        }
+    }
+}
+
+/// Sales gridded by location, so that only those near a station need measuring.
+pub struct SaleIndex {
+    sales: Vec<Sale>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl SaleIndex {
+    pub fn new(sales: Vec<Sale>) -> SaleIndex {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, sale) in sales.iter().enumerate() {
+            cells.entry(cell(sale.coordinates)).or_default().push(i);
+        }
+        SaleIndex { sales, cells }
+    }
+
+    pub fn num_sales(&self) -> usize {
+        self.sales.len()
+    }
+
+    /// Date of the latest sale, if there are any.
+    pub fn latest_date(&self) -> Option<NaiveDate> {
+        self.sales.iter().map(|sale| sale.date).max()
+    }
+
+    /// Sales within `radius` miles of the point.
+    pub fn near(&self, point: (f64, f64), radius: f64) -> impl Iterator<Item = &Sale> {
+        let latitude_delta = radius / MILES_PER_DEGREE_LATITUDE;
+        let longitude_delta = latitude_delta / point.1.to_radians().cos();
+        let (west, south) = cell((point.0 - longitude_delta, point.1 - latitude_delta));
+        let (east, north) = cell((point.0 + longitude_delta, point.1 + latitude_delta));
+        (west..=east)
+            .flat_map(move |x| (south..=north).map(move |y| (x, y)))
+            .filter_map(|c| self.cells.get(&c))
+            .flatten()
+            .map(|&i| &self.sales[i])
+            .filter(move |sale| haversine_miles(point, sale.coordinates) <= radius)
+    }
+}
+
+fn cell((longitude, latitude): (f64, f64)) -> (i64, i64) {
+    (
+        (longitude / GRID_CELL_DEGREES).floor() as i64,
+        (latitude / GRID_CELL_DEGREES).floor() as i64,
+    )
+}
+
+/// Sold prices near a station over the months leading up to the latest sale in the data.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SoldPriceSummary {
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub months: u32,
+    pub period_end: i64, // unix milliseconds, date of the latest sale in the data
+    pub stats: Stats,
+    pub stats_by_type: BTreeMap<PropertyType, Stats>,
+}
+
+impl SoldPriceSummary {
+    pub fn from_sales(
+        postcode: &str,
+        coordinates: (f64, f64),
+        radius: f64,
+        months: u32,
+        period_end: NaiveDate,
+        sales: &SaleIndex,
+    ) -> SoldPriceSummary {
+        let period_start = period_end.checked_sub_months(Months::new(months)).unwrap();
+        let nearby_sales = sales
+            .near(coordinates, radius)
+            .filter(|sale| sale.date > period_start && sale.date <= period_end)
+            .collect_vec();
+        let stats_by_type = nearby_sales
+            .iter()
+            .map(|sale| (sale.property_type, sale.price))
+            .into_group_map()
+            .into_iter()
+            .map(|(property_type, prices)| (property_type, Stats::from_vec(&prices)))
+            .collect();
+        SoldPriceSummary {
+            postcode: postcode.to_owned(),
+            coordinates,
+            months,
+            period_end: period_end.and_hms_opt(0, 0, 0).unwrap().timestamp_millis(),
+            stats: Stats::from_vec(&nearby_sales.iter().map(|sale| sale.price).collect_vec()),
+            stats_by_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sale, SaleIndex, SoldPriceSummary};
+    use crate::lib::property::property::PropertyType;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_from_sales() {
+        let kings_cross = (-0.123795, 51.530312);
+        let sale = |price: u32, date: (i32, u32, u32), coordinates: (f64, f64)| Sale {
+            coordinates,
+            price,
+            date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+            property_type: PropertyType::Flat,
+        };
+        let sales = SaleIndex::new(vec![
+            sale(500000, (2024, 6, 1), kings_cross),
+            sale(700000, (2024, 1, 1), kings_cross),
+            sale(900000, (2023, 1, 1), kings_cross), // older than 12 months
+            sale(300000, (2024, 6, 1), (-0.141899, 51.515224)), // over a mile away
+            sale(400000, (2024, 6, 1), (-0.1245, 51.5285)), // 0.13 miles away, in the next cell
+        ]);
+        let period_end = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+
+        let summary =
+            SoldPriceSummary::from_sales("N1 9AL", kings_cross, 0.25, 12, period_end, &sales);
+        assert_eq!(summary.stats.count, 3);
+        assert_eq!(summary.stats.median, 500000.0);
+        assert_eq!(summary.stats_by_type[&PropertyType::Flat].count, 3);
+
+        let summary =
+            SoldPriceSummary::from_sales("N1 9AL", kings_cross, 0.25, 24, period_end, &sales);
+        assert_eq!(summary.stats.count, 4);
+
+        assert_eq!(
+            Sale::comparable_property_type(PropertyType::Maisonette),
+            Some(PropertyType::Flat)
+        );
+        assert_eq!(Sale::comparable_property_type(PropertyType::Bungalow), None);
+    }
+}
@@ -0,0 +1,233 @@
+use super::pbf::{self, OsmWay};
+use crate::lib::math::geo::haversine_miles;
+use anyhow::Result;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    path::Path,
+};
+
+// Roughly 150m by 220m in London.
+const GRID_CELL_DEGREES: f64 = 0.002;
+// Points further than this many cells from any node aren't on the network.
+const MAX_SNAP_CELLS: i64 = 3;
+
+// Highways pedestrians can't use, or shouldn't be routed along.
+const UNWALKABLE_HIGHWAYS: [&str; 8] = [
+    "motorway",
+    "motorway_link",
+    "trunk",
+    "trunk_link",
+    "construction",
+    "proposed",
+    "raceway",
+    "bus_guideway",
+];
+
+/// Street network for walking, from an OpenStreetMap extract. Edges are weighted by their length
+/// in miles, and walked both ways regardless of one-way restrictions for traffic.
+pub struct PedestrianGraph {
+    coordinates: Vec<(f64, f64)>, // (longitude, latitude) of each node
+    edges: Vec<Vec<(usize, f64)>>,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl PedestrianGraph {
+    /// Load from an .osm.pbf file. The file is read twice, so that only the coordinates of
+    /// nodes on walkable ways are kept.
+    pub fn load(path: &Path) -> Result<PedestrianGraph> {
+        let ways = pbf::read_ways(path, is_walkable)?;
+        let node_ids = ways
+            .iter()
+            .flat_map(|way| way.node_ids.iter().copied())
+            .collect::<HashSet<_>>();
+        let nodes = pbf::read_nodes(path, &node_ids)?;
+        Ok(PedestrianGraph::new(&ways, &nodes))
+    }
+
+    pub fn new(ways: &[OsmWay], nodes: &HashMap<i64, (f64, f64)>) -> PedestrianGraph {
+        let mut graph = PedestrianGraph {
+            coordinates: vec![],
+            edges: vec![],
+            cells: HashMap::new(),
+        };
+        let mut indices: HashMap<i64, usize> = HashMap::new();
+        for way in ways {
+            // Extracts clipped to an area can reference nodes outside it.
+            let way_nodes = way
+                .node_ids
+                .iter()
+                .filter_map(|id| nodes.get(id).map(|&coordinates| (*id, coordinates)))
+                .map(|(id, coordinates)| {
+                    *indices
+                        .entry(id)
+                        .or_insert_with(|| graph.add_node(coordinates))
+                })
+                .collect::<Vec<_>>();
+            for pair in way_nodes.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                let miles = haversine_miles(graph.coordinates[from], graph.coordinates[to]);
+                graph.edges[from].push((to, miles));
+                graph.edges[to].push((from, miles));
+            }
+        }
+        graph
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.coordinates.len()
+    }
+
+    pub fn coordinates(&self, node: usize) -> (f64, f64) {
+        self.coordinates[node]
+    }
+
+    /// Nearest node to the point, and how far away it is in miles.
+    pub fn nearest(&self, point: (f64, f64)) -> Option<(usize, f64)> {
+        let (x, y) = cell(point);
+        // The first ring of cells with any nodes in it is near enough.
+        (1..=MAX_SNAP_CELLS).find_map(|radius| {
+            (x - radius..=x + radius)
+                .flat_map(|cx| (y - radius..=y + radius).map(move |cy| (cx, cy)))
+                .filter_map(|c| self.cells.get(&c))
+                .flatten()
+                .map(|&node| (node, haversine_miles(point, self.coordinates[node])))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        })
+    }
+
+    /// Miles walked from the point to every node within `max_miles` of it, including getting
+    /// onto the network at the nearest node.
+    pub fn distances_from(&self, point: (f64, f64), max_miles: f64) -> HashMap<usize, f64> {
+        let mut distances = HashMap::new();
+        let (start, snap_miles) = match self.nearest(point) {
+            Some(nearest) => nearest,
+            None => return distances,
+        };
+        let mut queue = BinaryHeap::from([Visit {
+            miles: snap_miles,
+            node: start,
+        }]);
+        while let Some(Visit { miles, node }) = queue.pop() {
+            if miles > max_miles || distances.contains_key(&node) {
+                continue;
+            }
+            distances.insert(node, miles);
+            for &(next, edge_miles) in &self.edges[node] {
+                if !distances.contains_key(&next) {
+                    queue.push(Visit {
+                        miles: miles + edge_miles,
+                        node: next,
+                    });
+                }
+            }
+        }
+        distances
+    }
+
+    /// Miles walked to the point, given the distances to nodes from `distances_from`, or None if
+    /// it couldn't be reached.
+    pub fn distance_to(&self, distances: &HashMap<usize, f64>, point: (f64, f64)) -> Option<f64> {
+        let (node, snap_miles) = self.nearest(point)?;
+        distances.get(&node).map(|miles| miles + snap_miles)
+    }
+
+    fn add_node(&mut self, coordinates: (f64, f64)) -> usize {
+        let node = self.coordinates.len();
+        self.coordinates.push(coordinates);
+        self.edges.push(vec![]);
+        self.cells.entry(cell(coordinates)).or_default().push(node);
+        node
+    }
+}
+
+pub fn is_walkable(tags: &HashMap<&str, &str>) -> bool {
+    let highway = match tags.get("highway") {
+        Some(highway) => highway,
+        None => return false,
+    };
+    !UNWALKABLE_HIGHWAYS.contains(highway)
+        && !matches!(tags.get("foot"), Some(&"no") | Some(&"private"))
+        && !matches!(tags.get("access"), Some(&"no") | Some(&"private"))
+        && tags.get("area") != Some(&"yes")
+}
+
+fn cell((longitude, latitude): (f64, f64)) -> (i64, i64) {
+    (
+        (longitude / GRID_CELL_DEGREES).floor() as i64,
+        (latitude / GRID_CELL_DEGREES).floor() as i64,
+    )
+}
+
+// Min-heap entry for Dijkstra's algorithm.
+struct Visit {
+    miles: f64,
+    node: usize,
+}
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.miles.total_cmp(&self.miles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_walkable, PedestrianGraph};
+    use crate::lib::routing::pbf::OsmWay;
+    use statrs::assert_almost_eq;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_distances_from() {
+        // Three nodes on a line north from the station, each about 0.1 miles apart, and one
+        // far away that isn't connected.
+        let nodes = HashMap::from([
+            (1, (-0.123795, 51.530312)),
+            (2, (-0.123795, 51.531760)),
+            (3, (-0.123795, 51.533208)),
+            (4, (-0.100000, 51.600000)),
+        ]);
+        let ways = vec![OsmWay {
+            id: 1,
+            node_ids: vec![1, 2, 3, 99],
+        }];
+        let graph = PedestrianGraph::new(&ways, &nodes);
+        assert_eq!(graph.num_nodes(), 3);
+
+        let distances = graph.distances_from((-0.123795, 51.530312), 0.15);
+        assert_eq!(distances.len(), 2);
+        assert_almost_eq!(
+            graph
+                .distance_to(&distances, (-0.123795, 51.531760))
+                .unwrap(),
+            0.1,
+            1e-3
+        );
+        assert_eq!(graph.distance_to(&distances, (-0.123795, 51.533208)), None);
+        assert_eq!(graph.distance_to(&distances, (-0.1, 51.6)), None);
+
+        let tags = |pairs: &[(&'static str, &'static str)]| pairs.iter().copied().collect();
+        assert!(is_walkable(&tags(&[("highway", "residential")])));
+        assert!(!is_walkable(&tags(&[("highway", "motorway")])));
+        assert!(!is_walkable(&tags(&[
+            ("highway", "footway"),
+            ("access", "private")
+        ])));
+        assert!(!is_walkable(&tags(&[("building", "yes")])));
+    }
+}
@@ -0,0 +1,123 @@
+use super::graph::PedestrianGraph;
+use crate::lib::tube::TubeStation;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Area within a given walk of a station, as the convex hull of the street network reached.
+/// This overstates the area where streets don't connect, so listings are filtered by their own
+/// walking time rather than by whether they fall inside.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StationIsochrone {
+    pub name: String,
+    pub postcode: String,
+    pub coordinates: (f64, f64), // (longitude, latitude)
+    pub minutes: u32,
+    pub polygon: Vec<(f64, f64)>, // closed ring of (longitude, latitude), anticlockwise
+}
+
+pub fn walking_miles(minutes: f64, speed_mph: f64) -> f64 {
+    minutes / 60.0 * speed_mph
+}
+
+impl StationIsochrone {
+    /// Isochrones for each of `minutes`, walking at `speed_mph`.
+    pub fn for_station(
+        graph: &PedestrianGraph,
+        station: &TubeStation,
+        speed_mph: f64,
+        minutes: &[u32],
+    ) -> Vec<StationIsochrone> {
+        let max_minutes = minutes.iter().copied().max().unwrap_or(0);
+        let distances = graph.distances_from(
+            station.coordinates,
+            walking_miles(max_minutes as f64, speed_mph),
+        );
+        minutes
+            .iter()
+            .map(|&minutes| {
+                let max_miles = walking_miles(minutes as f64, speed_mph);
+                let points = distances
+                    .iter()
+                    .filter(|(_, &miles)| miles <= max_miles)
+                    .map(|(&node, _)| graph.coordinates(node))
+                    .chain([station.coordinates])
+                    .collect_vec();
+                StationIsochrone {
+                    name: station.name.clone(),
+                    postcode: station.postcode.clone(),
+                    coordinates: station.coordinates,
+                    minutes,
+                    polygon: convex_hull(points),
+                }
+            })
+            .collect()
+    }
+
+    /// GeoJSON FeatureCollection of isochrone polygons.
+    #[allow(dead_code)] // only used by the server
+    pub fn to_geojson(isochrones: &[StationIsochrone]) -> Value {
+        json!({
+            "type": "FeatureCollection",
+            "features": isochrones.iter().map(|isochrone| json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [isochrone
+                        .polygon
+                        .iter()
+                        .map(|&(longitude, latitude)| [longitude, latitude])
+                        .collect_vec()],
+                },
+                "properties": {
+                    "name": isochrone.name,
+                    "postcode": isochrone.postcode,
+                    "minutes": isochrone.minutes,
+                },
+            })).collect_vec(),
+        })
+    }
+}
+
+/// Andrew's monotone chain, closed by repeating the first point.
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f64, f64)> = vec![];
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        // The last point of each pass starts the next.
+        hull.pop();
+    }
+    hull.push(hull[0]);
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convex_hull;
+
+    #[test]
+    fn test_convex_hull() {
+        let points = vec![(0.0, 0.0), (1.0, 0.0), (0.5, 0.5), (1.0, 1.0), (0.0, 1.0)];
+        assert_eq!(
+            convex_hull(points),
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]
+        );
+    }
+}
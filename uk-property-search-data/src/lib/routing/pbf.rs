@@ -0,0 +1,141 @@
+use anyhow::Result;
+use osmpbf::{Element, ElementReader};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+/// A way from an OpenStreetMap extract, as the ids of the nodes along it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsmWay {
+    pub id: i64,
+    pub node_ids: Vec<i64>,
+}
+
+/// Ways whose tags pass `keep`.
+pub fn read_ways(path: &Path, keep: impl Fn(&HashMap<&str, &str>) -> bool) -> Result<Vec<OsmWay>> {
+    let mut ways = vec![];
+    ElementReader::from_path(path)?.for_each(|element| {
+        if let Element::Way(way) = element {
+            if keep(&way.tags().collect()) {
+                ways.push(OsmWay {
+                    id: way.id(),
+                    node_ids: way.refs().collect(),
+                });
+            }
+        }
+    })?;
+    Ok(ways)
+}
+
+/// (longitude, latitude) of each wanted node.
+pub fn read_nodes(path: &Path, wanted: &HashSet<i64>) -> Result<HashMap<i64, (f64, f64)>> {
+    let mut nodes = HashMap::new();
+    ElementReader::from_path(path)?.for_each(|element| {
+        let (id, coordinates) = match element {
+            Element::Node(node) => (node.id(), (node.lon(), node.lat())),
+            Element::DenseNode(node) => (node.id(), (node.lon(), node.lat())),
+            _ => return,
+        };
+        if wanted.contains(&id) {
+            nodes.insert(id, coordinates);
+        }
+    })?;
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_nodes, read_ways, OsmWay};
+    use std::{collections::HashSet, io::Write};
+
+    fn varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn zigzag(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+
+    fn field(number: u64, bytes: &[u8]) -> Vec<u8> {
+        [
+            varint(number << 3 | 2),
+            varint(bytes.len() as u64),
+            bytes.to_vec(),
+        ]
+        .concat()
+    }
+
+    fn packed(values: &[u64]) -> Vec<u8> {
+        values.iter().flat_map(|&v| varint(v)).collect()
+    }
+
+    #[test]
+    fn test_read_ways_and_nodes() {
+        let strings = ["", "highway", "footway", "building", "yes"]
+            .iter()
+            .flat_map(|s| field(1, s.as_bytes()))
+            .collect::<Vec<_>>();
+        // Two nodes with ids 10 and 11, at granularity 100 nanodegrees.
+        let dense = [
+            field(1, &packed(&[zigzag(10), zigzag(1)])),
+            field(8, &packed(&[zigzag(515303120), zigzag(10)])),
+            field(9, &packed(&[zigzag(-1237950), zigzag(-10)])),
+        ]
+        .concat();
+        let way = |id: u64, key: u64, val: u64| {
+            [
+                varint(1 << 3),
+                varint(id),
+                field(2, &packed(&[key])),
+                field(3, &packed(&[val])),
+                field(8, &packed(&[zigzag(10), zigzag(1)])),
+            ]
+            .concat()
+        };
+        let group = [
+            field(2, &dense),
+            field(3, &way(1, 1, 2)),
+            field(3, &way(2, 3, 4)),
+        ]
+        .concat();
+        let block = [field(1, &strings), field(2, &group)].concat();
+        let blob = field(1, &block);
+        let header = [
+            field(1, b"OSMData"),
+            varint(3 << 3),
+            varint(blob.len() as u64),
+        ]
+        .concat();
+        let bytes = [(header.len() as u32).to_be_bytes().to_vec(), header, blob].concat();
+        let mut file = tempfile::Builder::new()
+            .suffix(".osm.pbf")
+            .tempfile()
+            .unwrap();
+        file.write_all(&bytes).unwrap();
+        let path = file.path();
+
+        let ways = read_ways(path, |tags| tags.contains_key("highway")).unwrap();
+        assert_eq!(
+            ways,
+            vec![OsmWay {
+                id: 1,
+                node_ids: vec![10, 11]
+            }]
+        );
+        let nodes = read_nodes(path, &HashSet::from([11])).unwrap();
+        assert_eq!(nodes.len(), 1);
+        let (longitude, latitude) = nodes[&11];
+        assert!((longitude - -0.123796).abs() < 1e-9);
+        assert!((latitude - 51.530313).abs() < 1e-9);
+    }
+}
@@ -10,6 +10,12 @@ pub struct School {
     pub coordinates: (f64, f64), // (longitude, latitude)
     pub rating: u8,
     pub inspection_date_ms: Option<i64>, // unix milliseconds
+    #[serde(default)]
+    pub borough: Option<String>, // GSS code, None outside the borough boundaries
+    #[serde(default)]
+    pub ward: Option<String>, // GSS code
+    #[serde(default)]
+    pub lsoa: Option<String>, // GSS code
 }
 
 #[derive(Copy, Clone, Debug)]
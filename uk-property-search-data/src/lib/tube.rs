@@ -9,4 +9,10 @@ pub struct TubeStation {
     pub postcode: String,
     pub coordinates: (f64, f64), // (longitude, latitude)
     pub lines: HashSet<String>,
+    #[serde(default)]
+    pub borough: Option<String>, // GSS code, None outside the borough boundaries
+    #[serde(default)]
+    pub ward: Option<String>, // GSS code
+    #[serde(default)]
+    pub lsoa: Option<String>, // GSS code
 }
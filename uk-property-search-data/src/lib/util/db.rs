@@ -1,5 +1,25 @@
 use super::properties::Properties;
-use crate::lib::{property::property::PropertySummary, school::School, tube::TubeStation};
+use crate::lib::{
+    finance::cost_of_living::{CouncilTax, TravelcardFare},
+    geography::boundary::Boundary,
+    postcode::Postcode,
+    property::{
+        area::{AreaLevel, AreaPropertySummary},
+        assignment::StationOverlap,
+        discount::PriceDiscountSnapshot,
+        epc::EpcCertificate,
+        estate_agents::drift::DriftReport,
+        heat::MarketHeat,
+        lifecycle::{ListingEvent, ListingSnapshot},
+        listing_details::ListingDetails,
+        property::PropertySummary,
+        rollup::StationSketch,
+        sold_price::SoldPriceSummary,
+    },
+    routing::isochrone::StationIsochrone,
+    school::School,
+    tube::TubeStation,
+};
 use mongodb::{options::ClientOptions, Client, Collection, Database};
 use serde::{Deserialize, Serialize};
 
@@ -24,6 +44,66 @@ impl Db {
         self.database.collection("property")
     }
 
+    pub fn area_property(&self, level: AreaLevel) -> Collection<AreaPropertySummary> {
+        self.database.collection(match level {
+            AreaLevel::District => "district_property",
+            AreaLevel::Sector => "sector_property",
+        })
+    }
+
+    pub fn borough_property(&self) -> Collection<AreaPropertySummary> {
+        self.database.collection("borough_property")
+    }
+
+    pub fn property_sketches(&self) -> Collection<StationSketch> {
+        self.database.collection("property_sketches")
+    }
+
+    pub fn sold_prices(&self) -> Collection<SoldPriceSummary> {
+        self.database.collection("sold_prices")
+    }
+
+    pub fn price_discounts(&self) -> Collection<PriceDiscountSnapshot> {
+        self.database.collection("price_discounts")
+    }
+
+    pub fn epc(&self) -> Collection<EpcCertificate> {
+        self.database.collection("epc")
+    }
+
+    pub fn market_heat(&self) -> Collection<MarketHeat> {
+        self.database.collection("market_heat")
+    }
+
+    pub fn station_overlap(&self) -> Collection<StationOverlap> {
+        self.database.collection("station_overlap")
+    }
+
+    pub fn isochrones(&self) -> Collection<StationIsochrone> {
+        self.database.collection("isochrones")
+    }
+
+    pub fn postcodes(&self) -> Collection<Postcode> {
+        self.database.collection("postcodes")
+    }
+
+    // Filled in full before being renamed over `postcodes`.
+    pub fn postcodes_staging(&self) -> Collection<Postcode> {
+        self.database.collection("postcodes_staging")
+    }
+
+    pub fn boundaries(&self) -> Collection<Boundary> {
+        self.database.collection("boundaries")
+    }
+
+    pub fn council_tax(&self) -> Collection<CouncilTax> {
+        self.database.collection("council_tax")
+    }
+
+    pub fn travelcard_fares(&self) -> Collection<TravelcardFare> {
+        self.database.collection("travelcard_fares")
+    }
+
     pub fn schools(&self) -> Collection<School> {
         self.database.collection("schools")
     }
@@ -32,16 +112,39 @@ impl Db {
         self.database.collection("tube")
     }
 
+    pub fn listing_details(&self) -> Collection<ListingDetails> {
+        self.database.collection("listing_details")
+    }
+
+    pub fn listing_snapshots(&self) -> Collection<ListingSnapshot> {
+        self.database.collection("listing_snapshots")
+    }
+
+    pub fn listing_events(&self) -> Collection<ListingEvent> {
+        self.database.collection("listing_events")
+    }
+
+    pub fn schema_drift(&self) -> Collection<DriftReport> {
+        self.database.collection("schema_drift")
+    }
+
     pub fn last_updated(&self) -> Collection<LastUpdated> {
         self.database.collection("last_updated")
     }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct LastUpdated {
-    pub property: Option<i64>, // unix milliseconds
-    pub schools: Option<i64>,  // unix milliseconds
-    pub tube: Option<i64>,     // unix milliseconds
+    pub property: Option<i64>,       // unix milliseconds
+    pub schools: Option<i64>,        // unix milliseconds
+    pub tube: Option<i64>,           // unix milliseconds
+    pub sold_prices: Option<i64>,    // unix milliseconds
+    pub epc: Option<i64>,            // unix milliseconds
+    pub isochrones: Option<i64>,     // unix milliseconds
+    pub postcodes: Option<i64>,      // unix milliseconds
+    pub boundaries: Option<i64>,     // unix milliseconds
+    pub cost_of_living: Option<i64>, // unix milliseconds
 }
 
 #[cfg(test)]
@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use std::time::Duration;
+use thiserror::Error;
+
+// Only keep the start of the response body in errors, enough to recognise the page.
+const MAX_BODY_SAMPLE_LENGTH: usize = 500;
+
+/// Failure while scraping an estate agent, classified from the response status and body.
+#[derive(Debug, Error)]
+pub enum ScraperError {
+    #[error("Blocked at [{}] by URL: [{url}] with status code [{status_code}]: {reason}", .at.format("%H:%M"))]
+    Blocked {
+        url: String,
+        status_code: u16,
+        reason: String,
+        at: DateTime<Utc>,
+    },
+    #[error("Rate limited at [{}] by URL: [{url}], retry after: [{retry_after:?}]", .at.format("%H:%M"))]
+    RateLimited {
+        url: String,
+        retry_after: Option<Duration>,
+        at: DateTime<Utc>,
+    },
+    #[error("Not found: [{url}]")]
+    NotFound { url: String },
+    #[error("Schema changed at URL: [{url}]: {message}\nContext: {context}\nResponse body sample:\n{body_sample}")]
+    SchemaChanged {
+        url: String,
+        message: String,
+        context: String,
+        body_sample: String,
+    },
+    #[error(
+        "Transient network error for URL: [{url:?}] with status code [{status_code:?}]: {message}"
+    )]
+    Transient {
+        url: Option<String>,
+        status_code: Option<u16>,
+        message: String,
+    },
+}
+
+/// What a caller should do about a [ScraperError].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScraperErrorAction {
+    Backoff, // wait and try again
+    Skip,    // give up on this request but carry on with the rest
+    Abort,   // stop the run, further requests will fail the same way
+}
+
+impl ScraperError {
+    /// Classify an unsuccessful or unexpected response. Returns None if the response looks healthy.
+    pub fn classify(
+        url: &str,
+        status: StatusCode,
+        retry_after: Option<Duration>,
+        body: &str,
+    ) -> Option<ScraperError> {
+        if let Some(reason) = find_block_marker(status, body) {
+            return Some(ScraperError::Blocked {
+                url: url.to_owned(),
+                status_code: status.as_u16(),
+                reason: reason.to_owned(),
+                at: Utc::now(),
+            });
+        }
+        match status {
+            StatusCode::FORBIDDEN => Some(ScraperError::Blocked {
+                url: url.to_owned(),
+                status_code: status.as_u16(),
+                reason: "Access forbidden".to_owned(),
+                at: Utc::now(),
+            }),
+            StatusCode::TOO_MANY_REQUESTS => Some(ScraperError::RateLimited {
+                url: url.to_owned(),
+                retry_after,
+                at: Utc::now(),
+            }),
+            StatusCode::NOT_FOUND | StatusCode::GONE => Some(ScraperError::NotFound {
+                url: url.to_owned(),
+            }),
+            s if s.is_client_error() || s.is_server_error() => Some(ScraperError::Transient {
+                url: Some(url.to_owned()),
+                status_code: Some(s.as_u16()),
+                message: body_sample(body),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn schema_changed(url: &str, message: String, context: &str, body: &str) -> ScraperError {
+        ScraperError::SchemaChanged {
+            url: url.to_owned(),
+            message,
+            context: context.to_owned(),
+            body_sample: body_sample(body),
+        }
+    }
+
+    pub fn action(&self) -> ScraperErrorAction {
+        match self {
+            ScraperError::Blocked { .. } => ScraperErrorAction::Abort,
+            ScraperError::RateLimited { .. } | ScraperError::Transient { .. } => {
+                ScraperErrorAction::Backoff
+            }
+            ScraperError::NotFound { .. } | ScraperError::SchemaChanged { .. } => {
+                ScraperErrorAction::Skip
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for ScraperError {
+    fn from(err: reqwest::Error) -> Self {
+        ScraperError::Transient {
+            url: err.url().map(|u| u.to_string()),
+            status_code: err.status().map(|s| s.as_u16()),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<reqwest_middleware::Error> for ScraperError {
+    fn from(err: reqwest_middleware::Error) -> Self {
+        match err {
+            reqwest_middleware::Error::Reqwest(e) => e.into(),
+            reqwest_middleware::Error::Middleware(e) => ScraperError::Transient {
+                url: None,
+                status_code: None,
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+fn find_block_marker(status: StatusCode, body: &str) -> Option<&'static str> {
+    const BLOCK_MARKERS: &[(&str, &str)] = &[
+        ("captcha", "Captcha challenge"),
+        ("are you a robot", "Robot check"),
+        ("access denied", "Access denied page"),
+        ("attention required", "Cloudflare challenge"),
+        ("cf-chl", "Cloudflare challenge"),
+        ("_incapsula_resource", "Incapsula challenge"),
+    ];
+    const BLOCK_PAGE_TITLES: &[(&str, &str)] = &[
+        ("are you a robot", "Robot check"),
+        ("access denied", "Access denied page"),
+        ("attention required", "Cloudflare challenge"),
+        ("just a moment", "Cloudflare challenge"),
+    ];
+    // Block pages are small HTML documents, so don't scan large JSON payloads for markers.
+    if body.trim_start().starts_with('{') || body.trim_start().starts_with('[') {
+        return None;
+    }
+    let body = body.to_lowercase();
+    match status {
+        // Healthy pages may embed captcha scripts (e.g. reCAPTCHA on forms), so only trust markers
+        // anywhere in the body when the status already signals a refusal.
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            BLOCK_MARKERS
+                .iter()
+                .find(|(marker, _)| body.contains(marker))
+                .map(|(_, reason)| *reason)
+        }
+        // Otherwise the page itself must be a block page, recognised by its title.
+        _ => {
+            let title = page_title(&body)?;
+            BLOCK_PAGE_TITLES
+                .iter()
+                .find(|(marker, _)| title.contains(marker))
+                .map(|(_, reason)| *reason)
+        }
+    }
+}
+
+fn page_title(body: &str) -> Option<&str> {
+    let start = body.find("<title")?;
+    let start = start + body[start..].find('>')? + 1;
+    let end = start + body[start..].find("</title>")?;
+    Some(&body[start..end])
+}
+
+fn body_sample(body: &str) -> String {
+    match body.char_indices().nth(MAX_BODY_SAMPLE_LENGTH) {
+        Some((index, _)) => format!("{}...", &body[..index]),
+        None => body.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScraperError, ScraperErrorAction};
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    const URL: &str = "https://www.rightmove.co.uk/api/_search";
+
+    #[test]
+    fn test_classify_healthy() {
+        assert!(
+            ScraperError::classify(URL, StatusCode::OK, None, "{\"properties\": []}").is_none()
+        );
+    }
+
+    #[test]
+    fn test_classify_captcha() {
+        let body = "<html><body>Please complete the CAPTCHA to continue</body></html>";
+        let err = ScraperError::classify(URL, StatusCode::FORBIDDEN, None, body).unwrap();
+        assert!(matches!(err, ScraperError::Blocked { .. }));
+        assert_eq!(err.action(), ScraperErrorAction::Abort);
+    }
+
+    #[test]
+    fn test_classify_block_page() {
+        let body = "<html><head><title>Attention Required! | Cloudflare</title></head></html>";
+        let err = ScraperError::classify(URL, StatusCode::OK, None, body).unwrap();
+        assert!(matches!(err, ScraperError::Blocked { .. }));
+    }
+
+    #[test]
+    fn test_classify_healthy_page_with_captcha_script() {
+        let body = "<html><head><title>Flats for sale in SW1A</title>\
+            <script src=\"https://www.google.com/recaptcha/api.js\"></script></head></html>";
+        assert!(ScraperError::classify(URL, StatusCode::OK, None, body).is_none());
+    }
+
+    #[test]
+    fn test_classify_status_codes() {
+        let rate_limited = ScraperError::classify(
+            URL,
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(Duration::from_secs(30)),
+            "",
+        )
+        .unwrap();
+        assert!(matches!(
+            rate_limited,
+            ScraperError::RateLimited {
+                retry_after: Some(_),
+                ..
+            }
+        ));
+        assert_eq!(rate_limited.action(), ScraperErrorAction::Backoff);
+
+        let not_found = ScraperError::classify(URL, StatusCode::NOT_FOUND, None, "").unwrap();
+        assert_eq!(not_found.action(), ScraperErrorAction::Skip);
+
+        let bad_request = ScraperError::classify(URL, StatusCode::BAD_REQUEST, None, "").unwrap();
+        assert!(matches!(bad_request, ScraperError::Transient { .. }));
+    }
+
+    #[test]
+    fn test_schema_changed_truncates_body() {
+        let body = "x".repeat(10000);
+        match ScraperError::schema_changed(URL, "missing field".to_owned(), "test", &body) {
+            ScraperError::SchemaChanged { body_sample, .. } => {
+                assert!(body_sample.len() < 1000)
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+}
@@ -1,50 +1,53 @@
-use anyhow::{anyhow, Result};
+use super::error::ScraperError;
+use anyhow::Result;
 use async_trait::async_trait;
 use futures::StreamExt;
-use itertools::Itertools;
-use mongodb::Collection;
-use reqwest::Response;
+use mongodb::{
+    bson::{doc, Document},
+    options::FindOptions,
+    Collection,
+};
+use reqwest::{header::RETRY_AFTER, Response};
 use rocket::serde::DeserializeOwned;
+use std::time::Duration;
 
-/// Decode response as json, but print the response body on failure.
+/// Decode response as text or json, classifying blocked, rate limited or otherwise failed responses.
 #[async_trait]
-pub trait DecodeJsonResponseExt {
-    async fn json_or_err<T: DeserializeOwned>(self, context: &str) -> Result<T>;
+pub trait DecodeResponseExt {
+    async fn text_or_err(self) -> Result<String, ScraperError>;
+    async fn json_or_err<T: DeserializeOwned>(self, context: &str) -> Result<T, ScraperError>;
 }
 
 #[async_trait]
-impl DecodeJsonResponseExt for Response {
-    async fn json_or_err<T: DeserializeOwned>(self, context: &str) -> Result<T> {
-        let url = self.url().clone();
-        let status_code = self.status().as_u16();
+impl DecodeResponseExt for Response {
+    async fn text_or_err(self) -> Result<String, ScraperError> {
+        let url = self.url().to_string();
+        let status = self.status();
+        let retry_after = self
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs);
         let text = self.text().await?;
-        serde_json::from_str(&text).map_err(|e| {
-            anyhow!(
-                "{}\nURL: {}\nStatus code: {}\nResponse body:\n{}\nContext: {}",
-                e,
-                url,
-                status_code,
-                &text,
-                context,
-            )
-        })
+        match ScraperError::classify(&url, status, retry_after, &text) {
+            Some(err) => Err(err),
+            None => Ok(text),
+        }
     }
-}
-
-/// Unwrap Vec<Result<T>> into Vec<T>, panicking on failure.
-pub trait VecResultExt<T> {
-    fn unwrap_all(self) -> Vec<T>;
-}
 
-impl<T> VecResultExt<T> for Vec<Result<T>> {
-    fn unwrap_all(self) -> Vec<T> {
-        self.into_iter().map(|result| result.unwrap()).collect_vec()
+    async fn json_or_err<T: DeserializeOwned>(self, context: &str) -> Result<T, ScraperError> {
+        let url = self.url().to_string();
+        let text = self.text_or_err().await?;
+        serde_json::from_str(&text)
+            .map_err(|e| ScraperError::schema_changed(&url, e.to_string(), context, &text))
     }
 }
 
 #[async_trait]
 pub trait MongoCollectionExt<T> {
     async fn find_to_vec(&self) -> Vec<T>;
+    async fn find_filtered_to_vec(&self, filter: Document, options: Option<FindOptions>) -> Vec<T>;
 }
 
 #[async_trait]
@@ -53,7 +56,11 @@ where
     T: DeserializeOwned + Unpin + Send + Sync,
 {
     async fn find_to_vec(&self) -> Vec<T> {
-        self.find(None, None)
+        self.find_filtered_to_vec(doc! {}, None).await
+    }
+
+    async fn find_filtered_to_vec(&self, filter: Document, options: Option<FindOptions>) -> Vec<T> {
+        self.find(filter, options)
             .await
             .unwrap()
             .collect::<Vec<_>>()
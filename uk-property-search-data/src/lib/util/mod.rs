@@ -1,4 +1,5 @@
 pub mod db;
+pub mod error;
 pub mod ext;
 pub mod globals;
 pub mod http;
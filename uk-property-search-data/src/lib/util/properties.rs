@@ -8,6 +8,58 @@ impl Properties {
     pub fn new() -> Self {
         Self {
             config: Config::builder()
+                // Defaults for optional settings, overridable in properties.toml
+                .set_default("drift.max.affected.proportion", 0.05)
+                .unwrap()
+                .set_default("property.price.excluded.qualifiers", "poa,sharedOwnership")
+                .unwrap()
+                .set_default("rightmove.max.detail.fetches.per.run", 500)
+                .unwrap()
+                .set_default("property.station.assignment", "radius")
+                .unwrap()
+                .set_default("property.distance.rings", "")
+                .unwrap()
+                .set_default("property.catchment", "radius")
+                .unwrap()
+                .set_default("property.area.levels", "district,sector")
+                .unwrap()
+                .set_default(
+                    "geography.boroughs.path",
+                    "assets/boundaries/boroughs.geojson",
+                )
+                .unwrap()
+                .set_default("geography.wards.path", "assets/boundaries/wards.geojson")
+                .unwrap()
+                .set_default("geography.lsoas.path", "assets/boundaries/lsoas.geojson")
+                .unwrap()
+                .set_default("property.walking.minutes", 10.0)
+                .unwrap()
+                .set_default(
+                    "routing.osm.path",
+                    "assets/osm/greater-london-latest.osm.pbf",
+                )
+                .unwrap()
+                .set_default("routing.walking.speed.mph", 3.0)
+                .unwrap()
+                .set_default("routing.isochrone.minutes", "5,10,15")
+                .unwrap()
+                .set_default("property.stats.trimming", "none")
+                .unwrap()
+                .set_default(
+                    "property.stats.percentiles",
+                    "0.1,0.2,0.3,0.4,0.5,0.6,0.7,0.8,0.9",
+                )
+                .unwrap()
+                .set_default("property.stats.histogram.bins", 20)
+                .unwrap()
+                .set_default("property.stats.bootstrap.resamples", 200)
+                .unwrap()
+                .set_default("property.stats.bootstrap.confidence", 0.95)
+                .unwrap()
+                .set_default("property.stats.min.count", 5)
+                .unwrap()
+                .set_default("property.stats.below.min.count", "flag")
+                .unwrap()
                 .add_source(File::with_name("properties.toml"))
                 .build()
                 .unwrap(),
@@ -18,6 +70,10 @@ impl Properties {
         self.config.get_int(key).unwrap()
     }
 
+    pub fn get_float(&self, key: &str) -> f64 {
+        self.config.get_float(key).unwrap()
+    }
+
     pub fn get_string(&self, key: &str) -> String {
         self.config.get_string(key).unwrap()
     }
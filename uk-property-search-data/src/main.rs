@@ -9,7 +9,11 @@ use lib::util::globals::Globals;
 use log::info;
 use stopwatch::Stopwatch;
 use tasks::{
-    update_property::update_property, update_schools::update_schools, update_tube::update_tube,
+    update_boundaries::update_boundaries, update_cost_of_living::update_cost_of_living,
+    update_epc::update_epc, update_isochrones::update_isochrones,
+    update_postcodes::update_postcodes, update_property::update_property,
+    update_schools::update_schools, update_sold_prices::update_sold_prices,
+    update_tube::update_tube,
 };
 
 #[tokio::main]
@@ -20,8 +24,14 @@ async fn main() -> Result<()> {
     for task in args.task {
         let sw = Stopwatch::start_new();
         match task {
+            CliTask::UpdateBoundaries => update_boundaries(&globals).await?,
+            CliTask::UpdateCostOfLiving => update_cost_of_living(&globals).await?,
+            CliTask::UpdateEpc => update_epc(&globals).await?,
+            CliTask::UpdateIsochrones => update_isochrones(&globals).await?,
+            CliTask::UpdatePostcodes => update_postcodes(&globals).await?,
             CliTask::UpdateProperty => update_property(&globals).await?,
             CliTask::UpdateSchools => update_schools(&globals).await?,
+            CliTask::UpdateSoldPrices => update_sold_prices(&globals).await?,
             CliTask::UpdateTube => update_tube(&globals).await?,
         }
         info!("Completed task [{:?}] in [{:?}].", task, sw.elapsed());
@@ -1,3 +1,9 @@
+pub mod update_boundaries;
+pub mod update_cost_of_living;
+pub mod update_epc;
+pub mod update_isochrones;
+pub mod update_postcodes;
 pub mod update_property;
 pub mod update_schools;
+pub mod update_sold_prices;
 pub mod update_tube;
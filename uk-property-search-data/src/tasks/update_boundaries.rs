@@ -0,0 +1,57 @@
+use crate::lib::{
+    geography::boundary::{Boundary, BoundaryLevel},
+    util::globals::Globals,
+};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use log::info;
+use mongodb::{bson::doc, options::FindOneAndUpdateOptions, IndexModel};
+
+pub async fn update_boundaries(globals: &Globals) -> Result<()> {
+    let mut boundaries = vec![];
+    for (level, path_key) in [
+        (BoundaryLevel::Borough, "geography.boroughs.path"),
+        (BoundaryLevel::Ward, "geography.wards.path"),
+        (BoundaryLevel::Lsoa, "geography.lsoas.path"),
+    ] {
+        let path = globals.properties.get_string(path_key);
+        let level_boundaries = Boundary::read_geojson(level, &path)?;
+        if level_boundaries.is_empty() {
+            bail!("No boundaries found in [{}]!", path);
+        }
+        info!(
+            "Read [{}] [{}] boundaries from [{}].",
+            level_boundaries.len(),
+            level.name(),
+            path
+        );
+        boundaries.extend(level_boundaries);
+    }
+
+    // Detailed polygons for every LSOA are too large to write in one transaction, so the
+    // collection is briefly incomplete while it's replaced.
+    globals.db.boundaries().delete_many(doc! {}, None).await?;
+    globals
+        .db
+        .boundaries()
+        .insert_many(boundaries, None)
+        .await?;
+    globals
+        .db
+        .last_updated()
+        .find_one_and_update(
+            doc! {},
+            doc! {"$set": {"boundaries":  Utc::now().timestamp_millis() }},
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+
+    // Creating an existing index is a no-op.
+    globals
+        .db
+        .boundaries()
+        .create_index(IndexModel::builder().keys(doc! {"level": 1}).build(), None)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::lib::{
+    finance::cost_of_living::{CouncilTax, TravelcardFare},
+    util::globals::Globals,
+};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use itertools::{multizip, Itertools};
+use log::info;
+use mongodb::{bson::doc, options::FindOneAndUpdateOptions};
+use polars::{
+    io::SerReader,
+    prelude::{CsvReader, DataType},
+};
+
+const COUNCIL_TAX_BANDS: [&str; 8] = ["A", "B", "C", "D", "E", "F", "G", "H"];
+
+pub async fn update_cost_of_living(globals: &Globals) -> Result<()> {
+    // One row per borough, by GSS code and name, with the annual charge for each band in
+    // columns A to H.
+    let council_tax_df = CsvReader::from_path("assets/council_tax.csv")?.finish()?;
+    let codes = council_tax_df.column("code")?.utf8()?;
+    let boroughs = council_tax_df.column("borough")?.utf8()?;
+    let bands = COUNCIL_TAX_BANDS
+        .iter()
+        .map(|band| {
+            Ok(council_tax_df
+                .column(band)?
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .collect_vec())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut council_tax = vec![];
+    for (i, (code, borough)) in codes.into_iter().zip(boroughs).enumerate() {
+        let charges = bands.iter().map(|band| band[i]).collect::<Option<Vec<_>>>();
+        match (code, borough, charges) {
+            (Some(code), Some(borough), Some(charges)) => council_tax.push(CouncilTax {
+                code: code.to_owned(),
+                borough: borough.to_owned(),
+                bands: charges,
+            }),
+            (code, borough, _) => bail!(
+                "Council tax for [{:?}] [{:?}] is missing a code or band!",
+                code,
+                borough
+            ),
+        }
+    }
+
+    let fares_df = CsvReader::from_path("assets/travelcard_fares.csv")?.finish()?;
+    let from_zones = fares_df.column("from_zone")?.i64()?;
+    let to_zones = fares_df.column("to_zone")?.i64()?;
+    let annual_fares = fares_df.column("annual")?.cast(&DataType::Float64)?;
+    let annual_fares = annual_fares.f64()?;
+    let fares = multizip((from_zones, to_zones, annual_fares))
+        .filter_map(|(from_zone, to_zone, annual)| {
+            let (from_zone, to_zone): (u8, u8) =
+                (from_zone?.try_into().ok()?, to_zone?.try_into().ok()?);
+            Some(TravelcardFare {
+                from_zone: from_zone.min(to_zone),
+                to_zone: from_zone.max(to_zone),
+                annual: annual?,
+            })
+        })
+        .collect_vec();
+    if council_tax.is_empty() || fares.is_empty() {
+        bail!("No council tax or travelcard fares found!");
+    }
+    info!(
+        "Read council tax for [{}] boroughs and [{}] travelcard fares.",
+        council_tax.len(),
+        fares.len()
+    );
+
+    let mut session = globals.db.client.start_session(None).await?;
+    session.start_transaction(None).await?;
+
+    globals
+        .db
+        .council_tax()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    globals
+        .db
+        .council_tax()
+        .insert_many_with_session(council_tax, None, &mut session)
+        .await?;
+    globals
+        .db
+        .travelcard_fares()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    globals
+        .db
+        .travelcard_fares()
+        .insert_many_with_session(fares, None, &mut session)
+        .await?;
+    globals
+        .db
+        .last_updated()
+        .find_one_and_update_with_session(
+            doc! {},
+            doc! {"$set": {"costOfLiving":  Utc::now().timestamp_millis() }},
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+            &mut session,
+        )
+        .await?;
+    session.commit_transaction().await?;
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use super::update_property::search_radius;
+use crate::lib::{
+    math::geo::haversine_miles,
+    postcode::PostcodeLookup,
+    property::{
+        epc::{normalise_address, EpcCertificate, EPC_MATCH_RADIUS},
+        listing_details::EpcBand,
+    },
+    tube::TubeStation,
+    util::{ext::MongoCollectionExt, globals::Globals},
+};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use itertools::{multizip, Itertools};
+use log::info;
+use mongodb::{
+    bson::doc,
+    options::{FindOneAndUpdateOptions, IndexOptions},
+    IndexModel,
+};
+use polars::{
+    io::SerReader,
+    prelude::{CsvReader, DataFrame, DataType},
+};
+use std::{collections::HashMap, fs};
+
+const SQUARE_FEET_PER_SQUARE_METRE: f64 = 10.7639;
+
+pub async fn update_epc(globals: &Globals) -> Result<()> {
+    fn read_certificates(path: &str) -> Result<DataFrame> {
+        Ok(CsvReader::from_path(path)?
+            .with_columns(Some(
+                [
+                    "ADDRESS",
+                    "POSTCODE",
+                    "PROPERTY_TYPE",
+                    "CURRENT_ENERGY_RATING",
+                    "POTENTIAL_ENERGY_RATING",
+                    "TOTAL_FLOOR_AREA",
+                    "LODGEMENT_DATE",
+                ]
+                .map(|column| column.to_owned())
+                .to_vec(),
+            ))
+            .finish()?)
+    }
+
+    // The bulk download has a directory of certificates for each local authority.
+    let certificate_paths = fs::read_dir("assets/epc")?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("certificates.csv"))
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .sorted()
+        .collect_vec();
+    if certificate_paths.is_empty() {
+        bail!("No EPC certificates (assets/epc/*/certificates.csv) found!");
+    }
+    info!("Reading EPC certificates: [{:?}]", certificate_paths);
+
+    let mut certificates_df = read_certificates(&certificate_paths[0])?;
+    for path in &certificate_paths[1..] {
+        certificates_df.vstack_mut(&read_certificates(path)?)?;
+    }
+
+    let addresses = certificates_df.column("ADDRESS")?.utf8()?;
+    let postcodes = certificates_df.column("POSTCODE")?.utf8()?;
+    let property_types = certificates_df.column("PROPERTY_TYPE")?.utf8()?;
+    let current_ratings = certificates_df.column("CURRENT_ENERGY_RATING")?.utf8()?;
+    let potential_ratings = certificates_df.column("POTENTIAL_ENERGY_RATING")?.utf8()?;
+    let floor_areas = certificates_df
+        .column("TOTAL_FLOOR_AREA")?
+        .cast(&DataType::Float64)?;
+    let floor_areas = floor_areas.f64()?;
+    let lodgement_dates = certificates_df.column("LODGEMENT_DATE")?.utf8()?;
+    let postcode_lookup =
+        PostcodeLookup::load(&globals.db, postcodes.into_iter().flatten()).await?;
+
+    // Listings are only searched for near stations, so leave out certificates further away.
+    let tube_stations: Vec<TubeStation> = globals.db.tube().find_to_vec().await;
+    // Cover every listing the property update searches, including those in distance rings.
+    let search_radius = search_radius(globals);
+    let mut is_near_station: HashMap<String, bool> = HashMap::new();
+
+    // An address can have several certificates over the years, so keep the latest one.
+    let mut latest_certificates: HashMap<(String, String), EpcCertificate> = HashMap::new();
+    for (
+        address,
+        postcode,
+        property_type,
+        current_rating,
+        potential_rating,
+        floor_area,
+        lodgement_date,
+    ) in multizip((
+        addresses,
+        postcodes,
+        property_types,
+        current_ratings,
+        potential_ratings,
+        floor_areas,
+        lodgement_dates,
+    )) {
+        let (
+            Some(address),
+            Some(postcode),
+            Some(current_rating),
+            Some(floor_area),
+            Some(lodgement_date),
+            Some(coordinates),
+        ) = (
+            address,
+            postcode,
+            current_rating.and_then(EpcBand::parse),
+            floor_area,
+            lodgement_date,
+            postcode.and_then(|postcode| postcode_lookup.coordinates(postcode)),
+        )
+        else {
+            continue;
+        };
+        let near_station = *is_near_station
+            .entry(postcode.to_owned())
+            .or_insert_with(|| {
+                tube_stations.iter().any(|station| {
+                    haversine_miles(station.coordinates, coordinates)
+                        <= search_radius + EPC_MATCH_RADIUS
+                })
+            });
+        if !near_station || floor_area <= 0.0 {
+            continue;
+        }
+        let certificate = EpcCertificate {
+            postcode: postcode.to_owned(),
+            address: normalise_address(address),
+            coordinates,
+            property_type: property_type.unwrap_or_default().to_owned(),
+            floor_area_square_feet: (floor_area * SQUARE_FEET_PER_SQUARE_METRE).round() as i32,
+            current_rating,
+            potential_rating: potential_rating.and_then(EpcBand::parse),
+            lodgement_date: lodgement_date
+                .get(..10)
+                .unwrap_or(lodgement_date)
+                .to_owned(),
+        };
+        let key = (certificate.postcode.clone(), certificate.address.clone());
+        match latest_certificates.get(&key) {
+            Some(existing) if existing.lodgement_date >= certificate.lodgement_date => {}
+            _ => {
+                latest_certificates.insert(key, certificate);
+            }
+        }
+    }
+    let certificates = latest_certificates.into_values().collect_vec();
+    if certificates.is_empty() {
+        bail!("No EPC certificates found near stations!");
+    }
+    info!(
+        "Read [{}] addresses with EPC certificates near stations.",
+        certificates.len()
+    );
+
+    let mut session = globals.db.client.start_session(None).await?;
+    session.start_transaction(None).await?;
+
+    globals
+        .db
+        .epc()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    globals
+        .db
+        .epc()
+        .insert_many_with_session(certificates, None, &mut session)
+        .await?;
+    globals
+        .db
+        .last_updated()
+        .find_one_and_update_with_session(
+            doc! {},
+            doc! {"$set": {"epc":  Utc::now().timestamp_millis() }},
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+            &mut session,
+        )
+        .await?;
+    session.commit_transaction().await?;
+
+    // Indexes can't be created inside a transaction, but creating an existing one is a no-op.
+    globals
+        .db
+        .epc()
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"postcode": 1, "address": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
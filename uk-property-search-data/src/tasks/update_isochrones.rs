@@ -0,0 +1,77 @@
+use crate::lib::{
+    routing::{graph::PedestrianGraph, isochrone::StationIsochrone},
+    tube::TubeStation,
+    util::{ext::MongoCollectionExt, globals::Globals},
+};
+use anyhow::Result;
+use chrono::Utc;
+use log::{info, warn};
+use mongodb::{bson::doc, options::FindOneAndUpdateOptions};
+use std::path::Path;
+use tokio::task;
+
+pub async fn update_isochrones(globals: &Globals) -> Result<()> {
+    let path = globals.properties.get_string("routing.osm.path");
+    let speed_mph = globals.properties.get_float("routing.walking.speed.mph");
+    let minutes = globals
+        .properties
+        .get_string("routing.isochrone.minutes")
+        .split(',')
+        .map(|m| {
+            m.trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("[{}] is not a valid number of minutes!", m))
+        })
+        .collect::<Vec<u32>>();
+
+    let tube_stations: Vec<TubeStation> = globals.db.tube().find_to_vec().await;
+    // Reading the network and walking from every station would otherwise stall the runtime.
+    let isochrones = task::spawn_blocking(move || -> Result<Vec<StationIsochrone>> {
+        let graph = PedestrianGraph::load(Path::new(&path))?;
+        info!("Loaded street network with [{}] nodes.", graph.num_nodes());
+        Ok(tube_stations
+            .iter()
+            .filter(|station| {
+                let is_near = graph.nearest(station.coordinates).is_some();
+                if !is_near {
+                    warn!(
+                        "Station [{}] isn't near the street network, so has no isochrones.",
+                        station.name
+                    );
+                }
+                is_near
+            })
+            .flat_map(|station| StationIsochrone::for_station(&graph, station, speed_mph, &minutes))
+            .collect())
+    })
+    .await??;
+
+    let mut session = globals.db.client.start_session(None).await?;
+    session.start_transaction(None).await?;
+
+    globals
+        .db
+        .isochrones()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    if !isochrones.is_empty() {
+        globals
+            .db
+            .isochrones()
+            .insert_many_with_session(isochrones, None, &mut session)
+            .await?;
+    }
+    globals
+        .db
+        .last_updated()
+        .find_one_and_update_with_session(
+            doc! {},
+            doc! {"$set": {"isochrones":  Utc::now().timestamp_millis() }},
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+            &mut session,
+        )
+        .await?;
+    session.commit_transaction().await?;
+
+    Ok(())
+}
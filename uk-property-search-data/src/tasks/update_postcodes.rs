@@ -0,0 +1,56 @@
+use crate::lib::{postcode::Postcode, util::globals::Globals};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use log::info;
+use mongodb::{bson::doc, options::FindOneAndUpdateOptions, IndexModel};
+
+pub async fn update_postcodes(globals: &Globals) -> Result<()> {
+    let postcodes = Postcode::read_csv("assets/ukpostcodes.csv")?;
+    if postcodes.is_empty() {
+        bail!("No postcodes found in assets/ukpostcodes.csv!");
+    }
+    info!("Read [{}] postcodes with coordinates.", postcodes.len());
+
+    // Every postcode in the country is too many writes for one transaction, so they're written
+    // to a staging collection which then replaces the old one in a single rename.
+    let staging = globals.db.postcodes_staging();
+    staging.drop(None).await?;
+    staging.insert_many(postcodes, None).await?;
+
+    // Postcodes are looked up by `_id`, which is already indexed, including for prefix matches.
+    // Indexes are kept by the rename.
+    staging
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"coordinates": "2d"})
+                .build(),
+            None,
+        )
+        .await?;
+
+    let database = globals.db.database.name();
+    globals
+        .db
+        .client
+        .database("admin")
+        .run_command(
+            doc! {
+                "renameCollection": format!("{}.{}", database, staging.name()),
+                "to": format!("{}.{}", database, globals.db.postcodes().name()),
+                "dropTarget": true,
+            },
+            None,
+        )
+        .await?;
+    globals
+        .db
+        .last_updated()
+        .find_one_and_update(
+            doc! {},
+            doc! {"$set": {"postcodes":  Utc::now().timestamp_millis() }},
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -1,28 +1,96 @@
 use crate::lib::{
+    geography::index::Geography,
+    postcode::PostcodeIndex,
     property::{
         aggregator::PropertyAggregator,
-        estate_agents::rightmove::Rightmove,
-        property::{PropertyAction, PropertySummary},
+        area::{AreaLevel, AreaListings},
+        assignment::{self, StationAssignment, StationOverlap},
+        catchment::Catchment,
+        discount::PriceDiscountSnapshot,
+        distance::DistanceRings,
+        enrichment::ListingEnricher,
+        epc::EpcIndex,
+        estate_agents::{
+            drift::DriftReport,
+            property_log::PropertyLog,
+            rightmove::{Rightmove, RightmoveProperty},
+        },
+        heat::MarketHeat,
+        imputation::SquareFeetImputer,
+        lifecycle::{self, ListingEvent, ListingSnapshot, SearchKey},
+        market_speed::{MarketSpeed, MARKET_SPEED_WINDOW_WEEKS, WEEK_MS},
+        property::{PropertyAction, PropertySummary, SearchCoverage},
+        rollup::StationSketch,
+        sold_price::{Sale, SoldPriceSummary},
     },
+    routing::{graph::PedestrianGraph, isochrone::walking_miles},
     tube::TubeStation,
-    util::ext::{MongoCollectionExt, VecResultExt},
-    util::globals::Globals,
+    util::ext::MongoCollectionExt,
+    util::{
+        error::{ScraperError, ScraperErrorAction},
+        globals::Globals,
+    },
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use chrono::Utc;
 use futures::{
     future::{join, join_all},
     TryFutureExt,
 };
 use itertools::{iproduct, Itertools};
-use log::info;
+use log::{info, warn};
 use mongodb::{bson::doc, options::FindOneAndUpdateOptions};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, mem,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::task;
 
 // Only consider Studio - 3 bedroom flats
 const MAX_BEDS: u32 = 3;
 
-// Only consider 0.25 miles radius from train stations
-const SEARCH_RADIUS: f64 = 0.25;
+// Only consider 0.25 miles radius from train stations, unless walking time is used instead
+const CATCHMENT_RADIUS: f64 = 0.25;
+
+// Compare asking prices with sales over this many months
+const DISCOUNT_SOLD_MONTHS: u32 = 12;
+
+fn distance_rings(globals: &Globals) -> DistanceRings {
+    let rings = globals.properties.get_string("property.distance.rings");
+    DistanceRings::parse(&rings)
+        .unwrap_or_else(|| panic!("[{}] are not valid distance rings!", rings))
+}
+
+fn is_walking_catchment(globals: &Globals) -> bool {
+    match globals.properties.get_string("property.catchment").as_str() {
+        "radius" => false,
+        "walking" => true,
+        catchment => panic!("[{}] is not a valid catchment!", catchment),
+    }
+}
+
+/// Crow-flies radius in miles that listings near a station are filtered to, or for a walking
+/// catchment the furthest it could reach.
+pub fn catchment_radius(globals: &Globals) -> f64 {
+    if is_walking_catchment(globals) {
+        walking_miles(
+            globals.properties.get_float("property.walking.minutes"),
+            globals.properties.get_float("routing.walking.speed.mph"),
+        )
+    } else {
+        CATCHMENT_RADIUS
+    }
+}
+
+/// Radius in miles that each station is searched within, covering its catchment and any
+/// distance rings.
+pub fn search_radius(globals: &Globals) -> f64 {
+    Rightmove::search_radius_covering(
+        catchment_radius(globals).max(distance_rings(globals).outer_radius()),
+    )
+}
 
 pub async fn update_property(globals: &Globals) -> Result<()> {
     #[derive(Clone)]
@@ -31,28 +99,118 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
         location_identifier: String,
     }
 
-    let rightmove = Rightmove::new(&globals);
-    let aggregator = PropertyAggregator {};
+    let rightmove = Rightmove::new(globals);
+    let station_assignment = {
+        let assignment = globals.properties.get_string("property.station.assignment");
+        StationAssignment::parse(&assignment)
+            .unwrap_or_else(|| panic!("[{}] is not a valid station assignment!", assignment))
+    };
+    let distance_rings = distance_rings(globals);
+    let area_levels = {
+        let levels = globals.properties.get_string("property.area.levels");
+        AreaLevel::parse_list(&levels)
+            .unwrap_or_else(|| panic!("[{}] are not valid area levels!", levels))
+    };
+    let aggregator = PropertyAggregator::new(globals)?;
+    let property_log = PropertyLog::new(globals);
+    let enricher = ListingEnricher::new(globals, &rightmove, &property_log).await;
+    let mut report = RunReport::default();
 
     let tube_stations: Vec<TubeStation> = globals.db.tube().find_to_vec().await;
-    let station_infos: Vec<StationInfo> = join_all(tube_stations.into_iter().map(|station| {
+    let station_postcodes: HashSet<String> = tube_stations
+        .iter()
+        .map(|station| station.postcode.clone())
+        .collect();
+    let catchment = if is_walking_catchment(globals) {
+        let path = globals.properties.get_string("routing.osm.path");
+        let minutes = globals.properties.get_float("property.walking.minutes");
+        let speed_mph = globals.properties.get_float("routing.walking.speed.mph");
+        let stations = tube_stations.clone();
+        // Reading the network and walking from every station would otherwise stall the runtime.
+        task::spawn_blocking(move || -> Result<Catchment> {
+            let graph = PedestrianGraph::load(Path::new(&path))?;
+            info!(
+                "Routing walks over [{}] street network nodes.",
+                graph.num_nodes()
+            );
+            Ok(Catchment::walking(graph, &stations, minutes, speed_mph))
+        })
+        .await??
+    } else {
+        Catchment::Radius(CATCHMENT_RADIUS)
+    };
+    let station_info_results = join_all(tube_stations.into_iter().map(|station| {
+        let name = station.name.clone();
         rightmove
             .get_location_identifier(station.postcode.to_owned())
             .map_ok(|location_identifier| StationInfo {
                 station,
                 location_identifier,
             })
+            .map_err(|err| (name, err))
     }))
-    .await
-    .unwrap_all();
+    .await;
+    let station_infos = station_info_results
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(station_info) => Some(station_info),
+            Err((name, err)) => {
+                report.record_failure(format!("Station [{}] location identifier", name), err);
+                None
+            }
+        })
+        .collect_vec();
+    if report.is_aborted() {
+        bail!("Aborted property update!\n{}", report);
+    }
+
+    // Once blocked, every further request would be blocked too, so stop issuing them.
+    let blocked = AtomicBool::new(false);
+
+    struct BuyAndRentProperties {
+        station_info: StationInfo,
+        num_beds: u32,
+        buy_properties: Vec<RightmoveProperty>,
+        rent_properties: Vec<RightmoveProperty>,
+        buy_coverage: SearchCoverage,
+        rent_coverage: SearchCoverage,
+        // Outside the catchment, only searched for the outer distance rings.
+        buy_outer_properties: Vec<RightmoveProperty>,
+        rent_outer_properties: Vec<RightmoveProperty>,
+    }
+
+    impl BuyAndRentProperties {
+        fn properties_mut(&mut self, action: PropertyAction) -> &mut Vec<RightmoveProperty> {
+            match action {
+                PropertyAction::Buy => &mut self.buy_properties,
+                PropertyAction::Rent => &mut self.rent_properties,
+            }
+        }
+    }
 
-    async fn get_buy_and_rent_property_summary(
+    async fn get_buy_and_rent_properties(
         rightmove: &Rightmove,
-        aggregator: &PropertyAggregator,
+        enricher: &ListingEnricher<'_>,
+        blocked: &AtomicBool,
+        catchment: &Catchment,
         station_info: StationInfo,
         num_beds: u32,
         radius: f64,
-    ) -> BuyAndRentPropertySummary {
+    ) -> Result<BuyAndRentProperties, (String, ScraperError)> {
+        let description = format!(
+            "Station [{}] num beds [{}]",
+            station_info.station.name, num_beds
+        );
+        if blocked.load(Ordering::Relaxed) {
+            return Err((
+                description,
+                ScraperError::Transient {
+                    url: None,
+                    status_code: None,
+                    message: "Not attempted after being blocked".to_owned(),
+                },
+            ));
+        }
         let (buy_properties_result, rent_properties_result) = join(
             rightmove.search(
                 station_info.location_identifier.clone(),
@@ -68,17 +226,126 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
             ),
         )
         .await;
-        let buy_properties = buy_properties_result.unwrap();
-        let rent_properties = rent_properties_result.unwrap();
+        let (buy_result, rent_result) = match (buy_properties_result, rent_properties_result) {
+            (Ok(buy_result), Ok(rent_result)) => (buy_result, rent_result),
+            (Err(err), _) | (_, Err(err)) => {
+                if err.action() == ScraperErrorAction::Abort {
+                    blocked.store(true, Ordering::Relaxed);
+                }
+                return Err((description, err));
+            }
+        };
+        let buy_coverage = buy_result.coverage;
+        let rent_coverage = rent_result.coverage;
 
-        let buy_and_rent_property_stats =
-            aggregator.calculate_buy_and_rent_property_stats(buy_properties, rent_properties);
+        let (buy_properties, rent_properties) = match join(
+            enricher.enrich(buy_result.properties),
+            enricher.enrich(rent_result.properties),
+        )
+        .await
+        {
+            (Ok(buy_properties), Ok(rent_properties)) => (buy_properties, rent_properties),
+            (Err(err), _) | (_, Err(err)) => {
+                blocked.store(true, Ordering::Relaxed);
+                return Err((description, err));
+            }
+        };
 
-        info!("Got property stats for station: [{:?}] postcode: [{:?}]  num beds: [{:?}] radius: [{:?}]",
+        info!("Got properties for station: [{:?}] postcode: [{:?}]  num beds: [{:?}] radius: [{:?}] buy coverage: [{:?}] rent coverage: [{:?}]",
                 station_info.station.name,
                 station_info.station.postcode,
-                 num_beds, radius
+                 num_beds, radius,
+                 buy_coverage, rent_coverage
             );
+        for (action, coverage) in [
+            (PropertyAction::Buy, buy_coverage),
+            (PropertyAction::Rent, rent_coverage),
+        ] {
+            if !coverage.is_complete() {
+                warn!(
+                    "Incomplete coverage for station: [{:?}] action: [{:?}] num beds: [{:?}] - retrieved [{}/{}] listings ({:.1}%) in [{}] queries, [{}] still truncated",
+                    station_info.station.name,
+                    action,
+                    num_beds,
+                    coverage.retrieved,
+                    coverage.result_count,
+                    coverage.ratio() * 100.0,
+                    coverage.num_queries,
+                    coverage.num_truncated
+                );
+            }
+        }
+        // Only listings within the catchment count towards everything but the distance rings.
+        // Rightmove's radius isn't exactly crow-flies, so filter even when the search radius
+        // matches the catchment, to count the same listings whatever radius the rings need.
+        let split = |properties: Vec<RightmoveProperty>| -> (Vec<_>, Vec<_>) {
+            properties
+                .into_iter()
+                .partition(|p| catchment.contains(&station_info.station, p))
+        };
+        let (buy_properties, buy_outer_properties) = split(buy_properties);
+        let (rent_properties, rent_outer_properties) = split(rent_properties);
+        Ok(BuyAndRentProperties {
+            station_info,
+            num_beds,
+            buy_properties,
+            rent_properties,
+            buy_coverage,
+            rent_coverage,
+            buy_outer_properties,
+            rent_outer_properties,
+        })
+    }
+
+    fn get_buy_and_rent_property_summary(
+        aggregator: &PropertyAggregator,
+        imputer: &SquareFeetImputer,
+        market_speeds: &HashMap<SearchKey, MarketSpeed>,
+        distance_rings: &DistanceRings,
+        properties: BuyAndRentProperties,
+    ) -> BuyAndRentPropertySummary {
+        let BuyAndRentProperties {
+            station_info,
+            num_beds,
+            mut buy_properties,
+            mut rent_properties,
+            buy_coverage,
+            rent_coverage,
+            mut buy_outer_properties,
+            mut rent_outer_properties,
+        } = properties;
+        let postcode = &station_info.station.postcode;
+        imputer.impute(postcode, num_beds, &mut buy_properties);
+        imputer.impute(postcode, num_beds, &mut rent_properties);
+        imputer.impute(postcode, num_beds, &mut buy_outer_properties);
+        imputer.impute(postcode, num_beds, &mut rent_outer_properties);
+
+        buy_outer_properties.extend(buy_properties.iter().cloned());
+        rent_outer_properties.extend(rent_properties.iter().cloned());
+        let (buy_stats_by_distance, rent_stats_by_distance) = aggregator
+            .calculate_buy_and_rent_ring_stats(
+                distance_rings,
+                station_info.station.coordinates,
+                &buy_outer_properties,
+                &rent_outer_properties,
+            );
+
+        let sketch = |action: PropertyAction, properties: &Vec<RightmoveProperty>| StationSketch {
+            postcode: postcode.clone(),
+            action: action as u8,
+            num_beds,
+            sketch: aggregator.calculate_sketch(properties.clone()),
+        };
+        let buy_sketch = sketch(PropertyAction::Buy, &buy_properties);
+        let rent_sketch = sketch(PropertyAction::Rent, &rent_properties);
+        let buy_and_rent_property_stats =
+            aggregator.calculate_buy_and_rent_property_stats(buy_properties, rent_properties);
+        let market_speed = |action: PropertyAction| {
+            market_speeds
+                .get(&(postcode.clone(), action as u8, num_beds))
+                .cloned()
+                .unwrap_or_else(MarketSpeed::empty)
+        };
         BuyAndRentPropertySummary {
             buy_summary: PropertySummary {
                 postcode: station_info.station.postcode.clone(),
@@ -86,6 +353,10 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
                 action: PropertyAction::Buy as u8,
                 num_beds,
                 stats: buy_and_rent_property_stats.buy_stats,
+                stats_by_type: buy_and_rent_property_stats.buy_stats_by_type,
+                coverage: buy_coverage,
+                market_speed: market_speed(PropertyAction::Buy),
+                stats_by_distance: buy_stats_by_distance,
             },
             rent_summary: PropertySummary {
                 postcode: station_info.station.postcode.clone(),
@@ -93,16 +364,138 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
                 action: PropertyAction::Rent as u8,
                 num_beds,
                 stats: buy_and_rent_property_stats.rent_stats,
+                stats_by_type: buy_and_rent_property_stats.rent_stats_by_type,
+                coverage: rent_coverage,
+                market_speed: market_speed(PropertyAction::Rent),
+                stats_by_distance: rent_stats_by_distance,
             },
+            buy_sketch,
+            rent_sketch,
         }
     }
 
-    let all_buy_and_rent_property_summary = join_all(
-        iproduct!(station_infos, 0..(MAX_BEDS + 1), [SEARCH_RADIUS]).map(
+    // Sold prices don't record the number of beds, so compare with asking prices across all beds,
+    // but only against sales of the same property type.
+    fn get_price_discount_snapshots(
+        aggregator: &PropertyAggregator,
+        sold_prices: &[SoldPriceSummary],
+        all_buy_and_rent_properties: &[BuyAndRentProperties],
+    ) -> Vec<PriceDiscountSnapshot> {
+        let date = Utc::now().timestamp_millis();
+        all_buy_and_rent_properties
+            .iter()
+            .into_group_map_by(|p| p.station_info.station.postcode.clone())
+            .into_iter()
+            .filter_map(|(postcode, station_properties)| {
+                let sold = sold_prices
+                    .iter()
+                    .find(|s| s.postcode == postcode && s.months == DISCOUNT_SOLD_MONTHS)?;
+                let coordinates = station_properties[0].station_info.station.coordinates;
+                let buy_properties_by_type = station_properties
+                    .iter()
+                    .flat_map(|p| p.buy_properties.iter())
+                    .filter_map(|p| {
+                        Sale::comparable_property_type(p.property_type).map(|t| (t, p.clone()))
+                    })
+                    .into_group_map();
+                let snapshots = buy_properties_by_type
+                    .into_iter()
+                    .filter_map(|(property_type, properties)| {
+                        PriceDiscountSnapshot::new(
+                            date,
+                            &postcode,
+                            coordinates,
+                            Some(property_type),
+                            aggregator.calculate_asking_price_stats(properties),
+                            sold.stats_by_type.get(&property_type)?.clone(),
+                        )
+                    })
+                    .collect_vec();
+                Some(snapshots)
+            })
+            .flatten()
+            .collect()
+    }
+
+    // Only searches that retrieved every listing can tell us which listings were withdrawn.
+    fn diff_listings(
+        previous: Vec<ListingSnapshot>,
+        all_buy_and_rent_properties: &[BuyAndRentProperties],
+        now: i64,
+    ) -> (Vec<ListingSnapshot>, Vec<ListingEvent>) {
+        let mut current = vec![];
+        let mut complete_searches = HashSet::new();
+        for p in all_buy_and_rent_properties {
+            let postcode = &p.station_info.station.postcode;
+            for (action, properties, coverage) in [
+                (PropertyAction::Buy, &p.buy_properties, p.buy_coverage),
+                (PropertyAction::Rent, &p.rent_properties, p.rent_coverage),
+            ] {
+                let action = action as u8;
+                if coverage.is_complete() {
+                    complete_searches.insert((postcode.clone(), action, p.num_beds));
+                }
+                current.extend(properties.iter().map(|property| {
+                    ListingSnapshot::new(postcode, action, p.num_beds, property, now)
+                }));
+            }
+        }
+        lifecycle::diff(previous, current, &complete_searches, now)
+    }
+
+    // Searches for nearby stations overlap, so measure by how much, and optionally count each
+    // listing only towards its nearest station. Search coverage still describes the searches.
+    fn assign_listings(
+        station_assignment: StationAssignment,
+        all_buy_and_rent_properties: &mut [BuyAndRentProperties],
+    ) -> Vec<StationOverlap> {
+        let mut overlaps = vec![];
+        for (num_beds, mut group) in all_buy_and_rent_properties
+            .iter_mut()
+            .into_group_map_by(|p| p.num_beds)
+        {
+            let postcodes = group
+                .iter()
+                .map(|p| p.station_info.station.postcode.clone())
+                .collect_vec();
+            let coordinates = group
+                .iter()
+                .map(|p| p.station_info.station.coordinates)
+                .collect_vec();
+            for action in [PropertyAction::Buy, PropertyAction::Rent] {
+                let listings = group
+                    .iter_mut()
+                    .map(|p| mem::take(p.properties_mut(action)))
+                    .collect_vec();
+                overlaps.extend(assignment::overlaps(
+                    action as u8,
+                    num_beds,
+                    &postcodes,
+                    &listings,
+                ));
+                let listings = match station_assignment {
+                    StationAssignment::Radius => listings,
+                    StationAssignment::Nearest => {
+                        assignment::assign_to_nearest(&coordinates, listings)
+                    }
+                };
+                for (p, listings) in group.iter_mut().zip(listings) {
+                    *p.properties_mut(action) = listings;
+                }
+            }
+        }
+        overlaps
+    }
+
+    let search_radius = search_radius(globals);
+    let all_buy_and_rent_properties_results = join_all(
+        iproduct!(station_infos, 0..(MAX_BEDS + 1), [search_radius]).map(
             |(station_info, num_beds, radius)| {
-                get_buy_and_rent_property_summary(
+                get_buy_and_rent_properties(
                     &rightmove,
-                    &aggregator,
+                    &enricher,
+                    &blocked,
+                    &catchment,
                     station_info,
                     num_beds,
                     radius,
@@ -111,10 +504,251 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
         ),
     )
     .await;
-    let all_property_summary = all_buy_and_rent_property_summary
+    let mut all_buy_and_rent_properties = all_buy_and_rent_properties_results
         .into_iter()
-        .flat_map(|s| vec![s.buy_summary, s.rent_summary].into_iter())
+        .filter_map(|result| match result {
+            Ok(properties) => Some(properties),
+            Err((description, err)) => {
+                report.record_failure(description, err);
+                None
+            }
+        })
         .collect_vec();
+    if report.is_aborted() {
+        bail!("Aborted property update!\n{}", report);
+    }
+
+    // Assign listings to boroughs, wards and LSOAs before they're recorded as snapshots.
+    let geography = Geography::load(&globals.db).await;
+    for properties in all_buy_and_rent_properties.iter_mut() {
+        geography.fill(&mut properties.buy_properties);
+        geography.fill(&mut properties.rent_properties);
+        geography.fill(&mut properties.buy_outer_properties);
+        geography.fill(&mut properties.rent_outer_properties);
+    }
+
+    let previous_listing_snapshots = globals.db.listing_snapshots().find_to_vec().await;
+    let now = Utc::now().timestamp_millis();
+    let tracking_start = previous_listing_snapshots
+        .iter()
+        .map(|snapshot| snapshot.first_seen)
+        .min()
+        .unwrap_or(now);
+    let is_first_run = previous_listing_snapshots.is_empty();
+    let (listing_snapshots, listing_events) = diff_listings(
+        previous_listing_snapshots,
+        &all_buy_and_rent_properties,
+        now,
+    );
+    if is_first_run {
+        info!(
+            "Recorded [{}] listings as the baseline for future listing events.",
+            listing_snapshots.len()
+        );
+    } else {
+        info!(
+            "Recorded [{}] listing events since the last run.",
+            listing_events.len()
+        );
+    }
+
+    let market_speed_window_start = (now - MARKET_SPEED_WINDOW_WEEKS * WEEK_MS).max(tracking_start);
+    let recent_listing_events = globals
+        .db
+        .listing_events()
+        .find_filtered_to_vec(doc! {"date": {"$gte": market_speed_window_start}}, None)
+        .await
+        .into_iter()
+        .chain(listing_events.iter().cloned())
+        .collect_vec();
+    let market_speeds = MarketSpeed::by_search(
+        &recent_listing_events,
+        &listing_snapshots,
+        market_speed_window_start,
+        now,
+    );
+
+    // Listing lifecycle follows the searches, so only assign listings to stations after diffing.
+    let station_overlaps = assign_listings(station_assignment, &mut all_buy_and_rent_properties);
+    info!(
+        "Found [{}] overlapping station searches sharing [{}] listings, assigning by [{:?}].",
+        station_overlaps.len(),
+        station_overlaps.iter().map(|o| o.num_shared).sum::<usize>(),
+        station_assignment
+    );
+
+    // Fill in floor area from the EPC register before estimating what's still missing.
+    let epc_index = EpcIndex::new(globals.db.epc().find_to_vec().await);
+    info!(
+        "Matching listings against [{}] EPC certificates.",
+        epc_index.num_certificates()
+    );
+    for properties in all_buy_and_rent_properties.iter_mut() {
+        epc_index.fill(&mut properties.buy_properties);
+        epc_index.fill(&mut properties.rent_properties);
+        epc_index.fill(&mut properties.buy_outer_properties);
+        epc_index.fill(&mut properties.rent_outer_properties);
+    }
+
+    // Square footage is estimated from listings across all stations, so fit once everything is in.
+    let imputer = SquareFeetImputer::fit(all_buy_and_rent_properties.iter().flat_map(|p| {
+        p.buy_properties
+            .iter()
+            .chain(p.rent_properties.iter())
+            .map(|property| {
+                (
+                    p.station_info.station.postcode.as_str(),
+                    p.num_beds,
+                    property,
+                )
+            })
+    }));
+
+    // Listings are only found by searching around stations, but can also be grouped by the
+    // borough or postcode area they're in, including those outside the catchment.
+    let mut area_listings = AreaListings::default();
+    for station in all_buy_and_rent_properties
+        .iter()
+        .map(|p| &p.station_info.station)
+        .unique_by(|station| &station.postcode)
+    {
+        area_listings.add_search(station.coordinates, search_radius);
+    }
+    for p in &all_buy_and_rent_properties {
+        for (action, properties, outer_properties) in [
+            (
+                PropertyAction::Buy,
+                &p.buy_properties,
+                &p.buy_outer_properties,
+            ),
+            (
+                PropertyAction::Rent,
+                &p.rent_properties,
+                &p.rent_outer_properties,
+            ),
+        ] {
+            let mut properties = properties
+                .iter()
+                .chain(outer_properties.iter())
+                .cloned()
+                .collect_vec();
+            imputer.impute(
+                &p.station_info.station.postcode,
+                p.num_beds,
+                &mut properties,
+            );
+            area_listings.add(action, p.num_beds, properties);
+        }
+    }
+    let borough_property_summaries =
+        area_listings.summaries(&aggregator, |point| geography.boroughs.locate_code(point));
+    let area_property_summaries = match area_listings.bounds() {
+        Some(bounds) if !area_levels.is_empty() => {
+            let postcode_index = PostcodeIndex::load(&globals.db, bounds).await?;
+            info!(
+                "Grouping [{}] listings by area among [{}] postcodes.",
+                area_listings.num_listings(),
+                postcode_index.num_postcodes()
+            );
+            area_levels
+                .iter()
+                .map(|&level| {
+                    (
+                        level,
+                        area_listings.summaries(
+                            &aggregator,
+                            AreaListings::postcode_area(&postcode_index, level),
+                        ),
+                    )
+                })
+                .collect_vec()
+        }
+        _ => vec![],
+    };
+
+    let sold_prices = globals.db.sold_prices().find_to_vec().await;
+    let price_discount_snapshots =
+        get_price_discount_snapshots(&aggregator, &sold_prices, &all_buy_and_rent_properties);
+    let (mut all_property_summary, mut all_property_sketches): (Vec<_>, Vec<_>) =
+        all_buy_and_rent_properties
+            .into_iter()
+            .map(|properties| {
+                get_buy_and_rent_property_summary(
+                    &aggregator,
+                    &imputer,
+                    &market_speeds,
+                    &distance_rings,
+                    properties,
+                )
+            })
+            .flat_map(|s| {
+                [
+                    (s.buy_summary, s.buy_sketch),
+                    (s.rent_summary, s.rent_sketch),
+                ]
+            })
+            .unzip();
+    let market_heats = MarketHeat::from_summaries(now, &all_property_summary);
+
+    // Stations whose searches were skipped keep the previous run's summaries and sketches, so
+    // that a transient failure doesn't drop them until the next run.
+    fn carry_over<T>(
+        previous: Vec<T>,
+        current: &mut Vec<T>,
+        station_postcodes: &HashSet<String>,
+        key: impl Fn(&T) -> (String, u8, u32),
+    ) -> usize {
+        let current_keys: HashSet<_> = current.iter().map(&key).collect();
+        let kept = previous
+            .into_iter()
+            .filter(|p| {
+                let key = key(p);
+                station_postcodes.contains(&key.0) && !current_keys.contains(&key)
+            })
+            .collect_vec();
+        let num_kept = kept.len();
+        current.extend(kept);
+        num_kept
+    }
+    if !report.skipped.is_empty() {
+        report.num_carried_over = carry_over(
+            globals.db.property().find_to_vec().await,
+            &mut all_property_summary,
+            &station_postcodes,
+            |s| (s.postcode.clone(), s.action, s.num_beds),
+        );
+        carry_over(
+            globals.db.property_sketches().find_to_vec().await,
+            &mut all_property_sketches,
+            &station_postcodes,
+            |s| (s.postcode.clone(), s.action, s.num_beds),
+        );
+    }
+    info!("Property update report:\n{}", report);
+
+    let drift_report = DriftReport::merge(vec![
+        rightmove.drift().report(),
+        property_log.drift().report(),
+    ]);
+    if !drift_report.is_empty() {
+        warn!(
+            "Found [{}] kinds of schema drift in estate agent responses:\n{}",
+            drift_report.findings.len(),
+            drift_report.describe_findings()
+        );
+        globals
+            .db
+            .schema_drift()
+            .insert_one(&drift_report, None)
+            .await?;
+    }
+    drift_report.check(
+        globals
+            .properties
+            .get_float("drift.max.affected.proportion"),
+    )?;
+
+    let listing_details = enricher.into_seen();
 
     let mut session = globals.db.client.start_session(None).await?;
     session.start_transaction(None).await?;
@@ -129,6 +763,101 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
         .property()
         .insert_many_with_session(all_property_summary, None, &mut session)
         .await?;
+    globals
+        .db
+        .property_sketches()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    globals
+        .db
+        .property_sketches()
+        .insert_many_with_session(all_property_sketches, None, &mut session)
+        .await?;
+    globals
+        .db
+        .borough_property()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    if !borough_property_summaries.is_empty() {
+        globals
+            .db
+            .borough_property()
+            .insert_many_with_session(borough_property_summaries, None, &mut session)
+            .await?;
+    }
+    for (level, summaries) in area_property_summaries {
+        globals
+            .db
+            .area_property(level)
+            .delete_many_with_session(doc! {}, None, &mut session)
+            .await?;
+        if !summaries.is_empty() {
+            globals
+                .db
+                .area_property(level)
+                .insert_many_with_session(summaries, None, &mut session)
+                .await?;
+        }
+    }
+    globals
+        .db
+        .station_overlap()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    if !station_overlaps.is_empty() {
+        globals
+            .db
+            .station_overlap()
+            .insert_many_with_session(station_overlaps, None, &mut session)
+            .await?;
+    }
+    globals
+        .db
+        .listing_details()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    if !listing_details.is_empty() {
+        globals
+            .db
+            .listing_details()
+            .insert_many_with_session(listing_details, None, &mut session)
+            .await?;
+    }
+    globals
+        .db
+        .listing_snapshots()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    if !listing_snapshots.is_empty() {
+        globals
+            .db
+            .listing_snapshots()
+            .insert_many_with_session(listing_snapshots, None, &mut session)
+            .await?;
+    }
+    if !listing_events.is_empty() {
+        globals
+            .db
+            .listing_events()
+            .insert_many_with_session(listing_events, None, &mut session)
+            .await?;
+    }
+    // Heat is kept to show how it changes over time.
+    if !market_heats.is_empty() {
+        globals
+            .db
+            .market_heat()
+            .insert_many_with_session(market_heats, None, &mut session)
+            .await?;
+    }
+    // Discount snapshots are kept to show how the asking gap trends over time.
+    if !price_discount_snapshots.is_empty() {
+        globals
+            .db
+            .price_discounts()
+            .insert_many_with_session(price_discount_snapshots, None, &mut session)
+            .await?;
+    }
     globals
         .db
         .last_updated()
@@ -147,4 +876,57 @@ pub async fn update_property(globals: &Globals) -> Result<()> {
 struct BuyAndRentPropertySummary {
     buy_summary: PropertySummary,
     rent_summary: PropertySummary,
+    buy_sketch: StationSketch,
+    rent_sketch: StationSketch,
+}
+
+/// Summary of scraping failures during a run, grouped by what was done about them.
+#[derive(Default)]
+struct RunReport {
+    skipped: Vec<(String, ScraperError)>,
+    aborted: Option<(String, ScraperError)>,
+    num_carried_over: usize, // previous summaries kept for skipped searches
+}
+
+impl RunReport {
+    fn record_failure(&mut self, description: String, err: ScraperError) {
+        match err.action() {
+            ScraperErrorAction::Abort if self.aborted.is_none() => {
+                warn!("Aborting after [{}] failed: {}", description, err);
+                self.aborted = Some((description, err));
+            }
+            _ => {
+                warn!("Skipping [{}]: {}", description, err);
+                self.skipped.push((description, err));
+            }
+        }
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.aborted.is_some()
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((description, err)) = &self.aborted {
+            writeln!(f, "Aborted at [{}]: {}", description, err)?;
+        }
+        writeln!(
+            f,
+            "Skipped [{}] searches, keeping [{}] summaries from the previous run.",
+            self.skipped.len(),
+            self.num_carried_over
+        )?;
+        for (description, err) in &self.skipped {
+            let summary = err.to_string();
+            writeln!(
+                f,
+                "- [{}]: {}",
+                description,
+                summary.lines().next().unwrap_or("")
+            )?;
+        }
+        Ok(())
+    }
 }
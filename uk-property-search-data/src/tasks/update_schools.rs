@@ -1,4 +1,6 @@
 use crate::lib::{
+    geography::index::Geography,
+    postcode::PostcodeLookup,
     school::{Rating, School},
     util::globals::Globals,
 };
@@ -11,16 +13,16 @@ use polars::{io::SerReader, prelude::CsvReader};
 pub async fn update_schools(globals: &Globals) -> Result<()> {
     let schools_df =
         CsvReader::from_path("assets/2020-2021_england_school_information.csv")?.finish()?;
-    let postcodes_df = CsvReader::from_path("assets/ukpostcodes.csv")?.finish()?;
-    let merged_df = schools_df.inner_join(&postcodes_df, ["POSTCODE"], ["pcds"])?;
 
-    let ids = merged_df.column("URN")?.i64()?;
-    let names = merged_df.column("SCHNAME")?.utf8()?;
-    let postcodes = merged_df.column("POSTCODE")?.utf8()?;
-    let longitudes = merged_df.column("long")?.f64()?;
-    let latitudes = merged_df.column("lat")?.f64()?;
-    let rating_strings = merged_df.column("OFSTEDRATING")?.utf8()?;
-    let inspection_dates = merged_df.column("OFSTEDLASTINSP")?.utf8()?;
+    let ids = schools_df.column("URN")?.i64()?;
+    let names = schools_df.column("SCHNAME")?.utf8()?;
+    let postcodes = schools_df.column("POSTCODE")?.utf8()?;
+    let rating_strings = schools_df.column("OFSTEDRATING")?.utf8()?;
+    let inspection_dates = schools_df.column("OFSTEDLASTINSP")?.utf8()?;
+
+    let postcode_lookup =
+        PostcodeLookup::load(&globals.db, postcodes.into_iter().flatten()).await?;
+    let geography = Geography::load(&globals.db).await;
 
     fn parse_rating_string(rating_string: Option<&str>) -> Rating {
         rating_string.map_or(Rating::Unknown, |s| match s {
@@ -42,26 +44,24 @@ pub async fn update_schools(globals: &Globals) -> Result<()> {
         })
     }
 
-    let schools: Vec<School> = multizip((
-        ids,
-        names,
-        postcodes,
-        longitudes,
-        latitudes,
-        rating_strings,
-        inspection_dates,
-    ))
-    .map(
-        |(id, name, postcode, longitude, latitude, rating_string, inspection_date)| School {
-            id: id.unwrap(),
-            name: name.unwrap().to_owned(),
-            postcode: postcode.unwrap().to_owned(),
-            coordinates: (longitude.unwrap(), latitude.unwrap()),
-            rating: parse_rating_string(rating_string) as u8,
-            inspection_date_ms: parse_inspection_date(inspection_date),
-        },
-    )
-    .collect();
+    // Schools whose postcode can't be geocoded are left out.
+    let schools: Vec<School> = multizip((ids, names, postcodes, rating_strings, inspection_dates))
+        .filter_map(|(id, name, postcode, rating_string, inspection_date)| {
+            let postcode = postcode?;
+            let coordinates = postcode_lookup.coordinates(postcode)?;
+            Some(School {
+                id: id.unwrap(),
+                name: name.unwrap().to_owned(),
+                postcode: postcode.to_owned(),
+                coordinates,
+                rating: parse_rating_string(rating_string) as u8,
+                inspection_date_ms: parse_inspection_date(inspection_date),
+                borough: geography.boroughs.locate_code(coordinates),
+                ward: geography.wards.locate_code(coordinates),
+                lsoa: geography.lsoas.locate_code(coordinates),
+            })
+        })
+        .collect();
 
     let mut session = globals.db.client.start_session(None).await?;
     session.start_transaction(None).await?;
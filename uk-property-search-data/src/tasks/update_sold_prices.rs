@@ -0,0 +1,127 @@
+use super::update_property::catchment_radius;
+use crate::lib::{
+    postcode::PostcodeLookup,
+    property::sold_price::{Sale, SaleIndex, SoldPriceSummary},
+    tube::TubeStation,
+    util::{ext::MongoCollectionExt, globals::Globals},
+};
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, Utc};
+use itertools::{iproduct, multizip, Itertools};
+use log::info;
+use mongodb::{bson::doc, options::FindOneAndUpdateOptions};
+use polars::{
+    io::SerReader,
+    prelude::{CsvReader, DataFrame},
+};
+use std::fs;
+
+// Summarise sales over the last 12 and 24 months of the data.
+const PERIOD_MONTHS: [u32; 2] = [12, 24];
+
+pub async fn update_sold_prices(globals: &Globals) -> Result<()> {
+    // Price Paid files have no header, so only read the columns we need by position.
+    fn read_price_paid(path: &str) -> Result<DataFrame> {
+        let mut df = CsvReader::from_path(path)?
+            .has_header(false)
+            .with_projection(Some(vec![1, 2, 3, 4, 14]))
+            .finish()?;
+        df.set_column_names(&["price", "date", "postcode", "property_type", "category"])?;
+        Ok(df)
+    }
+
+    let price_paid_paths = fs::read_dir("assets")?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .filter(|path| {
+            let file_name = path.rsplit('/').next().unwrap_or_default();
+            file_name.starts_with("pp-") && file_name.ends_with(".csv")
+        })
+        .sorted()
+        .collect_vec();
+    if price_paid_paths.is_empty() {
+        bail!("No Price Paid files (assets/pp-*.csv) found!");
+    }
+    info!("Reading Price Paid files: [{:?}]", price_paid_paths);
+
+    let mut price_paid_df = read_price_paid(&price_paid_paths[0])?;
+    for path in &price_paid_paths[1..] {
+        price_paid_df.vstack_mut(&read_price_paid(path)?)?;
+    }
+
+    let prices = price_paid_df.column("price")?.i64()?;
+    let dates = price_paid_df.column("date")?.utf8()?;
+    let property_types = price_paid_df.column("property_type")?.utf8()?;
+    let categories = price_paid_df.column("category")?.utf8()?;
+    let postcodes = price_paid_df.column("postcode")?.utf8()?;
+    let postcode_lookup =
+        PostcodeLookup::load(&globals.db, postcodes.into_iter().flatten()).await?;
+
+    // Category B covers repossessions, buy-to-let and transfers to companies, which are not
+    // representative of market prices.
+    let sales = SaleIndex::new(
+        multizip((prices, dates, property_types, categories, postcodes))
+            .filter(|(_, _, _, category, _)| *category == Some("A"))
+            .filter_map(|(price, date, property_type, _, postcode)| {
+                Some(Sale {
+                    coordinates: postcode_lookup.coordinates(postcode?)?,
+                    price: price?.try_into().ok()?,
+                    date: NaiveDate::parse_from_str(date?.get(..10)?, "%Y-%m-%d").ok()?,
+                    property_type: Sale::parse_property_type(property_type?),
+                })
+            })
+            .collect_vec(),
+    );
+    let period_end = match sales.latest_date() {
+        Some(date) => date,
+        None => bail!("No sales found in Price Paid files!"),
+    };
+    info!(
+        "Read [{}] sales up to [{}] from Price Paid files.",
+        sales.num_sales(),
+        period_end
+    );
+
+    let tube_stations: Vec<TubeStation> = globals.db.tube().find_to_vec().await;
+    // Sales over the same catchment as the asking prices they're compared with.
+    let catchment_radius = catchment_radius(globals);
+    let sold_price_summaries = iproduct!(tube_stations.iter(), PERIOD_MONTHS)
+        .map(|(station, months)| {
+            SoldPriceSummary::from_sales(
+                &station.postcode,
+                station.coordinates,
+                catchment_radius,
+                months,
+                period_end,
+                &sales,
+            )
+        })
+        .collect_vec();
+
+    let mut session = globals.db.client.start_session(None).await?;
+    session.start_transaction(None).await?;
+
+    globals
+        .db
+        .sold_prices()
+        .delete_many_with_session(doc! {}, None, &mut session)
+        .await?;
+    globals
+        .db
+        .sold_prices()
+        .insert_many_with_session(sold_price_summaries, None, &mut session)
+        .await?;
+    globals
+        .db
+        .last_updated()
+        .find_one_and_update_with_session(
+            doc! {},
+            doc! {"$set": {"soldPrices":  Utc::now().timestamp_millis() }},
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+            &mut session,
+        )
+        .await?;
+    session.commit_transaction().await?;
+
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use std::collections::HashSet;
 
-use crate::lib::{tube::TubeStation, util::globals::Globals};
+use crate::lib::{
+    geography::index::Geography, postcode::PostcodeLookup, tube::TubeStation,
+    util::globals::Globals,
+};
 use anyhow::Result;
 use chrono::Utc;
 use itertools::{multizip, Itertools};
@@ -17,6 +20,17 @@ pub async fn update_tube(globals: &Globals) -> Result<()> {
     let zones = stations_df.column("Zone")?.utf8()?;
     let postcodes = stations_df.column("Postcode")?.utf8()?;
 
+    // Stations without coordinates in the csv are placed at their postcode instead, so the
+    // postcodes collection is only needed if there are any.
+    let postcode_lookup = PostcodeLookup::load(
+        &globals.db,
+        multizip((latitudes, longitudes, postcodes))
+            .filter(|(latitude, longitude, _)| latitude.is_none() || longitude.is_none())
+            .filter_map(|(_, _, postcode)| postcode),
+    )
+    .await?;
+    let geography = Geography::load(&globals.db).await;
+
     let lines = lines_df.column("Tube Line")?.utf8()?;
     let from_stations = lines_df.column("From Station")?.utf8()?;
     let to_stations = lines_df.column("To Station")?.utf8()?;
@@ -34,8 +48,15 @@ pub async fn update_tube(globals: &Globals) -> Result<()> {
 
     let tube_stations: Vec<TubeStation> =
         multizip((stations, latitudes, longitudes, zones, postcodes))
-            .map(
-                |(station, latitude, longitude, zone, postcode)| TubeStation {
+            .map(|(station, latitude, longitude, zone, postcode)| {
+                let coordinates = longitude.zip(latitude).unwrap_or_else(|| {
+                    postcode_lookup
+                        .coordinates(postcode.unwrap())
+                        .unwrap_or_else(|| {
+                            panic!("Station [{}] has no coordinates!", station.unwrap())
+                        })
+                });
+                TubeStation {
                     name: station.unwrap().to_owned(),
                     zone: zone
                         .unwrap()
@@ -43,7 +64,7 @@ pub async fn update_tube(globals: &Globals) -> Result<()> {
                         .filter_map(|z| z.parse::<u8>().ok())
                         .collect_vec(),
                     postcode: postcode.unwrap().to_owned(),
-                    coordinates: (longitude.unwrap(), latitude.unwrap()),
+                    coordinates,
                     lines: station_lines_lookup
                         .get(station.unwrap())
                         .expect(&format!(
@@ -51,8 +72,11 @@ pub async fn update_tube(globals: &Globals) -> Result<()> {
                             station.unwrap()
                         ))
                         .to_owned(),
-                },
-            )
+                    borough: geography.boroughs.locate_code(coordinates),
+                    ward: geography.wards.locate_code(coordinates),
+                    lsoa: geography.lsoas.locate_code(coordinates),
+                }
+            })
             .collect();
 
     let mut session = globals.db.client.start_session(None).await?;